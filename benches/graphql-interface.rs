@@ -1,4 +1,4 @@
-use api_interface::DatabaseCredentials;
+use api_interface::{DatabaseCredentials, SessionPolicy};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
 fn bench(c: &mut Criterion) {
@@ -6,7 +6,6 @@ fn bench(c: &mut Criterion) {
     let rt = tokio::runtime::Runtime::new().unwrap();
 
     let db_host = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
-    let db_host = db_host.replace("http://", "");
 
     let username = std::env::var("TEST_DATABASE_USERNAME").expect("TEST_DATABASE_USERNAME");
     let password = std::env::var("TEST_DATABASE_PASSWORD").expect("TEST_DATABASE_PASSWORD");
@@ -17,6 +16,9 @@ fn bench(c: &mut Criterion) {
         db_pass: &password,
         db_ns: "benchmarks",
         db: &db_name,
+        migrate: true,
+        session_policy: SessionPolicy::default(),
+        account_token_key: None,
     };
 
     let schema = rt
@@ -1,7 +1,7 @@
 use anyhow::Result;
 use api_database::Client;
 
-use api_core::{api::MutateUsers, User, UserType};
+use api_core::{api::MutateUsers, Role, User, UserType};
 use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
 
 use fake::{
@@ -14,7 +14,6 @@ use uuid::Uuid;
 
 async fn create_client(with_ns: Option<&str>) -> Result<Client> {
     let db_host = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
-    let db_host = db_host.replace("http://", "");
 
     let username = std::env::var("TEST_DATABASE_USERNAME").expect("TEST_DATABASE_USERNAME");
     let password = std::env::var("TEST_DATABASE_PASSWORD").expect("TEST_DATABASE_PASSWORD");
@@ -50,6 +49,8 @@ fn bench(c: &mut Criterion) {
         avatar: None,
         user_type: UserType::Company,
         phone_number: None,
+        role: Role::User,
+        email_verified: None,
         created: OffsetDateTime::now_utc(),
         updated: OffsetDateTime::now_utc(),
     };
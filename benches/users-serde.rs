@@ -1,4 +1,4 @@
-use api_core::{User, UserType};
+use api_core::{Role, User, UserType};
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
 use fake::{
     faker::{
@@ -24,6 +24,8 @@ fn bench(c: &mut Criterion) {
             avatar: None,
             user_type: UserType::Individual,
             phone_number: Some(PhoneNumber(EN).fake()),
+            role: Role::User,
+            email_verified: None,
             created: OffsetDateTime::now_utc(),
             updated: OffsetDateTime::now_utc(),
         })
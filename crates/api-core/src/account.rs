@@ -0,0 +1,88 @@
+use std::fmt::{self, Debug};
+
+#[cfg(feature = "async-graphql")]
+use async_graphql::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::api::{CoreError, QueryAccounts};
+
+/// A user's account with an identity provider
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+#[cfg_attr(feature = "async-graphql", graphql(complex))]
+pub struct Account {
+    pub user_id: Uuid,
+    pub provider: String,
+    pub provider_account_id: String,
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub tokens: AccountTokens,
+}
+
+#[cfg(feature = "async-graphql")]
+#[ComplexObject]
+impl Account {
+    #[graphql(deprecation = "use `providerAccountId`")]
+    async fn account_id(&self) -> &str {
+        &self.provider_account_id
+    }
+}
+
+/// A provider linked to a user, without the account's tokens
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct LinkedAccount {
+    pub provider: String,
+    pub provider_account_id: String,
+    pub linked_at: OffsetDateTime,
+}
+
+/// Object safe access to a user's linked accounts, so `User` can resolve them without knowing
+/// the backend
+#[async_trait::async_trait]
+pub trait AccountLoader: Send + Sync {
+    async fn load_user_accounts(&self, user_id: &Uuid) -> Result<Vec<LinkedAccount>, CoreError>;
+}
+
+#[async_trait::async_trait]
+impl<T: QueryAccounts + Sync> AccountLoader for T {
+    async fn load_user_accounts(&self, user_id: &Uuid) -> Result<Vec<LinkedAccount>, CoreError> {
+        Ok(self.get_user_accounts(user_id).await?.collect())
+    }
+}
+
+/// The OAuth payload issued by the provider when the account was linked or last refreshed.
+/// Backends encrypt the tokens at rest
+#[derive(Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
+#[cfg_attr(feature = "async-graphql", graphql(input_name = "AccountTokensInput"))]
+pub struct AccountTokens {
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    pub expires_at: Option<OffsetDateTime>,
+    pub token_type: Option<String>,
+    pub scope: Option<String>,
+    pub id_token: Option<String>,
+    pub session_state: Option<String>,
+}
+
+impl Debug for AccountTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let redact = |token: &Option<String>| token.as_ref().map(|_| "<redacted>");
+
+        f.debug_struct("AccountTokens")
+            .field("access_token", &redact(&self.access_token))
+            .field("refresh_token", &redact(&self.refresh_token))
+            .field("expires_at", &self.expires_at)
+            .field("token_type", &self.token_type)
+            .field("scope", &self.scope)
+            .field("id_token", &redact(&self.id_token))
+            .field("session_state", &self.session_state)
+            .finish()
+    }
+}
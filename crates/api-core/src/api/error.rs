@@ -10,6 +10,16 @@ pub enum CoreError {
     Other(String),
     #[error(transparent)]
     Uuid(#[from] uuid::Error),
+    /// Unlinking would leave the user with no way to sign in
+    #[error("cannot unlink the user's last sign-in method")]
+    LastSignInMethod,
+    #[error("account provider `{0}` is not registered")]
+    UnknownProvider(String),
+    #[error("account provider `{0}` is disabled")]
+    ProviderDisabled(String),
+    /// The cursor points at an item that no longer exists
+    #[error("cursor `{0}` is no longer valid")]
+    InvalidCursor(uuid::Uuid),
     #[error("unknown core error")]
     Unknown,
     #[error("unreachable logic")]
@@ -1,15 +1,26 @@
 mod error;
+mod page;
 pub use std::fmt::Debug;
 
-use crate::{Session, User};
+use crate::{
+    Account, AccountProvider, AccountTokens, ApiKey, DeletedUser, LinkedAccount, Role, Session,
+    User, UserFilter, UserOrderBy, UserSearch, UserSearchResults, VerificationToken,
+};
 
 pub use error::*;
+pub use page::*;
 use time::OffsetDateTime;
 pub use uuid::Uuid;
 
 #[trait_variant::make(QueryUsers: Send)]
 pub trait LocalQueryUsers {
     async fn get_users(&self) -> Result<impl ExactSizeIterator<Item = User>, CoreError>;
+    async fn get_users_page(
+        &self,
+        page: Page,
+        filter: &UserFilter,
+        order_by: UserOrderBy,
+    ) -> Result<Paged<User>, CoreError>;
     async fn get_user_by_id(&self, id: &Uuid) -> Result<Option<User>, CoreError>;
     async fn get_user_by_email(
         &self,
@@ -20,10 +31,8 @@ pub trait LocalQueryUsers {
         provider: impl AsRef<str> + Send + Debug,
         provider_account_id: impl AsRef<str> + Send + Debug,
     ) -> Result<Option<User>, CoreError>;
-    async fn search(
-        &self,
-        query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = User>, CoreError>;
+    async fn search(&self, search: &UserSearch) -> Result<UserSearchResults, CoreError>;
+    /// Looks up a live session by the token handed to the client, not by its stored digest
     async fn get_session_and_user(
         &self,
         session_token: impl AsRef<str> + Send + Debug,
@@ -34,35 +43,113 @@ pub trait LocalQueryUsers {
 pub trait LocalMutateUsers {
     async fn create_user(&self, user: &User) -> Result<User, CoreError>;
     async fn update_user(&self, id: &Uuid, data: &User) -> Result<Option<User>, CoreError>;
-    async fn delete_user(&self, id: &Uuid) -> Result<Option<User>, CoreError>;
+    /// Deletes the user together with their accounts, sessions and listing relations
+    async fn delete_user(&self, id: &Uuid) -> Result<Option<DeletedUser>, CoreError>;
+    async fn set_user_role(&self, id: &Uuid, role: Role) -> Result<Option<User>, CoreError>;
+    /// Marks the user currently holding `email` as verified
+    async fn set_email_verified(
+        &self,
+        email: impl AsRef<str> + Send + Debug,
+        verified_at: &OffsetDateTime,
+    ) -> Result<Option<User>, CoreError>;
+    /// Creates the user, links their first account and opens a session through `provider` as
+    /// one unit, nothing is stored when any step fails. The session's user and provider are
+    /// taken from the registration, and its token is stored as a digest like in
+    /// [`MutateSessions::create_session`]
+    async fn register_user(
+        &self,
+        user: &User,
+        provider: impl AsRef<str> + Send + Debug + Sync,
+        provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        tokens: &AccountTokens,
+        session: &Session,
+    ) -> Result<(User, Session), CoreError>;
+}
+
+#[trait_variant::make(QueryAccounts: Send)]
+pub trait LocalQueryAccounts {
+    async fn get_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<Account>, CoreError>;
+    /// The user's linked accounts, oldest first
+    async fn get_user_accounts(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = LinkedAccount>, CoreError>;
 }
 
 #[trait_variant::make(MutateAccounts: Send)]
 pub trait LocalMutateAccounts {
+    /// Fails when the account provider is unknown or disabled, or when the account is already
+    /// linked to another user. Linking it to the same user again is a no-op
     async fn link_account(
         &self,
         provider: impl AsRef<str> + Send + Debug + Sync,
         provider_account_id: impl AsRef<str> + Send + Debug + Sync,
         user_id: &Uuid,
+        tokens: &AccountTokens,
     ) -> Result<(), CoreError>;
+    /// Replaces the stored tokens, typically after the provider refreshed them
+    async fn update_account_tokens(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+        tokens: &AccountTokens,
+    ) -> Result<Option<Account>, CoreError>;
+    /// Fails with [`CoreError::LastSignInMethod`] when the user has no other account and no
+    /// verified email to sign in with, unless `force` is set
     async fn unlink_account(
         &self,
         provider: impl AsRef<str> + Send + Debug,
         provider_account_id: impl AsRef<str> + Send + Debug,
+        force: bool,
     ) -> Result<(), CoreError>;
 }
 
+#[trait_variant::make(QueryAccountProviders: Send)]
+pub trait LocalQueryAccountProviders {
+    async fn get_account_providers(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = AccountProvider>, CoreError>;
+    async fn get_account_provider(
+        &self,
+        name: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<AccountProvider>, CoreError>;
+}
+
+#[trait_variant::make(MutateAccountProviders: Send)]
+pub trait LocalMutateAccountProviders {
+    async fn create_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<AccountProvider, CoreError>;
+    /// Updates the provider with the same name. The name itself can't change as accounts and
+    /// sessions are looked up by it
+    async fn update_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<Option<AccountProvider>, CoreError>;
+}
+
 #[trait_variant::make(QuerySessions: Send)]
 pub trait LocalQuerySessions {
+    /// Expired sessions are left out of every lookup, even before they are purged
     async fn get_user_sessions(
         &self,
         user_id: &Uuid,
     ) -> Result<impl ExactSizeIterator<Item = Session>, CoreError>;
+    async fn get_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError>;
 }
 
 #[trait_variant::make(MutateSessions: Send)]
 pub trait LocalMutateSessions {
-    async fn create_session(&self, session: &Session) -> Result<(), CoreError>;
+    /// Fails when the account provider is unknown or disabled. `session_token` is the token
+    /// handed to the client, only its digest is stored and returned. The same goes for the
+    /// tokens sessions are updated and deleted by
+    async fn create_session(&self, session: &Session) -> Result<Session, CoreError>;
+    /// Expired sessions are treated as absent and cannot be extended
     async fn update_session(
         &self,
         id: impl AsRef<str> + Send + Debug,
@@ -70,5 +157,40 @@ pub trait LocalMutateSessions {
     ) -> Result<Option<Session>, CoreError>;
     async fn delete_session(&self, id: impl AsRef<str> + Send + Debug) -> Result<(), CoreError>;
     async fn delete_user_sessions(&self, user_id: &Uuid) -> Result<(), CoreError>;
+    /// Deletes a session by its id rather than its token
+    async fn revoke_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError>;
     async fn delete_expired_sessions(&self) -> Result<(), CoreError>;
+    /// Deletes at most `batch_size` expired sessions, returning how many were removed
+    async fn purge_expired_sessions(&self, batch_size: usize) -> Result<usize, CoreError>;
+}
+
+#[trait_variant::make(QueryApiKeys: Send)]
+pub trait LocalQueryApiKeys {
+    async fn get_api_keys(&self) -> Result<impl ExactSizeIterator<Item = ApiKey>, CoreError>;
+    async fn get_api_key_by_hash(
+        &self,
+        hash: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<ApiKey>, CoreError>;
+}
+
+#[trait_variant::make(MutateApiKeys: Send)]
+pub trait LocalMutateApiKeys {
+    async fn create_api_key(&self, key: &ApiKey) -> Result<ApiKey, CoreError>;
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<Option<ApiKey>, CoreError>;
+}
+
+#[trait_variant::make(MutateVerificationTokens: Send)]
+pub trait LocalMutateVerificationTokens {
+    /// Stores the token under its digest and returns the stored record
+    async fn create_verification_token(
+        &self,
+        token: &VerificationToken,
+    ) -> Result<VerificationToken, CoreError>;
+    /// Deletes the token and returns it if it had not expired. `token` is the raw token as handed
+    /// out. Each token can be used once, even when the same token is presented concurrently
+    async fn use_verification_token(
+        &self,
+        identifier: impl AsRef<str> + Send + Debug,
+        token: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<VerificationToken>, CoreError>;
 }
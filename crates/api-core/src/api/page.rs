@@ -0,0 +1,73 @@
+use uuid::Uuid;
+
+/// Which side of the cursor a page is read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// Items after the cursor, in ascending id order
+    Forward,
+    /// Items before the cursor, in descending id order
+    Backward,
+}
+
+/// A keyset page request. Results are ordered by id, which is time ordered for v7 UUIDs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Page {
+    /// Exclusive bound to read from. `None` starts at the first (or last) item
+    pub cursor: Option<Uuid>,
+    pub limit: usize,
+    pub direction: Direction,
+}
+
+impl Page {
+    pub fn forward(cursor: Option<Uuid>, limit: usize) -> Self {
+        Self {
+            cursor,
+            limit,
+            direction: Direction::Forward,
+        }
+    }
+
+    pub fn backward(cursor: Option<Uuid>, limit: usize) -> Self {
+        Self {
+            cursor,
+            limit,
+            direction: Direction::Backward,
+        }
+    }
+}
+
+/// A page of results in ascending id order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Paged<T> {
+    pub items: Vec<T>,
+    pub has_previous: bool,
+    pub has_next: bool,
+    pub total_count: usize,
+}
+
+impl<T> Paged<T> {
+    /// Builds a page from up to `page.limit + 1` items read in `page.direction` order. The extra
+    /// item only signals that more results exist past the page.
+    pub fn from_overfetch(mut items: Vec<T>, page: &Page, total_count: usize) -> Self {
+        let has_more = items.len() > page.limit;
+        items.truncate(page.limit);
+
+        match page.direction {
+            Direction::Forward => Self {
+                items,
+                has_previous: page.cursor.is_some(),
+                has_next: has_more,
+                total_count,
+            },
+            Direction::Backward => {
+                items.reverse();
+                Self {
+                    items,
+                    has_previous: has_more,
+                    has_next: page.cursor.is_some(),
+                    total_count,
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,42 @@
+#[cfg(feature = "async-graphql")]
+use async_graphql::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+/// A credential other services authenticate with through the `X-Api-Key` header. Only a hash of
+/// the key is stored
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct ApiKey {
+    pub id: Uuid,
+    pub name: String,
+    /// The first characters of the key, enough to tell keys apart
+    pub prefix: String,
+    #[cfg_attr(feature = "async-graphql", graphql(skip))]
+    pub hash: String,
+    pub scopes: Vec<ApiKeyScope>,
+    /// Keys without an expiry stay valid until revoked
+    pub expires_at: Option<OffsetDateTime>,
+    pub created: OffsetDateTime,
+}
+
+impl ApiKey {
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires_at
+            .map(|expires_at| expires_at <= now)
+            .unwrap_or(false)
+    }
+}
+
+/// The part of the API a key can be used for
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum ApiKeyScope {
+    Users,
+    Accounts,
+    Sessions,
+}
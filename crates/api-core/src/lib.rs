@@ -1,28 +1,52 @@
+mod account;
 pub mod api;
+mod api_key;
+#[cfg(feature = "memory")]
+pub mod memory;
+mod search;
+mod verification_token;
+mod viewer;
 
 #[cfg(feature = "async-graphql")]
 use async_graphql::*;
 
 #[cfg(feature = "serde")]
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use uuid::Uuid;
 
 use time::OffsetDateTime;
 
+pub use account::*;
+pub use api_key::*;
+pub use search::*;
+pub use verification_token::*;
+pub use viewer::*;
+
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
-#[cfg_attr(feature = "async-graphql", graphql(input_name = "UserInput"))]
+#[cfg_attr(feature = "async-graphql", graphql(input_name = "UserInput", complex))]
 pub struct User {
     #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
     pub id: Uuid,
     pub username: String,
+    #[cfg_attr(feature = "async-graphql", graphql(skip_output))]
     pub email: String,
     pub name: Option<String>,
     pub avatar: Option<String>,
     #[cfg_attr(feature = "serde", serde(rename = "type"))]
     pub user_type: UserType,
+    #[cfg_attr(feature = "async-graphql", graphql(skip_output))]
     pub phone_number: Option<String>,
+    /// When the user proved they control `email`. Cleared whenever the email changes
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip))]
+    pub email_verified: Option<OffsetDateTime>,
+    /// Assigned through `setUserRole`, never from user input
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub role: Role,
     #[cfg_attr(
         feature = "async-graphql",
         graphql(default_with = "default_date_time()")
@@ -40,6 +64,69 @@ fn default_date_time() -> OffsetDateTime {
     OffsetDateTime::now_utc()
 }
 
+#[cfg(feature = "async-graphql")]
+#[ComplexObject]
+impl User {
+    /// Only visible to the user and to staff
+    async fn email(&self, ctx: &Context<'_>) -> Option<&str> {
+        Viewer::from_context(ctx)
+            .can_view_private(&self.id)
+            .then_some(self.email.as_str())
+    }
+
+    /// Only visible to the user and to staff
+    async fn phone_number(&self, ctx: &Context<'_>) -> Option<&str> {
+        Viewer::from_context(ctx)
+            .can_view_private(&self.id)
+            .then_some(self.phone_number.as_deref())
+            .flatten()
+    }
+
+    /// Only visible to the user and to staff
+    async fn email_verified(&self, ctx: &Context<'_>) -> Option<OffsetDateTime> {
+        Viewer::from_context(ctx)
+            .can_view_private(&self.id)
+            .then_some(self.email_verified)
+            .flatten()
+    }
+
+    /// Only visible to the user and to staff
+    async fn accounts(&self, ctx: &Context<'_>) -> Result<Option<Vec<LinkedAccount>>> {
+        if !Viewer::from_context(ctx).can_view_private(&self.id) {
+            return Ok(None);
+        }
+
+        let loader = ctx.data::<std::sync::Arc<dyn AccountLoader>>()?;
+        Ok(Some(loader.load_user_accounts(&self.id).await?))
+    }
+}
+
+/// A deleted user and what was removed along with them
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct DeletedUser {
+    pub user: User,
+    pub accounts: Vec<LinkedAccount>,
+    /// Number of sessions that were ended
+    pub sessions: usize,
+    /// Number of listings the user no longer sells
+    pub listings: usize,
+}
+
+/// What a user is allowed to do beyond managing their own data
+#[derive(Debug, Default, PartialEq, PartialOrd, Ord, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum Role {
+    #[default]
+    User,
+    Support,
+    Admin,
+    /// Other services acting on behalf of users
+    Service,
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(Enum))]
@@ -48,18 +135,98 @@ pub enum UserType {
     Company,
 }
 
+/// Conditions a user must meet to be listed. Unset fields don't filter
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(InputObject))]
+pub struct UserFilter {
+    pub user_type: Option<UserType>,
+    pub created_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+    pub updated_after: Option<OffsetDateTime>,
+    pub updated_before: Option<OffsetDateTime>,
+    pub has_phone_number: Option<bool>,
+    pub has_avatar: Option<bool>,
+}
+
+impl UserFilter {
+    pub fn matches(&self, user: &User) -> bool {
+        self.user_type.map(|t| user.user_type == t).unwrap_or(true)
+            && self.created_after.map(|d| user.created > d).unwrap_or(true)
+            && self
+                .created_before
+                .map(|d| user.created < d)
+                .unwrap_or(true)
+            && self.updated_after.map(|d| user.updated > d).unwrap_or(true)
+            && self
+                .updated_before
+                .map(|d| user.updated < d)
+                .unwrap_or(true)
+            && self
+                .has_phone_number
+                .map(|has| user.phone_number.is_some() == has)
+                .unwrap_or(true)
+            && self
+                .has_avatar
+                .map(|has| user.avatar.is_some() == has)
+                .unwrap_or(true)
+    }
+}
+
+/// Field users are listed by. Ties are broken by id
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum UserOrderBy {
+    #[default]
+    Created,
+    Updated,
+    Username,
+}
+
 #[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
 #[cfg_attr(feature = "async-graphql", graphql(input_name = "SessionInput"))]
 pub struct Session {
+    /// Opaque handle for listing and revoking a session without knowing its token
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub id: Uuid,
     pub expires_at: OffsetDateTime,
+    /// Minted by the API when the session is created. Storage hashes it with
+    /// [`hash_session_token`] and only ever holds the digest, which is never exposed
+    #[cfg_attr(feature = "async-graphql", graphql(skip))]
     pub session_token: String,
     pub account_provider: AccountProvider,
     pub user_id: Uuid,
+    /// When the session is due to be extended, if the backend extends sessions on use
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub refresh_at: Option<OffsetDateTime>,
+    /// User agent of the device that signed in, as reported by the client
+    pub user_agent: Option<String>,
+    /// Address of the device that signed in, as reported by the client
+    pub ip_address: Option<String>,
+    /// Name the user gave the device
+    pub device_label: Option<String>,
+    #[cfg_attr(
+        feature = "async-graphql",
+        graphql(default_with = "default_date_time()")
+    )]
+    pub created: OffsetDateTime,
+    /// Last time the session was used to authenticate
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub last_seen_at: Option<OffsetDateTime>,
+}
+
+/// The digest sessions are stored and looked up by, so a copy of the database cannot be used to
+/// sign in
+pub fn hash_session_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
 }
 
-#[derive(Default, Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
+/// A registered identity provider. Accounts can only be linked and sessions created through
+/// enabled providers. As an input only `name` is read
+#[derive(Debug, PartialEq, PartialOrd, Ord, Eq, Clone)]
 #[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 #[cfg_attr(feature = "async-graphql", derive(InputObject, SimpleObject))]
 #[cfg_attr(feature = "async-graphql", graphql(input_name = "AccountInput"))]
@@ -67,6 +234,50 @@ pub struct AccountProvider {
     #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
     pub id: Uuid,
     pub name: String,
+    /// Name shown to users on sign in pages
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub display_name: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub icon_url: Option<String>,
+    #[cfg_attr(feature = "serde", serde(default, rename = "type"))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub provider_type: ProviderType,
+    #[cfg_attr(feature = "serde", serde(default = "enabled_by_default"))]
+    #[cfg_attr(feature = "async-graphql", graphql(skip_input))]
+    pub enabled: bool,
+}
+
+impl Default for AccountProvider {
+    fn default() -> Self {
+        Self {
+            id: Uuid::default(),
+            name: String::default(),
+            display_name: None,
+            icon_url: None,
+            provider_type: ProviderType::default(),
+            enabled: true,
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+fn enabled_by_default() -> bool {
+    true
+}
+
+/// How a provider authenticates users
+#[derive(Debug, Default, PartialEq, PartialOrd, Ord, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+#[cfg_attr(feature = "async-graphql", derive(Enum))]
+pub enum ProviderType {
+    #[default]
+    Oauth,
+    Oidc,
+    Email,
+    Credentials,
 }
 
 pub mod reexports {
@@ -0,0 +1,919 @@
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    fmt::Debug,
+    sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use time::OffsetDateTime;
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        CoreError, Direction, MutateAccountProviders, MutateAccounts, MutateApiKeys,
+        MutateSessions, MutateUsers, MutateVerificationTokens, Page, Paged, QueryAccountProviders,
+        QueryAccounts, QueryApiKeys, QuerySessions, QueryUsers,
+    },
+    hash_session_token, hash_verification_token, Account, AccountProvider, AccountTokens, ApiKey,
+    DeletedUser, FacetCount, LinkedAccount, Role, Session, User, UserFilter, UserHighlight,
+    UserOrderBy, UserSearch, UserSearchFacets, UserSearchHit, UserSearchResults, VerificationToken,
+};
+
+/// An in-memory store implementing every `api` trait. Intended for tests and local development
+/// where a database is not available. Cloning the store shares the underlying data.
+#[derive(Clone, Debug, Default)]
+pub struct MemoryStore {
+    inner: Arc<RwLock<Store>>,
+}
+
+#[derive(Debug, Default)]
+struct Store {
+    users: BTreeMap<Uuid, User>,
+    providers: Vec<AccountProvider>,
+    accounts: Vec<AccountLink>,
+    sessions: Vec<Session>,
+    api_keys: BTreeMap<Uuid, ApiKey>,
+    verification_tokens: Vec<VerificationToken>,
+}
+
+#[derive(Debug, Clone)]
+struct AccountLink {
+    user_id: Uuid,
+    provider_id: Uuid,
+    provider_account_id: String,
+    tokens: AccountTokens,
+    linked_at: OffsetDateTime,
+}
+
+impl Store {
+    fn provider_by_name(&self, name: &str) -> Option<&AccountProvider> {
+        self.providers.iter().find(|provider| provider.name == name)
+    }
+
+    /// The provider accounts can be linked and sessions created through
+    fn enabled_provider(&self, name: &str) -> Result<&AccountProvider, CoreError> {
+        match self.provider_by_name(name) {
+            Some(provider) if provider.enabled => Ok(provider),
+            Some(_) => Err(CoreError::ProviderDisabled(name.to_owned())),
+            None => Err(CoreError::UnknownProvider(name.to_owned())),
+        }
+    }
+
+    fn account_mut(
+        &mut self,
+        provider: &str,
+        provider_account_id: &str,
+    ) -> Option<(String, &mut AccountLink)> {
+        let provider = self.provider_by_name(provider)?.clone();
+
+        self.accounts
+            .iter_mut()
+            .find(|account| {
+                account.provider_id == provider.id
+                    && account.provider_account_id == provider_account_id
+            })
+            .map(|account| (provider.name, account))
+    }
+
+    fn linked_accounts(&self, user_id: &Uuid) -> Vec<LinkedAccount> {
+        // links are pushed in the order they were made
+        self.accounts
+            .iter()
+            .filter(|account| &account.user_id == user_id)
+            .filter_map(|account| {
+                let provider = self
+                    .providers
+                    .iter()
+                    .find(|provider| provider.id == account.provider_id)?;
+
+                Some(LinkedAccount {
+                    provider: provider.name.clone(),
+                    provider_account_id: account.provider_account_id.clone(),
+                    linked_at: account.linked_at,
+                })
+            })
+            .collect()
+    }
+
+    fn check_email(&self, email: &str, id: &Uuid) -> Result<(), CoreError> {
+        if self
+            .users
+            .values()
+            .any(|user| user.email == email && &user.id != id)
+        {
+            Err(CoreError::Database(format!(
+                "email `{email}` is already in use"
+            )))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl MemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> Result<RwLockReadGuard<'_, Store>, CoreError> {
+        self.inner
+            .read()
+            .map_err(|e| CoreError::Other(e.to_string()))
+    }
+
+    fn write(&self) -> Result<RwLockWriteGuard<'_, Store>, CoreError> {
+        self.inner
+            .write()
+            .map_err(|e| CoreError::Other(e.to_string()))
+    }
+}
+
+impl QueryUsers for MemoryStore {
+    async fn get_users(&self) -> Result<impl ExactSizeIterator<Item = User>, CoreError> {
+        let store = self.read()?;
+
+        Ok(store
+            .users
+            .values()
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    async fn get_users_page(
+        &self,
+        page: Page,
+        filter: &UserFilter,
+        order_by: UserOrderBy,
+    ) -> Result<Paged<User>, CoreError> {
+        let store = self.read()?;
+
+        let compare = |a: &User, b: &User| {
+            match order_by {
+                UserOrderBy::Created => a.created.cmp(&b.created),
+                UserOrderBy::Updated => a.updated.cmp(&b.updated),
+                UserOrderBy::Username => a.username.cmp(&b.username),
+            }
+            .then(a.id.cmp(&b.id))
+        };
+
+        let mut users: Vec<_> = store
+            .users
+            .values()
+            .filter(|user| filter.matches(user))
+            .collect();
+        let total_count = users.len();
+
+        if let Some(cursor) = page.cursor {
+            let anchor = store
+                .users
+                .get(&cursor)
+                .ok_or(CoreError::InvalidCursor(cursor))?;
+            let side = match page.direction {
+                Direction::Forward => Ordering::Greater,
+                Direction::Backward => Ordering::Less,
+            };
+            users.retain(|user| compare(user, anchor) == side);
+        }
+
+        users.sort_by(|a, b| match page.direction {
+            Direction::Forward => compare(a, b),
+            Direction::Backward => compare(b, a),
+        });
+
+        let users = users
+            .into_iter()
+            .take(page.limit.saturating_add(1))
+            .cloned()
+            .collect();
+
+        Ok(Paged::from_overfetch(users, &page, total_count))
+    }
+
+    async fn get_user_by_id(&self, id: &Uuid) -> Result<Option<User>, CoreError> {
+        let store = self.read()?;
+
+        Ok(store.users.get(id).cloned())
+    }
+
+    async fn get_user_by_email(
+        &self,
+        email: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<User>, CoreError> {
+        let store = self.read()?;
+        let email = email.as_ref();
+
+        Ok(store
+            .users
+            .values()
+            .find(|user| user.email == email)
+            .cloned())
+    }
+
+    async fn get_user_by_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<User>, CoreError> {
+        let store = self.read()?;
+        let provider_account_id = provider_account_id.as_ref();
+
+        let user = store
+            .provider_by_name(provider.as_ref())
+            .and_then(|provider| {
+                store
+                    .accounts
+                    .iter()
+                    .find(|account| {
+                        account.provider_id == provider.id
+                            && account.provider_account_id == provider_account_id
+                    })
+                    .and_then(|account| store.users.get(&account.user_id))
+            });
+
+        Ok(user.cloned())
+    }
+
+    async fn search(&self, search: &UserSearch) -> Result<UserSearchResults, CoreError> {
+        let store = self.read()?;
+        let query = search.query.to_lowercase();
+
+        let matches = |value: &str| value.to_lowercase().contains(&query);
+
+        let mut found: Vec<&User> = store
+            .users
+            .values()
+            .filter(|user| search.filter.matches(user))
+            .filter(|user| matches(&user.username) || user.name.as_deref().is_some_and(matches))
+            .collect();
+        // there is no relevance to rank by, so hits are listed oldest first
+        found.sort_by_key(|user| (user.created, user.id));
+
+        let mut user_type = BTreeMap::new();
+        for user in &found {
+            *user_type
+                .entry(format!("{:?}", user.user_type))
+                .or_insert(0) += 1;
+        }
+
+        let hits = found
+            .iter()
+            .skip(search.offset)
+            .take(search.limit)
+            .map(|user| UserSearchHit {
+                user: (*user).clone(),
+                highlight: UserHighlight {
+                    username: highlight(&user.username, &query)
+                        .unwrap_or_else(|| user.username.clone()),
+                    name: user
+                        .name
+                        .as_deref()
+                        .map(|name| highlight(name, &query).unwrap_or_else(|| name.to_owned())),
+                },
+                matched_terms: vec![query.clone()],
+            })
+            .collect();
+
+        Ok(UserSearchResults {
+            hits,
+            total_hits: found.len(),
+            offset: search.offset,
+            limit: search.limit,
+            facets: UserSearchFacets {
+                user_type: user_type
+                    .into_iter()
+                    .map(|(value, count)| FacetCount { value, count })
+                    .collect(),
+            },
+        })
+    }
+
+    async fn get_session_and_user(
+        &self,
+        session_token: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<(User, Session)>, CoreError> {
+        let mut store = self.write()?;
+        let session_token = hash_session_token(session_token.as_ref());
+        let now = OffsetDateTime::now_utc();
+
+        let Some(session) = store
+            .sessions
+            .iter_mut()
+            .find(|session| session.session_token == session_token && session.expires_at > now)
+        else {
+            return Ok(None);
+        };
+        session.last_seen_at = Some(now);
+        let session = session.clone();
+
+        let res = store
+            .users
+            .get(&session.user_id)
+            .map(|user| (user.clone(), session));
+
+        Ok(res)
+    }
+}
+
+impl MutateUsers for MemoryStore {
+    async fn create_user(&self, user: &User) -> Result<User, CoreError> {
+        let mut store = self.write()?;
+        let id = Uuid::now_v7();
+        store.check_email(&user.email, &id)?;
+
+        let now = OffsetDateTime::now_utc();
+        let user = User {
+            id,
+            created: now,
+            updated: now,
+            ..user.clone()
+        };
+        store.users.insert(id, user.clone());
+
+        Ok(user)
+    }
+
+    async fn update_user(&self, id: &Uuid, data: &User) -> Result<Option<User>, CoreError> {
+        let mut store = self.write()?;
+        store.check_email(&data.email, id)?;
+
+        let user = store.users.get_mut(id).map(|user| {
+            *user = User {
+                id: user.id,
+                role: user.role,
+                email_verified: user.email_verified.filter(|_| user.email == data.email),
+                created: user.created,
+                updated: OffsetDateTime::now_utc(),
+                ..data.clone()
+            };
+            user.clone()
+        });
+
+        Ok(user)
+    }
+
+    async fn delete_user(&self, id: &Uuid) -> Result<Option<DeletedUser>, CoreError> {
+        let mut store = self.write()?;
+        let Some(user) = store.users.remove(id) else {
+            return Ok(None);
+        };
+
+        let accounts = store.linked_accounts(id);
+        store.accounts.retain(|account| &account.user_id != id);
+        let before = store.sessions.len();
+        store.sessions.retain(|session| &session.user_id != id);
+        let sessions = before - store.sessions.len();
+
+        // listings are not kept in memory
+        Ok(Some(DeletedUser {
+            user,
+            accounts,
+            sessions,
+            listings: 0,
+        }))
+    }
+
+    async fn set_user_role(&self, id: &Uuid, role: Role) -> Result<Option<User>, CoreError> {
+        let mut store = self.write()?;
+
+        Ok(store.users.get_mut(id).map(|user| {
+            user.role = role;
+            user.updated = OffsetDateTime::now_utc();
+            user.clone()
+        }))
+    }
+
+    async fn set_email_verified(
+        &self,
+        email: impl AsRef<str> + Send + Debug,
+        verified_at: &OffsetDateTime,
+    ) -> Result<Option<User>, CoreError> {
+        let mut store = self.write()?;
+        let email = email.as_ref();
+
+        Ok(store
+            .users
+            .values_mut()
+            .find(|user| user.email == email)
+            .map(|user| {
+                user.email_verified = Some(*verified_at);
+                user.updated = OffsetDateTime::now_utc();
+                user.clone()
+            }))
+    }
+
+    async fn register_user(
+        &self,
+        user: &User,
+        provider: impl AsRef<str> + Send + Debug + Sync,
+        provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        tokens: &AccountTokens,
+        session: &Session,
+    ) -> Result<(User, Session), CoreError> {
+        // every check runs before anything is inserted so a failure leaves the store untouched
+        let mut store = self.write()?;
+        let provider = provider.as_ref();
+        let provider_account_id = provider_account_id.as_ref();
+
+        let account_provider = store.enabled_provider(provider)?.clone();
+        if store.accounts.iter().any(|account| {
+            account.provider_id == account_provider.id
+                && account.provider_account_id == provider_account_id
+        }) {
+            return Err(CoreError::Database(format!(
+                "account `{provider_account_id}` of `{provider}` is already linked"
+            )));
+        }
+
+        let id = Uuid::now_v7();
+        store.check_email(&user.email, &id)?;
+
+        let now = OffsetDateTime::now_utc();
+        let user = User {
+            id,
+            created: now,
+            updated: now,
+            ..user.clone()
+        };
+        let session = Session {
+            id: Uuid::now_v7(),
+            user_id: id,
+            account_provider,
+            session_token: hash_session_token(&session.session_token),
+            created: now,
+            last_seen_at: None,
+            ..session.clone()
+        };
+
+        store.users.insert(id, user.clone());
+        store.accounts.push(AccountLink {
+            user_id: id,
+            provider_id: session.account_provider.id,
+            provider_account_id: provider_account_id.to_owned(),
+            tokens: tokens.clone(),
+            linked_at: now,
+        });
+        store.sessions.push(session.clone());
+
+        Ok((user, session))
+    }
+}
+
+impl AccountLink {
+    fn to_account(&self, provider: String) -> Account {
+        Account {
+            user_id: self.user_id,
+            provider,
+            provider_account_id: self.provider_account_id.clone(),
+            tokens: self.tokens.clone(),
+        }
+    }
+}
+
+impl QueryAccounts for MemoryStore {
+    async fn get_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<Account>, CoreError> {
+        let store = self.read()?;
+        let provider_account_id = provider_account_id.as_ref();
+
+        Ok(store
+            .provider_by_name(provider.as_ref())
+            .and_then(|provider| {
+                store
+                    .accounts
+                    .iter()
+                    .find(|account| {
+                        account.provider_id == provider.id
+                            && account.provider_account_id == provider_account_id
+                    })
+                    .map(|account| account.to_account(provider.name.clone()))
+            }))
+    }
+
+    async fn get_user_accounts(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = LinkedAccount>, CoreError> {
+        Ok(self.read()?.linked_accounts(user_id).into_iter())
+    }
+}
+
+impl MutateAccounts for MemoryStore {
+    async fn link_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug + Sync,
+        provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        user_id: &Uuid,
+        tokens: &AccountTokens,
+    ) -> Result<(), CoreError> {
+        let mut store = self.write()?;
+        let provider = provider.as_ref();
+        let provider_account_id = provider_account_id.as_ref();
+
+        let provider_id = store.enabled_provider(provider)?.id;
+
+        if let Some(account) = store.accounts.iter().find(|account| {
+            account.provider_id == provider_id && account.provider_account_id == provider_account_id
+        }) {
+            // relinking to the same user is a no-op
+            return if &account.user_id == user_id {
+                Ok(())
+            } else {
+                Err(CoreError::Database(format!(
+                    "account `{provider_account_id}` of `{provider}` is already linked"
+                )))
+            };
+        }
+
+        if store
+            .accounts
+            .iter()
+            .any(|account| account.provider_id == provider_id && &account.user_id == user_id)
+        {
+            return Err(CoreError::Database(format!(
+                "user `{user_id}` is already linked to `{provider}`"
+            )));
+        }
+
+        store.accounts.push(AccountLink {
+            user_id: *user_id,
+            provider_id,
+            provider_account_id: provider_account_id.to_owned(),
+            tokens: tokens.clone(),
+            linked_at: OffsetDateTime::now_utc(),
+        });
+
+        Ok(())
+    }
+
+    async fn update_account_tokens(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+        tokens: &AccountTokens,
+    ) -> Result<Option<Account>, CoreError> {
+        let mut store = self.write()?;
+
+        Ok(store
+            .account_mut(provider.as_ref(), provider_account_id.as_ref())
+            .map(|(provider, account)| {
+                account.tokens = tokens.clone();
+                account.to_account(provider)
+            }))
+    }
+
+    async fn unlink_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+        force: bool,
+    ) -> Result<(), CoreError> {
+        let mut store = self.write()?;
+        let provider_account_id = provider_account_id.as_ref();
+
+        let Some(provider_id) = store.provider_by_name(provider.as_ref()).map(|p| p.id) else {
+            return Ok(());
+        };
+        let Some(user_id) = store
+            .accounts
+            .iter()
+            .find(|account| {
+                account.provider_id == provider_id
+                    && account.provider_account_id == provider_account_id
+            })
+            .map(|account| account.user_id)
+        else {
+            return Ok(());
+        };
+
+        let email_verified = store
+            .users
+            .get(&user_id)
+            .is_some_and(|user| user.email_verified.is_some());
+        let linked = store
+            .accounts
+            .iter()
+            .filter(|account| account.user_id == user_id)
+            .count();
+        if !force && !email_verified && linked <= 1 {
+            return Err(CoreError::LastSignInMethod);
+        }
+
+        store.accounts.retain(|account| {
+            account.provider_id != provider_id || account.provider_account_id != provider_account_id
+        });
+
+        Ok(())
+    }
+}
+
+impl QueryAccountProviders for MemoryStore {
+    async fn get_account_providers(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = AccountProvider>, CoreError> {
+        let store = self.read()?;
+
+        Ok(store.providers.clone().into_iter())
+    }
+
+    async fn get_account_provider(
+        &self,
+        name: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<AccountProvider>, CoreError> {
+        let store = self.read()?;
+
+        Ok(store.provider_by_name(name.as_ref()).cloned())
+    }
+}
+
+impl MutateAccountProviders for MemoryStore {
+    async fn create_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<AccountProvider, CoreError> {
+        let mut store = self.write()?;
+
+        if store.provider_by_name(&provider.name).is_some() {
+            return Err(CoreError::Database(format!(
+                "account provider `{}` already exists",
+                provider.name
+            )));
+        }
+
+        let provider = AccountProvider {
+            id: Uuid::now_v7(),
+            ..provider.clone()
+        };
+        store.providers.push(provider.clone());
+
+        Ok(provider)
+    }
+
+    async fn update_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<Option<AccountProvider>, CoreError> {
+        let mut store = self.write()?;
+
+        let updated = store
+            .providers
+            .iter_mut()
+            .find(|existing| existing.name == provider.name)
+            .map(|existing| {
+                *existing = AccountProvider {
+                    id: existing.id,
+                    ..provider.clone()
+                };
+                existing.clone()
+            });
+
+        // sessions hold a copy of their provider
+        if let Some(updated) = &updated {
+            for session in store
+                .sessions
+                .iter_mut()
+                .filter(|session| session.account_provider.id == updated.id)
+            {
+                session.account_provider = updated.clone();
+            }
+        }
+
+        Ok(updated)
+    }
+}
+
+impl QuerySessions for MemoryStore {
+    async fn get_user_sessions(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = Session>, CoreError> {
+        let store = self.read()?;
+        let now = OffsetDateTime::now_utc();
+
+        Ok(store
+            .sessions
+            .iter()
+            .filter(|session| &session.user_id == user_id && session.expires_at > now)
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    async fn get_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError> {
+        let store = self.read()?;
+        let now = OffsetDateTime::now_utc();
+
+        Ok(store
+            .sessions
+            .iter()
+            .find(|session| &session.id == id && session.expires_at > now)
+            .cloned())
+    }
+}
+
+impl MutateSessions for MemoryStore {
+    async fn create_session(&self, session: &Session) -> Result<Session, CoreError> {
+        let mut store = self.write()?;
+
+        let session = Session {
+            id: Uuid::now_v7(),
+            account_provider: store
+                .enabled_provider(&session.account_provider.name)?
+                .clone(),
+            session_token: hash_session_token(&session.session_token),
+            created: OffsetDateTime::now_utc(),
+            last_seen_at: None,
+            ..session.clone()
+        };
+        store.sessions.push(session.clone());
+
+        Ok(session)
+    }
+
+    async fn update_session(
+        &self,
+        id: impl AsRef<str> + Send + Debug,
+        expires_at: &OffsetDateTime,
+    ) -> Result<Option<Session>, CoreError> {
+        let mut store = self.write()?;
+        let session_token = hash_session_token(id.as_ref());
+        let now = OffsetDateTime::now_utc();
+
+        let session = store
+            .sessions
+            .iter_mut()
+            .find(|session| session.session_token == session_token && session.expires_at > now)
+            .map(|session| {
+                session.expires_at = *expires_at;
+                session.clone()
+            });
+
+        Ok(session)
+    }
+
+    async fn delete_session(&self, id: impl AsRef<str> + Send + Debug) -> Result<(), CoreError> {
+        let mut store = self.write()?;
+        let session_token = hash_session_token(id.as_ref());
+
+        store
+            .sessions
+            .retain(|session| session.session_token != session_token);
+
+        Ok(())
+    }
+
+    async fn delete_user_sessions(&self, user_id: &Uuid) -> Result<(), CoreError> {
+        let mut store = self.write()?;
+
+        store.sessions.retain(|session| &session.user_id != user_id);
+
+        Ok(())
+    }
+
+    async fn revoke_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError> {
+        let mut store = self.write()?;
+
+        let index = store.sessions.iter().position(|session| &session.id == id);
+
+        Ok(index.map(|index| store.sessions.remove(index)))
+    }
+
+    async fn delete_expired_sessions(&self) -> Result<(), CoreError> {
+        let mut store = self.write()?;
+        let now = OffsetDateTime::now_utc();
+
+        store.sessions.retain(|session| session.expires_at > now);
+
+        Ok(())
+    }
+
+    async fn purge_expired_sessions(&self, batch_size: usize) -> Result<usize, CoreError> {
+        let mut store = self.write()?;
+        let now = OffsetDateTime::now_utc();
+
+        let mut purged = 0;
+        store.sessions.retain(|session| {
+            let purge = session.expires_at <= now && purged < batch_size;
+            if purge {
+                purged += 1;
+            }
+            !purge
+        });
+
+        Ok(purged)
+    }
+}
+
+impl QueryApiKeys for MemoryStore {
+    async fn get_api_keys(&self) -> Result<impl ExactSizeIterator<Item = ApiKey>, CoreError> {
+        let store = self.read()?;
+
+        Ok(store
+            .api_keys
+            .values()
+            .cloned()
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+
+    async fn get_api_key_by_hash(
+        &self,
+        hash: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<ApiKey>, CoreError> {
+        let store = self.read()?;
+        let hash = hash.as_ref();
+
+        Ok(store
+            .api_keys
+            .values()
+            .find(|key| key.hash == hash)
+            .cloned())
+    }
+}
+
+impl MutateApiKeys for MemoryStore {
+    async fn create_api_key(&self, key: &ApiKey) -> Result<ApiKey, CoreError> {
+        let mut store = self.write()?;
+
+        if store
+            .api_keys
+            .values()
+            .any(|existing| existing.hash == key.hash)
+        {
+            return Err(CoreError::Database(String::from("api key already exists")));
+        }
+
+        let id = Uuid::now_v7();
+        let key = ApiKey {
+            id,
+            created: OffsetDateTime::now_utc(),
+            ..key.clone()
+        };
+        store.api_keys.insert(id, key.clone());
+
+        Ok(key)
+    }
+
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<Option<ApiKey>, CoreError> {
+        let mut store = self.write()?;
+
+        Ok(store.api_keys.remove(id))
+    }
+}
+
+impl MutateVerificationTokens for MemoryStore {
+    async fn create_verification_token(
+        &self,
+        token: &VerificationToken,
+    ) -> Result<VerificationToken, CoreError> {
+        let mut store = self.write()?;
+        let token = VerificationToken {
+            token: hash_verification_token(&token.token),
+            ..token.clone()
+        };
+
+        store.verification_tokens.push(token.clone());
+
+        Ok(token)
+    }
+
+    async fn use_verification_token(
+        &self,
+        identifier: impl AsRef<str> + Send + Debug,
+        token: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<VerificationToken>, CoreError> {
+        let mut store = self.write()?;
+        let identifier = identifier.as_ref();
+        let token = hash_verification_token(token.as_ref());
+
+        let index = store
+            .verification_tokens
+            .iter()
+            .position(|stored| stored.identifier == identifier && stored.token == token);
+
+        Ok(index
+            .map(|index| store.verification_tokens.remove(index))
+            .filter(|stored| !stored.is_expired(OffsetDateTime::now_utc())))
+    }
+}
+
+/// Wraps the first case-insensitive occurrence of a lowercase `query` in `<em>` tags
+fn highlight(value: &str, query: &str) -> Option<String> {
+    let lower = value.to_lowercase();
+    // lowercasing may change byte offsets outside ASCII
+    if query.is_empty() || lower.len() != value.len() {
+        return None;
+    }
+    let start = lower.find(query)?;
+    let end = start + query.len();
+
+    Some(format!(
+        "{}<em>{}</em>{}",
+        &value[..start],
+        &value[start..end],
+        &value[end..]
+    ))
+}
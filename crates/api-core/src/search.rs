@@ -0,0 +1,76 @@
+#[cfg(feature = "async-graphql")]
+use async_graphql::*;
+use time::OffsetDateTime;
+
+use crate::{User, UserType};
+
+/// Conditions search hits must meet. Unset fields don't filter
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(InputObject))]
+pub struct UserSearchFilter {
+    pub user_type: Option<UserType>,
+    pub created_after: Option<OffsetDateTime>,
+    pub created_before: Option<OffsetDateTime>,
+}
+
+impl UserSearchFilter {
+    pub fn matches(&self, user: &User) -> bool {
+        self.user_type.map(|t| user.user_type == t).unwrap_or(true)
+            && self.created_after.map(|d| user.created > d).unwrap_or(true)
+            && self
+                .created_before
+                .map(|d| user.created < d)
+                .unwrap_or(true)
+    }
+}
+
+/// A full-text search request. Hits are ranked by relevance and paged by offset
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct UserSearch {
+    pub query: String,
+    pub filter: UserSearchFilter,
+    pub offset: usize,
+    pub limit: usize,
+}
+
+/// Searchable fields with the matched parts wrapped in `<em>` tags
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct UserHighlight {
+    pub username: String,
+    pub name: Option<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct UserSearchHit {
+    pub user: User,
+    pub highlight: UserHighlight,
+    /// Parts of the searchable fields that matched the query
+    pub matched_terms: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Number of hits per value, counted over every hit rather than the returned page
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct UserSearchFacets {
+    pub user_type: Vec<FacetCount>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct UserSearchResults {
+    pub hits: Vec<UserSearchHit>,
+    /// Number of hits for the query, which may be an estimate
+    pub total_hits: usize,
+    pub offset: usize,
+    pub limit: usize,
+    pub facets: UserSearchFacets,
+}
@@ -1,10 +1,12 @@
 use std::fmt::Debug;
 
+use time::OffsetDateTime;
 use uuid::Uuid;
 
 use crate::{
-    api::{CoreError, LocalMutateUsers, LocalQueryUsers, MutateUsers, QueryUsers},
-    Session, User,
+    api::{CoreError, LocalMutateUsers, LocalQueryUsers, MutateUsers, Page, Paged, QueryUsers},
+    AccountTokens, DeletedUser, Role, Session, User, UserFilter, UserOrderBy, UserSearch,
+    UserSearchFacets, UserSearchResults,
 };
 
 pub struct SampleDb;
@@ -15,6 +17,15 @@ impl LocalQueryUsers for SampleDb {
         Ok([].into_iter())
     }
 
+    async fn get_users_page(
+        &self,
+        page: Page,
+        _filter: &UserFilter,
+        _order_by: UserOrderBy,
+    ) -> Result<Paged<User>, CoreError> {
+        Ok(Paged::from_overfetch(vec![], &page, 0))
+    }
+
     async fn get_user_by_id(&self, _id: &Uuid) -> Result<Option<User>, CoreError> {
         Ok(None)
     }
@@ -26,11 +37,14 @@ impl LocalQueryUsers for SampleDb {
         Ok(None)
     }
 
-    async fn search(
-        &self,
-        _query: impl AsRef<str> + Debug + Send,
-    ) -> Result<impl ExactSizeIterator<Item = User>, CoreError> {
-        Ok([].into_iter())
+    async fn search(&self, search: &UserSearch) -> Result<UserSearchResults, CoreError> {
+        Ok(UserSearchResults {
+            hits: vec![],
+            total_hits: 0,
+            offset: search.offset,
+            limit: search.limit,
+            facets: UserSearchFacets::default(),
+        })
     }
 
     async fn get_user_by_account(
@@ -58,9 +72,32 @@ impl LocalMutateUsers for SampleDb {
         Ok(None)
     }
 
-    async fn delete_user(&self, _id: &Uuid) -> Result<Option<User>, CoreError> {
+    async fn delete_user(&self, _id: &Uuid) -> Result<Option<DeletedUser>, CoreError> {
         Ok(None)
     }
+
+    async fn set_user_role(&self, _id: &Uuid, _role: Role) -> Result<Option<User>, CoreError> {
+        Ok(None)
+    }
+
+    async fn set_email_verified(
+        &self,
+        _email: impl AsRef<str> + Send + Debug,
+        _verified_at: &OffsetDateTime,
+    ) -> Result<Option<User>, CoreError> {
+        Ok(None)
+    }
+
+    async fn register_user(
+        &self,
+        user: &User,
+        _provider: impl AsRef<str> + Send + Debug + Sync,
+        _provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        _tokens: &AccountTokens,
+        session: &Session,
+    ) -> Result<(User, Session), CoreError> {
+        Ok((user.clone(), session.clone()))
+    }
 }
 
 impl MutateUsers for SampleDbSend {
@@ -72,9 +109,32 @@ impl MutateUsers for SampleDbSend {
         Ok(Some(data.to_owned()))
     }
 
-    async fn delete_user(&self, _id: &Uuid) -> Result<Option<User>, CoreError> {
+    async fn delete_user(&self, _id: &Uuid) -> Result<Option<DeletedUser>, CoreError> {
         Ok(None)
     }
+
+    async fn set_user_role(&self, _id: &Uuid, _role: Role) -> Result<Option<User>, CoreError> {
+        Ok(None)
+    }
+
+    async fn set_email_verified(
+        &self,
+        _email: impl AsRef<str> + Send + Debug,
+        _verified_at: &OffsetDateTime,
+    ) -> Result<Option<User>, CoreError> {
+        Ok(None)
+    }
+
+    async fn register_user(
+        &self,
+        user: &User,
+        _provider: impl AsRef<str> + Send + Debug + Sync,
+        _provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        _tokens: &AccountTokens,
+        session: &Session,
+    ) -> Result<(User, Session), CoreError> {
+        Ok((user.to_owned(), session.to_owned()))
+    }
 }
 
 impl QueryUsers for SampleDbSend {
@@ -82,6 +142,15 @@ impl QueryUsers for SampleDbSend {
         Ok([].into_iter())
     }
 
+    async fn get_users_page(
+        &self,
+        page: Page,
+        _filter: &UserFilter,
+        _order_by: UserOrderBy,
+    ) -> Result<Paged<User>, CoreError> {
+        Ok(Paged::from_overfetch(vec![], &page, 0))
+    }
+
     async fn get_user_by_id(&self, _id: &Uuid) -> Result<Option<User>, CoreError> {
         Ok(None)
     }
@@ -101,11 +170,14 @@ impl QueryUsers for SampleDbSend {
         Ok(None)
     }
 
-    async fn search(
-        &self,
-        _query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = User>, CoreError> {
-        Ok([].into_iter())
+    async fn search(&self, search: &UserSearch) -> Result<UserSearchResults, CoreError> {
+        Ok(UserSearchResults {
+            hits: vec![],
+            total_hits: 0,
+            offset: search.offset,
+            limit: search.limit,
+            facets: UserSearchFacets::default(),
+        })
     }
 
     async fn get_session_and_user(
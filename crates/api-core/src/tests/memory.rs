@@ -0,0 +1,865 @@
+use time::{Duration, OffsetDateTime};
+use uuid::Uuid;
+
+use crate::{
+    api::{
+        CoreError, MutateAccountProviders, MutateAccounts, MutateApiKeys, MutateSessions,
+        MutateUsers, MutateVerificationTokens, Page, QueryAccountProviders, QueryAccounts,
+        QueryApiKeys, QuerySessions, QueryUsers,
+    },
+    hash_session_token, hash_verification_token,
+    memory::MemoryStore,
+    AccountProvider, AccountTokens, ApiKey, ApiKeyScope, FacetCount, ProviderType, Role, Session,
+    User, UserFilter, UserOrderBy, UserSearch, UserType, VerificationToken,
+};
+
+use super::create_user;
+
+fn create_session(user_id: Uuid, provider: &str, expires_at: OffsetDateTime) -> Session {
+    Session {
+        expires_at,
+        session_token: Uuid::now_v7().to_string(),
+        account_provider: AccountProvider {
+            name: provider.to_owned(),
+            ..Default::default()
+        },
+        user_id,
+        id: Uuid::nil(),
+        refresh_at: None,
+        user_agent: None,
+        ip_address: None,
+        device_label: None,
+        created: OffsetDateTime::now_utc(),
+        last_seen_at: None,
+    }
+}
+
+/// A store with `github` and `gitlab` registered
+async fn store_with_providers() -> MemoryStore {
+    let store = MemoryStore::new();
+    for name in ["github", "gitlab"] {
+        store
+            .create_account_provider(&AccountProvider {
+                name: name.to_owned(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
+
+    store
+}
+
+#[tokio::test]
+async fn memory_user_crud() {
+    let store = MemoryStore::new();
+    let user = store.create_user(&create_user()).await.unwrap();
+
+    assert_eq!(store.get_users().await.unwrap().len(), 1);
+    assert_eq!(
+        store.get_user_by_id(&user.id).await.unwrap(),
+        Some(user.clone())
+    );
+    assert_eq!(
+        store.get_user_by_email(&user.email).await.unwrap(),
+        Some(user.clone())
+    );
+
+    let mut update = user.clone();
+    update.name = Some(String::from("FooBar"));
+    let updated = store
+        .update_user(&user.id, &update)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(updated.id, user.id);
+    assert_eq!(updated.name, update.name);
+    assert_eq!(updated.created, user.created);
+
+    let deleted = store.delete_user(&user.id).await.unwrap();
+    assert_eq!(deleted.map(|deleted| deleted.user), Some(updated));
+    assert_eq!(store.get_users().await.unwrap().len(), 0);
+    assert!(store
+        .update_user(&user.id, &update)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_user_role() {
+    let store = MemoryStore::new();
+    let user = store.create_user(&create_user()).await.unwrap();
+    assert_eq!(user.role, Role::User);
+
+    let updated = store
+        .set_user_role(&user.id, Role::Support)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(updated.role, Role::Support);
+
+    let mut update = updated.clone();
+    update.role = Role::Admin;
+    let updated = store
+        .update_user(&user.id, &update)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(updated.role, Role::Support);
+
+    assert!(store
+        .set_user_role(&Uuid::now_v7(), Role::Admin)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_email_unique() {
+    let store = MemoryStore::new();
+    let user = store.create_user(&create_user()).await.unwrap();
+
+    let mut duplicate = create_user();
+    duplicate.email.clone_from(&user.email);
+    assert!(store.create_user(&duplicate).await.is_err());
+
+    let other = store.create_user(&create_user()).await.unwrap();
+    assert!(store.update_user(&other.id, &duplicate).await.is_err());
+}
+
+#[tokio::test]
+async fn memory_search() {
+    let store = MemoryStore::new();
+    let mut user = create_user();
+    user.username = String::from("SearchableName");
+    let user = store.create_user(&user).await.unwrap();
+    store.create_user(&create_user()).await.unwrap();
+
+    let mut company = create_user();
+    company.username = String::from("searchable_company");
+    company.user_type = UserType::Company;
+    store.create_user(&company).await.unwrap();
+
+    let mut search = UserSearch {
+        query: String::from("searchable"),
+        limit: 1,
+        ..Default::default()
+    };
+    let results = store.search(&search).await.unwrap();
+    assert_eq!(results.total_hits, 2);
+    assert_eq!(results.hits.len(), 1);
+    assert_eq!(results.hits[0].user, user);
+    assert_eq!(
+        results.hits[0].highlight.username,
+        "<em>Searchable</em>Name"
+    );
+    assert_eq!(
+        results.facets.user_type,
+        vec![
+            FacetCount {
+                value: String::from("Company"),
+                count: 1
+            },
+            FacetCount {
+                value: String::from("Individual"),
+                count: 1
+            }
+        ]
+    );
+
+    search.filter.user_type = Some(UserType::Company);
+    search.limit = 10;
+    let results = store.search(&search).await.unwrap();
+    assert_eq!(results.total_hits, 1);
+    assert_eq!(results.hits[0].user.username, company.username);
+}
+
+#[tokio::test]
+async fn memory_accounts() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    // linking the same account twice is a no-op
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    assert!(store
+        .link_account("github", "5678", &user.id, &AccountTokens::default())
+        .await
+        .is_err());
+    // an account belongs to one user only
+    let other = store.create_user(&create_user()).await.unwrap();
+    assert!(store
+        .link_account("github", "1234", &other.id, &AccountTokens::default())
+        .await
+        .is_err());
+    assert_eq!(store.get_user_accounts(&other.id).await.unwrap().len(), 0);
+
+    assert_eq!(
+        store.get_user_by_account("github", "1234").await.unwrap(),
+        Some(user.clone())
+    );
+    assert!(store
+        .get_user_by_account("gitlab", "1234")
+        .await
+        .unwrap()
+        .is_none());
+
+    // the only account can't be unlinked unless forced
+    assert!(matches!(
+        store.unlink_account("github", "1234", false).await,
+        Err(CoreError::LastSignInMethod)
+    ));
+    store.unlink_account("github", "1234", true).await.unwrap();
+    assert!(store
+        .get_user_by_account("github", "1234")
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_account_tokens() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    let tokens = AccountTokens {
+        access_token: Some("access".to_owned()),
+        refresh_token: Some("refresh".to_owned()),
+        expires_at: Some(OffsetDateTime::now_utc() + Duration::HOUR),
+        token_type: Some("bearer".to_owned()),
+        scope: Some("read:user".to_owned()),
+        ..Default::default()
+    };
+
+    store
+        .link_account("github", "1234", &user.id, &tokens)
+        .await
+        .unwrap();
+
+    let account = store.get_account("github", "1234").await.unwrap().unwrap();
+    assert_eq!(account.user_id, user.id);
+    assert_eq!(account.tokens, tokens);
+    assert!(store.get_account("gitlab", "1234").await.unwrap().is_none());
+
+    let refreshed = AccountTokens {
+        access_token: Some("refreshed".to_owned()),
+        ..tokens.clone()
+    };
+    let account = store
+        .update_account_tokens("github", "1234", &refreshed)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(account.tokens, refreshed);
+    assert!(store
+        .update_account_tokens("github", "5678", &refreshed)
+        .await
+        .unwrap()
+        .is_none());
+
+    // secrets never end up in logs
+    assert!(!format!("{account:?}").contains("refreshed"));
+}
+
+#[tokio::test]
+async fn memory_user_accounts() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    let other = store.create_user(&create_user()).await.unwrap();
+
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    store
+        .link_account("gitlab", "5678", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    store
+        .link_account("github", "9012", &other.id, &AccountTokens::default())
+        .await
+        .unwrap();
+
+    let accounts: Vec<_> = store.get_user_accounts(&user.id).await.unwrap().collect();
+    assert_eq!(
+        accounts
+            .iter()
+            .map(|account| (
+                account.provider.as_str(),
+                account.provider_account_id.as_str()
+            ))
+            .collect::<Vec<_>>(),
+        vec![("github", "1234"), ("gitlab", "5678")]
+    );
+    assert!(accounts[0].linked_at <= accounts[1].linked_at);
+
+    store.unlink_account("github", "1234", false).await.unwrap();
+    assert_eq!(store.get_user_accounts(&user.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn memory_register_user() {
+    let store = store_with_providers().await;
+    let session = create_session(Uuid::nil(), "", OffsetDateTime::now_utc() + Duration::DAY);
+
+    let (user, registered) = store
+        .register_user(
+            &create_user(),
+            "github",
+            "1234",
+            &AccountTokens::default(),
+            &session,
+        )
+        .await
+        .unwrap();
+    assert_eq!(registered.user_id, user.id);
+    assert_eq!(registered.account_provider.name, "github");
+    assert_eq!(
+        registered.session_token,
+        hash_session_token(&session.session_token)
+    );
+    assert_eq!(
+        store
+            .get_user_by_account("github", "1234")
+            .await
+            .unwrap()
+            .map(|found| found.id),
+        Some(user.id)
+    );
+    assert_eq!(
+        store
+            .get_session_and_user(&session.session_token)
+            .await
+            .unwrap()
+            .map(|(found, _)| found.id),
+        Some(user.id)
+    );
+
+    // neither a linked account, a taken email nor an unknown provider leaves a user behind
+    let mut duplicate = create_user();
+    duplicate.email.clone_from(&user.email);
+    for (user, provider, provider_account_id) in [
+        (create_user(), "github", "1234"),
+        (duplicate, "github", "5678"),
+        (create_user(), "bitbucket", "5678"),
+    ] {
+        assert!(store
+            .register_user(
+                &user,
+                provider,
+                provider_account_id,
+                &AccountTokens::default(),
+                &session,
+            )
+            .await
+            .is_err());
+    }
+    assert_eq!(store.get_users().await.unwrap().len(), 1);
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn memory_delete_user_cascades() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    let other = store.create_user(&create_user()).await.unwrap();
+    let expires_at = OffsetDateTime::now_utc() + Duration::DAY;
+
+    for (user_id, provider_account_id) in [(user.id, "1234"), (other.id, "5678")] {
+        store
+            .link_account(
+                "github",
+                provider_account_id,
+                &user_id,
+                &AccountTokens::default(),
+            )
+            .await
+            .unwrap();
+        store
+            .create_session(&create_session(user_id, "github", expires_at))
+            .await
+            .unwrap();
+    }
+    let session = store
+        .create_session(&create_session(user.id, "github", expires_at))
+        .await
+        .unwrap();
+
+    let deleted = store
+        .delete_user(&user.id)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(deleted.user.id, user.id);
+    assert_eq!(
+        deleted
+            .accounts
+            .iter()
+            .map(|account| account.provider_account_id.as_str())
+            .collect::<Vec<_>>(),
+        vec!["1234"]
+    );
+    assert_eq!(deleted.sessions, 2);
+    assert!(store
+        .get_session_and_user(&session.session_token)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(store.get_account("github", "1234").await.unwrap().is_none());
+
+    assert!(store.get_account("github", "5678").await.unwrap().is_some());
+    assert_eq!(store.get_user_sessions(&other.id).await.unwrap().len(), 1);
+    assert!(store.delete_user(&user.id).await.unwrap().is_none());
+}
+
+#[tokio::test]
+async fn memory_account_providers() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+
+    assert_eq!(store.get_account_providers().await.unwrap().len(), 2);
+    assert!(store
+        .create_account_provider(&AccountProvider {
+            name: "github".to_owned(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+    assert!(matches!(
+        store
+            .link_account("bitbucket", "1234", &user.id, &AccountTokens::default())
+            .await,
+        Err(CoreError::UnknownProvider(name)) if name == "bitbucket"
+    ));
+
+    let github = store.get_account_provider("github").await.unwrap().unwrap();
+    let disabled = store
+        .update_account_provider(&AccountProvider {
+            display_name: Some("GitHub".to_owned()),
+            provider_type: ProviderType::Oidc,
+            enabled: false,
+            ..github.clone()
+        })
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(disabled.id, github.id);
+    assert_eq!(disabled.display_name.as_deref(), Some("GitHub"));
+
+    assert!(matches!(
+        store
+            .link_account("github", "1234", &user.id, &AccountTokens::default())
+            .await,
+        Err(CoreError::ProviderDisabled(_))
+    ));
+    let session = create_session(
+        user.id,
+        "github",
+        OffsetDateTime::now_utc() + Duration::HOUR,
+    );
+    assert!(matches!(
+        store.create_session(&session).await,
+        Err(CoreError::ProviderDisabled(_))
+    ));
+
+    assert!(store
+        .update_account_provider(&AccountProvider {
+            name: "bitbucket".to_owned(),
+            ..Default::default()
+        })
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_sessions() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    let now = OffsetDateTime::now_utc();
+
+    // sessions are only created for registered providers
+    let unknown = create_session(user.id, "bitbucket", now + Duration::hours(1));
+    assert!(matches!(
+        store.create_session(&unknown).await,
+        Err(CoreError::UnknownProvider(_))
+    ));
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 0);
+
+    let session = create_session(user.id, "github", now + Duration::hours(1));
+
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    let created = store.create_session(&session).await.unwrap();
+    // only the digest is kept, and it does not sign in itself
+    assert_eq!(
+        created.session_token,
+        hash_session_token(&session.session_token)
+    );
+    assert!(store
+        .get_session_and_user(&created.session_token)
+        .await
+        .unwrap()
+        .is_none());
+    let expired = create_session(user.id, "github", now - Duration::hours(1));
+    let expired_id = store.create_session(&expired).await.unwrap().id;
+    // expired sessions are hidden before they are purged
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 1);
+    assert!(store.get_session(&expired_id).await.unwrap().is_none());
+    assert!(store
+        .update_session(&expired.session_token, &(now + Duration::days(1)))
+        .await
+        .unwrap()
+        .is_none());
+    assert!(store
+        .get_session_and_user(&expired.session_token)
+        .await
+        .unwrap()
+        .is_none());
+    assert!(store.get_session(&created.id).await.unwrap().is_some());
+
+    let (found_user, found_session) = store
+        .get_session_and_user(&session.session_token)
+        .await
+        .unwrap()
+        .expect("session to exist");
+    assert_eq!(found_user, user);
+    assert_eq!(found_session.account_provider.name, "github");
+    assert_ne!(found_session.account_provider.id, Uuid::nil());
+    assert!(store
+        .get_session_and_user(&expired.session_token)
+        .await
+        .unwrap()
+        .is_none());
+
+    let expires_at = now + Duration::days(1);
+    let updated = store
+        .update_session(&session.session_token, &expires_at)
+        .await
+        .unwrap()
+        .expect("session to exist");
+    assert_eq!(updated.expires_at, expires_at);
+
+    store.delete_expired_sessions().await.unwrap();
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 1);
+    assert_eq!(store.purge_expired_sessions(10).await.unwrap(), 0);
+
+    store.delete_session(&session.session_token).await.unwrap();
+    assert!(store
+        .get_session_and_user(&session.session_token)
+        .await
+        .unwrap()
+        .is_none());
+
+    store.create_session(&session).await.unwrap();
+    store.delete_user_sessions(&user.id).await.unwrap();
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn memory_session_devices() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+
+    let session = Session {
+        user_agent: Some(String::from("Firefox")),
+        device_label: Some(String::from("laptop")),
+        ..create_session(
+            user.id,
+            "github",
+            OffsetDateTime::now_utc() + Duration::hours(1),
+        )
+    };
+    let created = store.create_session(&session).await.unwrap();
+    assert_ne!(created.id, Uuid::nil());
+    assert_eq!(created.device_label.as_deref(), Some("laptop"));
+    assert_eq!(created.last_seen_at, None);
+
+    let (_, found) = store
+        .get_session_and_user(&session.session_token)
+        .await
+        .unwrap()
+        .expect("session to exist");
+    assert!(found.last_seen_at.is_some());
+    assert_eq!(
+        store.get_session(&created.id).await.unwrap(),
+        Some(found.clone())
+    );
+
+    assert_eq!(
+        store.revoke_session(&created.id).await.unwrap(),
+        Some(found)
+    );
+    assert_eq!(store.revoke_session(&created.id).await.unwrap(), None);
+    assert!(store
+        .get_session_and_user(&session.session_token)
+        .await
+        .unwrap()
+        .is_none());
+}
+
+#[tokio::test]
+async fn memory_purge_expired_sessions() {
+    let store = store_with_providers().await;
+    let user = store.create_user(&create_user()).await.unwrap();
+    store
+        .link_account("github", "1234", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    let now = OffsetDateTime::now_utc();
+
+    for _ in 0..3 {
+        let expired = create_session(user.id, "github", now - Duration::hours(1));
+        store.create_session(&expired).await.unwrap();
+    }
+    let session = create_session(user.id, "github", now + Duration::hours(1));
+    store.create_session(&session).await.unwrap();
+
+    assert_eq!(store.purge_expired_sessions(2).await.unwrap(), 2);
+    assert_eq!(store.purge_expired_sessions(2).await.unwrap(), 1);
+    assert_eq!(store.purge_expired_sessions(2).await.unwrap(), 0);
+    assert_eq!(store.get_user_sessions(&user.id).await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn memory_users_page() {
+    let store = MemoryStore::new();
+    let mut created = vec![];
+    for _ in 0..5 {
+        created.push(store.create_user(&create_user()).await.unwrap());
+    }
+    created.sort_by_key(|user| (user.created, user.id));
+
+    let page = store
+        .get_users_page(
+            Page::forward(None, 2),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.items, created[..2]);
+    assert!(!page.has_previous);
+    assert!(page.has_next);
+    assert_eq!(page.total_count, 5);
+
+    let page = store
+        .get_users_page(
+            Page::forward(Some(created[3].id), 2),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.items, created[4..]);
+    assert!(page.has_previous);
+    assert!(!page.has_next);
+
+    let page = store
+        .get_users_page(
+            Page::backward(None, 2),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.items, created[3..]);
+    assert!(page.has_previous);
+    assert!(!page.has_next);
+
+    let page = store
+        .get_users_page(
+            Page::backward(Some(created[2].id), 5),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await
+        .unwrap();
+    assert_eq!(page.items, created[..2]);
+    assert!(!page.has_previous);
+    assert!(page.has_next);
+
+    // a cursor whose user was deleted since can't bound the page
+    store.delete_user(&created[2].id).await.unwrap();
+    assert!(matches!(
+        store
+            .get_users_page(
+                Page::forward(Some(created[2].id), 5),
+                &UserFilter::default(),
+                UserOrderBy::Created,
+            )
+            .await,
+        Err(CoreError::InvalidCursor(cursor)) if cursor == created[2].id
+    ));
+}
+
+#[tokio::test]
+async fn memory_users_page_filtered() {
+    let store = MemoryStore::new();
+    for (username, user_type) in [
+        ("carol", UserType::Company),
+        ("alice", UserType::Company),
+        ("bob", UserType::Individual),
+    ] {
+        let mut user = create_user();
+        user.username = username.to_owned();
+        user.user_type = user_type;
+        store.create_user(&user).await.unwrap();
+    }
+
+    let filter = UserFilter {
+        user_type: Some(UserType::Company),
+        ..Default::default()
+    };
+    let page = store
+        .get_users_page(Page::forward(None, 10), &filter, UserOrderBy::Username)
+        .await
+        .unwrap();
+    let usernames: Vec<_> = page.items.iter().map(|u| u.username.as_str()).collect();
+    assert_eq!(usernames, ["alice", "carol"]);
+    assert_eq!(page.total_count, 2);
+
+    let filter = UserFilter {
+        has_phone_number: Some(true),
+        ..Default::default()
+    };
+    let page = store
+        .get_users_page(Page::forward(None, 10), &filter, UserOrderBy::Created)
+        .await
+        .unwrap();
+    assert!(page.items.is_empty());
+}
+
+#[tokio::test]
+async fn memory_api_keys() {
+    let store = MemoryStore::new();
+    let key = ApiKey {
+        id: Uuid::nil(),
+        name: String::from("auth frontend"),
+        prefix: String::from("ak_1234"),
+        hash: String::from("hash"),
+        scopes: vec![ApiKeyScope::Sessions],
+        expires_at: Some(OffsetDateTime::now_utc() - Duration::SECOND),
+        created: OffsetDateTime::now_utc(),
+    };
+
+    let created = store.create_api_key(&key).await.unwrap();
+    assert_ne!(created.id, key.id);
+    assert!(created.is_expired(OffsetDateTime::now_utc()));
+    assert!(store.create_api_key(&key).await.is_err());
+
+    assert_eq!(store.get_api_keys().await.unwrap().len(), 1);
+    assert_eq!(
+        store.get_api_key_by_hash("hash").await.unwrap(),
+        Some(created.clone())
+    );
+    assert_eq!(store.get_api_key_by_hash("other").await.unwrap(), None);
+
+    assert_eq!(
+        store.revoke_api_key(&created.id).await.unwrap(),
+        Some(created.clone())
+    );
+    assert_eq!(store.get_api_key_by_hash("hash").await.unwrap(), None);
+    assert_eq!(store.revoke_api_key(&created.id).await.unwrap(), None);
+}
+
+#[tokio::test]
+async fn memory_verification_tokens() {
+    let store = MemoryStore::new();
+    let token = |token: &str, expires_at| VerificationToken {
+        identifier: String::from("user@email.com"),
+        token: token.to_owned(),
+        expires_at,
+    };
+    let now = OffsetDateTime::now_utc();
+
+    let created = store
+        .create_verification_token(&token("valid", now + Duration::hours(1)))
+        .await
+        .unwrap();
+    assert_eq!(created.token, hash_verification_token("valid"));
+    store
+        .create_verification_token(&token("expired", now - Duration::hours(1)))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        store
+            .use_verification_token("other@email.com", "valid")
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        store
+            .use_verification_token("user@email.com", "valid")
+            .await
+            .unwrap(),
+        Some(created)
+    );
+    assert_eq!(
+        store
+            .use_verification_token("user@email.com", "valid")
+            .await
+            .unwrap(),
+        None
+    );
+    assert_eq!(
+        store
+            .use_verification_token("user@email.com", "expired")
+            .await
+            .unwrap(),
+        None
+    );
+}
+
+#[tokio::test]
+async fn memory_email_verified() {
+    let store = MemoryStore::new();
+    let user = store.create_user(&create_user()).await.unwrap();
+    let now = OffsetDateTime::now_utc();
+
+    let verified = store
+        .set_email_verified(&user.email, &now)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(verified.email_verified, Some(now));
+
+    let updated = store
+        .update_user(&user.id, &user)
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(updated.email_verified, Some(now));
+
+    let changed = store
+        .update_user(
+            &user.id,
+            &User {
+                email: String::from("changed@email.com"),
+                ..user.clone()
+            },
+        )
+        .await
+        .unwrap()
+        .expect("user to exist");
+    assert_eq!(changed.email_verified, None);
+    assert!(store
+        .set_email_verified(&user.email, &now)
+        .await
+        .unwrap()
+        .is_none());
+}
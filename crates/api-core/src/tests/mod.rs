@@ -1,7 +1,9 @@
 mod async_graphql;
 mod db;
+#[cfg(feature = "memory")]
+mod memory;
 
-use crate::{tests::db::SampleDbSend, User, UserType};
+use crate::{tests::db::SampleDbSend, Role, User, UserType};
 
 use self::db::SampleDb;
 use fake::{
@@ -24,6 +26,8 @@ fn create_user() -> User {
         avatar: None,
         user_type: UserType::Individual,
         phone_number: None,
+        role: Role::User,
+        email_verified: None,
         created: OffsetDateTime::now_utc(),
         updated: OffsetDateTime::now_utc(),
     }
@@ -57,6 +61,8 @@ fn deserialise_list() {
         avatar: None,
         user_type: UserType::Individual,
         phone_number: None,
+        role: Role::User,
+        email_verified: None,
         created: OffsetDateTime::now_utc(),
         updated: OffsetDateTime::now_utc(),
     };
@@ -75,6 +81,17 @@ fn deserialise_list() {
     assert_eq!(source, users);
 }
 
+#[test]
+fn decode_without_email_verified() {
+    let user = create_user();
+    let mut json = serde_json::to_value(&user).unwrap();
+    json.as_object_mut().unwrap().remove("email_verified");
+
+    // payloads cached before the field existed still decode
+    let value: User = serde_json::from_value(json).unwrap();
+    assert_eq!(value, user);
+}
+
 #[tokio::test]
 async fn trait_blank_queries() {
     use crate::api::LocalQueryUsers;
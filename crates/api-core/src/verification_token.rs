@@ -0,0 +1,30 @@
+#[cfg(feature = "async-graphql")]
+use async_graphql::*;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use time::OffsetDateTime;
+
+/// A single use token proving control of `identifier`, such as an email address for passwordless
+/// sign in. Only a digest of the token is stored
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "async-graphql", derive(SimpleObject))]
+pub struct VerificationToken {
+    pub identifier: String,
+    /// Minted by the API when the token is created. Storage hashes it with
+    /// [`hash_verification_token`] and only ever holds the digest
+    pub token: String,
+    pub expires_at: OffsetDateTime,
+}
+
+impl VerificationToken {
+    pub fn is_expired(&self, now: OffsetDateTime) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// The digest verification tokens are stored and looked up by
+pub fn hash_verification_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,66 @@
+#[cfg(feature = "async-graphql")]
+use async_graphql::Context;
+use uuid::Uuid;
+
+use crate::{ApiKey, ApiKeyScope, Role, Session, User};
+
+/// The caller of a request
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Viewer {
+    /// No credentials were sent
+    #[default]
+    Anonymous,
+    /// Signed in through a session token
+    User { user: Box<User>, session: Session },
+    /// Another service signed in with an API key
+    ApiKey(ApiKey),
+}
+
+impl Viewer {
+    /// Reads the caller from a GraphQL context. Requests that did not go through authentication,
+    /// such as subscriptions, are anonymous
+    #[cfg(feature = "async-graphql")]
+    pub fn from_context<'a>(ctx: &'a Context<'_>) -> &'a Self {
+        static ANONYMOUS: Viewer = Viewer::Anonymous;
+
+        ctx.data_opt::<Self>().unwrap_or(&ANONYMOUS)
+    }
+
+    pub fn user(&self) -> Option<&User> {
+        match self {
+            Self::User { user, .. } => Some(user),
+            Self::Anonymous | Self::ApiKey(_) => None,
+        }
+    }
+
+    /// API keys act with the service role
+    pub fn role(&self) -> Option<Role> {
+        match self {
+            Self::Anonymous => None,
+            Self::User { user, .. } => Some(user.role),
+            Self::ApiKey(_) => Some(Role::Service),
+        }
+    }
+
+    /// Scopes only restrict API keys, everyone else is limited by their role
+    pub fn has_scope(&self, scope: ApiKeyScope) -> bool {
+        match self {
+            Self::ApiKey(key) => key.scopes.contains(&scope),
+            Self::Anonymous | Self::User { .. } => true,
+        }
+    }
+
+    pub fn is_user(&self, id: &Uuid) -> bool {
+        self.user().is_some_and(|user| &user.id == id)
+    }
+
+    /// Whether contact details of the user with `id` can be shown
+    pub fn can_view_private(&self, id: &Uuid) -> bool {
+        self.is_user(id) || self.can_view_all_private()
+    }
+
+    /// Whether contact details of every user can be shown, or used to filter users
+    pub fn can_view_all_private(&self) -> bool {
+        self.role().is_some_and(|role| role != Role::User)
+    }
+}
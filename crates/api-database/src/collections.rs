@@ -15,6 +15,11 @@ pub(crate) enum Collection {
     UserAccount,
     #[serde(rename = "user_session")]
     UserSession,
+    #[serde(rename = "api_key")]
+    ApiKey,
+    #[serde(rename = "verification_token")]
+    VerificationToken,
+    Sells,
 }
 
 impl std::fmt::Display for Collection {
@@ -27,6 +32,9 @@ impl std::fmt::Display for Collection {
                 Collection::AccountProvider => "account_provider",
                 Collection::UserAccount => "user_account",
                 Collection::UserSession => "user_session",
+                Collection::ApiKey => "api_key",
+                Collection::VerificationToken => "verification_token",
+                Collection::Sells => "sells",
             }
         )
     }
@@ -1,9 +1,20 @@
-use std::fmt;
+use std::{fmt, str::FromStr};
 
-use api_core::{api::CoreError, reexports::uuid::Uuid, AccountProvider, Session, User, UserType};
+use api_core::{
+    api::CoreError, reexports::uuid::Uuid, Account, AccountProvider, AccountTokens, ApiKey,
+    ApiKeyScope, LinkedAccount, ProviderType, Role, Session, User, UserType, VerificationToken,
+};
 use serde::{de, Deserialize, Serialize};
-use surrealdb::{opt::RecordId, sql::Id};
-use time::{format_description::well_known::Iso8601, OffsetDateTime};
+use surrealdb::{
+    opt::RecordId,
+    sql::{Datetime, Id},
+};
+use time::{
+    format_description::well_known::{Iso8601, Rfc3339},
+    OffsetDateTime,
+};
+
+use crate::{SessionPolicy, TokenCipher};
 
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct DatabaseEntityUser {
@@ -15,13 +26,17 @@ pub(crate) struct DatabaseEntityUser {
     #[serde(rename = "type")]
     pub user_type: UserType,
     pub phone_number: Option<String>,
+    #[serde(default)]
+    pub role: Role,
+    #[serde(default, deserialize_with = "deserialize_optional_date_time")]
+    pub email_verified: Option<OffsetDateTime>,
     #[serde(deserialize_with = "deserialize_date_time")]
     pub created: OffsetDateTime,
     #[serde(deserialize_with = "deserialize_date_time")]
     pub updated: OffsetDateTime,
 }
 
-fn deserialize_date_time<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
+pub(crate) fn deserialize_date_time<'de, D>(deserializer: D) -> Result<OffsetDateTime, D::Error>
 where
     D: de::Deserializer<'de>,
 {
@@ -45,6 +60,33 @@ where
     deserializer.deserialize_any(OffsetDateTimeVisitor)
 }
 
+pub(crate) fn deserialize_optional_date_time<'de, D>(
+    deserializer: D,
+) -> Result<Option<OffsetDateTime>, D::Error>
+where
+    D: de::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_date_time")] OffsetDateTime);
+
+    Ok(Option::<Wrapper>::deserialize(deserializer)?.map(|value| value.0))
+}
+
+fn serialize_optional_date_time<S>(
+    value: &Option<OffsetDateTime>,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    value.map(to_datetime).serialize(serializer)
+}
+
+pub(crate) fn to_datetime(value: OffsetDateTime) -> Datetime {
+    let value = value.format(&Rfc3339).expect("date time conversion");
+    Datetime::from_str(&value).expect("date time conversion")
+}
+
 #[derive(Serialize, Deserialize, Debug)]
 pub(crate) struct DatabaseEntitySession {
     pub id: RecordId,
@@ -54,12 +96,91 @@ pub(crate) struct DatabaseEntitySession {
     #[serde(deserialize_with = "deserialize_date_time")]
     pub expires_at: OffsetDateTime,
     pub session_token: String,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub created: OffsetDateTime,
+    #[serde(default)]
+    pub user_agent: Option<String>,
+    #[serde(default)]
+    pub ip_address: Option<String>,
+    #[serde(default)]
+    pub device_label: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_date_time")]
+    pub last_seen_at: Option<OffsetDateTime>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct DatabaseEntityAccount {
+    #[serde(rename = "in")]
+    pub in_field: RecordId,
+    pub provider: String,
+    pub provider_account_id: String,
+    #[serde(default)]
+    pub tokens: DatabaseEntityAccountTokens,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct DatabaseEntityLinkedAccount {
+    pub provider: String,
+    pub provider_account_id: String,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub linked_at: OffsetDateTime,
+}
+
+/// `AccountTokens` as stored, with the access, refresh and id tokens encrypted
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct DatabaseEntityAccountTokens {
+    pub access_token: Option<String>,
+    pub refresh_token: Option<String>,
+    #[serde(
+        default,
+        serialize_with = "serialize_optional_date_time",
+        deserialize_with = "deserialize_optional_date_time"
+    )]
+    pub expires_at: Option<OffsetDateTime>,
+    pub token_type: Option<String>,
+    pub scope: Option<String>,
+    pub id_token: Option<String>,
+    pub session_state: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct DatabaseEntityApiKey {
+    pub id: RecordId,
+    pub name: String,
+    pub prefix: String,
+    pub hash: String,
+    pub scopes: Vec<ApiKeyScope>,
+    #[serde(default, deserialize_with = "deserialize_optional_date_time")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub created: OffsetDateTime,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub(crate) struct DatabaseEntityVerificationToken {
+    pub identifier: String,
+    pub token: String,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub expires_at: OffsetDateTime,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub(crate) struct DatabaseEntityAccountProvider {
     pub id: RecordId,
     pub name: String,
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub icon_url: Option<String>,
+    #[serde(default, rename = "type")]
+    pub provider_type: ProviderType,
+    #[serde(default = "enabled_by_default")]
+    pub enabled: bool,
+}
+
+/// Providers created before the registry existed were all usable
+fn enabled_by_default() -> bool {
+    true
 }
 
 impl TryFrom<DatabaseEntityAccountProvider> for AccountProvider {
@@ -71,29 +192,142 @@ impl TryFrom<DatabaseEntityAccountProvider> for AccountProvider {
         Ok(AccountProvider {
             id,
             name: value.name,
+            display_name: value.display_name,
+            icon_url: value.icon_url,
+            provider_type: value.provider_type,
+            enabled: value.enabled,
         })
     }
 }
 
-impl TryFrom<DatabaseEntitySession> for Session {
-    type Error = CoreError;
-
-    fn try_from(value: DatabaseEntitySession) -> Result<Self, Self::Error> {
-        let user_id = record_id_to_uuid(&value.in_field)?;
-        let account_provider_id = record_id_to_uuid(&value.out.id)?;
+impl DatabaseEntitySession {
+    pub fn into_session(self, policy: &SessionPolicy) -> Result<Session, CoreError> {
+        let user_id = record_id_to_uuid(&self.in_field)?;
 
         Ok(Session {
-            expires_at: value.expires_at,
-            session_token: value.session_token,
-            account_provider: AccountProvider {
-                id: account_provider_id,
-                name: value.out.name,
-            },
+            id: record_id_to_uuid(&self.id)?,
+            refresh_at: policy.refresh_at(self.created, self.expires_at),
+            expires_at: self.expires_at,
+            session_token: self.session_token,
+            account_provider: AccountProvider::try_from(self.out)?,
             user_id,
+            user_agent: self.user_agent,
+            ip_address: self.ip_address,
+            device_label: self.device_label,
+            created: self.created,
+            last_seen_at: self.last_seen_at,
+        })
+    }
+}
+
+impl DatabaseEntityAccountTokens {
+    pub fn encrypt(
+        tokens: &AccountTokens,
+        cipher: Option<&TokenCipher>,
+        account: &str,
+    ) -> Result<Self, CoreError> {
+        let encrypt = |token: &Option<String>| -> Result<Option<String>, CoreError> {
+            match (token, cipher) {
+                (Some(token), Some(cipher)) => cipher.encrypt(token, account).map(Some),
+                (Some(_), None) => Err(CoreError::Other(
+                    "account tokens can't be stored without a token key".to_owned(),
+                )),
+                (None, _) => Ok(None),
+            }
+        };
+
+        Ok(Self {
+            access_token: encrypt(&tokens.access_token)?,
+            refresh_token: encrypt(&tokens.refresh_token)?,
+            expires_at: tokens.expires_at,
+            token_type: tokens.token_type.clone(),
+            scope: tokens.scope.clone(),
+            id_token: encrypt(&tokens.id_token)?,
+            session_state: tokens.session_state.clone(),
+        })
+    }
+
+    fn decrypt(
+        self,
+        cipher: Option<&TokenCipher>,
+        account: &str,
+    ) -> Result<AccountTokens, CoreError> {
+        let decrypt = |token: Option<String>| -> Result<Option<String>, CoreError> {
+            match (token, cipher) {
+                (Some(token), Some(cipher)) => cipher.decrypt(&token, account).map(Some),
+                (Some(_), None) => Err(CoreError::Other(
+                    "account tokens can't be read without a token key".to_owned(),
+                )),
+                (None, _) => Ok(None),
+            }
+        };
+
+        Ok(AccountTokens {
+            access_token: decrypt(self.access_token)?,
+            refresh_token: decrypt(self.refresh_token)?,
+            expires_at: self.expires_at,
+            token_type: self.token_type,
+            scope: self.scope,
+            id_token: decrypt(self.id_token)?,
+            session_state: self.session_state,
+        })
+    }
+}
+
+impl From<DatabaseEntityLinkedAccount> for LinkedAccount {
+    fn from(value: DatabaseEntityLinkedAccount) -> Self {
+        LinkedAccount {
+            provider: value.provider,
+            provider_account_id: value.provider_account_id,
+            linked_at: value.linked_at,
+        }
+    }
+}
+
+impl DatabaseEntityAccount {
+    pub fn into_account(self, cipher: Option<&TokenCipher>) -> Result<Account, CoreError> {
+        let account = account_binding(&self.provider, &self.provider_account_id);
+
+        Ok(Account {
+            user_id: record_id_to_uuid(&self.in_field)?,
+            tokens: self.tokens.decrypt(cipher, &account)?,
+            provider: self.provider,
+            provider_account_id: self.provider_account_id,
         })
     }
 }
 
+/// Associated data tying encrypted tokens to the account they were issued for
+pub(crate) fn account_binding(provider: &str, provider_account_id: &str) -> String {
+    format!("{provider}:{provider_account_id}")
+}
+
+impl TryFrom<DatabaseEntityApiKey> for ApiKey {
+    type Error = CoreError;
+
+    fn try_from(value: DatabaseEntityApiKey) -> Result<Self, Self::Error> {
+        Ok(ApiKey {
+            id: record_id_to_uuid(&value.id)?,
+            name: value.name,
+            prefix: value.prefix,
+            hash: value.hash,
+            scopes: value.scopes,
+            expires_at: value.expires_at,
+            created: value.created,
+        })
+    }
+}
+
+impl From<DatabaseEntityVerificationToken> for VerificationToken {
+    fn from(value: DatabaseEntityVerificationToken) -> Self {
+        VerificationToken {
+            identifier: value.identifier,
+            token: value.token,
+            expires_at: value.expires_at,
+        }
+    }
+}
+
 impl TryFrom<DatabaseEntityUser> for User {
     type Error = CoreError;
 
@@ -108,6 +342,8 @@ impl TryFrom<DatabaseEntityUser> for User {
             avatar: entity.avatar,
             user_type: entity.user_type,
             phone_number: entity.phone_number,
+            role: entity.role,
+            email_verified: entity.email_verified,
             created: entity.created,
             updated: entity.updated,
         })
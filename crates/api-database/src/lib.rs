@@ -3,27 +3,46 @@ use thiserror::Error;
 
 mod collections;
 pub(crate) mod entity;
+mod migrations;
 mod mutation;
 mod query;
 mod redis;
+mod search;
+mod session_policy;
+mod token_cipher;
+
+use std::sync::{atomic::AtomicBool, Arc};
 
 use surrealdb::{
-    engine::remote::ws::{Client as SurrealClient, Ws},
+    engine::any::{self, Any},
     opt::auth::Root,
     Surreal,
 };
-use tracing::{instrument, trace};
+use tracing::{debug, instrument, trace};
+
+use self::{redis::RedisPool, search::IndexQueue};
 
-use self::redis::RedisPool;
+pub use migrations::{AppliedMigration, Migration, MIGRATIONS};
+pub use search::REINDEX_BATCH_SIZE;
+pub use session_policy::SessionPolicy;
+pub use token_cipher::TokenCipher;
 
 pub(crate) fn map_db_error(error: surrealdb::Error) -> CoreError {
     CoreError::Database(error.to_string())
 }
 
+/// Schemes handled by an embedded engine rather than a remote server
+const EMBEDDED_SCHEMES: [&str; 4] = ["mem", "memory", "file", "rocksdb"];
+
+#[derive(Clone)]
 pub struct Client {
-    client: Surreal<SurrealClient>,
+    client: Surreal<Any>,
     redis: Option<(RedisPool, u64)>,
     search_client: Option<meilisearch_sdk::client::Client>,
+    index_queue: IndexQueue,
+    search_ready: Arc<AtomicBool>,
+    session_policy: SessionPolicy,
+    token_cipher: Option<TokenCipher>,
 }
 
 impl Client {
@@ -37,15 +56,23 @@ impl Client {
         redis: Option<(&str, bool, u16, u64)>,
         meilisearch: Option<(&str, Option<&str>)>,
     ) -> Result<Self, ClientError> {
-        trace!("connecting to database");
-        let db = Surreal::new::<Ws>(dsn).await?;
+        let (dsn, embedded) = match dsn.split_once("://") {
+            Some((scheme, _)) => (dsn.to_owned(), EMBEDDED_SCHEMES.contains(&scheme)),
+            // DSNs without a scheme are treated as remote websocket hosts
+            None => (format!("ws://{dsn}"), false),
+        };
 
-        // Signin as a namespace, database, or root user
-        db.signin(Root { username, password }).await?;
+        trace!(embedded = embedded, "connecting to database");
+        let db = any::connect(dsn).await?;
+
+        if !embedded {
+            // Signin as a namespace, database, or root user
+            db.signin(Root { username, password }).await?;
+        }
 
         db.use_ns(namespace).use_db(database).await?;
 
-        Ok(Client {
+        let client = Client {
             client: db,
             search_client: match meilisearch {
                 Some((host, api_key)) => Some(
@@ -54,7 +81,10 @@ impl Client {
                 ),
                 None => None,
             },
-
+            index_queue: IndexQueue::default(),
+            search_ready: Arc::default(),
+            session_policy: SessionPolicy::default(),
+            token_cipher: None,
             redis: match redis {
                 Some((dsn, clustered, size, ttl)) => Some((
                     if clustered {
@@ -66,7 +96,30 @@ impl Client {
                 )),
                 None => None,
             },
-        })
+        };
+
+        if embedded {
+            trace!("applying migrations to embedded database");
+            client.migrate().await?;
+            debug!("migrations applied");
+        }
+
+        Ok(client)
+    }
+
+    pub fn with_session_policy(self, session_policy: SessionPolicy) -> Self {
+        Self {
+            session_policy,
+            ..self
+        }
+    }
+
+    /// Account tokens can only be stored once a cipher is set
+    pub fn with_token_cipher(self, token_cipher: TokenCipher) -> Self {
+        Self {
+            token_cipher: Some(token_cipher),
+            ..self
+        }
     }
 }
 
@@ -76,6 +129,8 @@ pub enum ClientError {
     Engine(#[from] surrealdb::Error),
     #[error("the data for key `{0}` is not available")]
     Other(String),
+    #[error("migration failed: {0}")]
+    Migration(String),
     #[error("invalid header (expected {expected:?}, found {found:?})")]
     InvalidHeader { expected: String, found: String },
     #[error("unknown data store error")]
@@ -0,0 +1,204 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use surrealdb::sql::Datetime;
+use time::OffsetDateTime;
+use tracing::{debug, info, instrument, trace};
+
+use crate::{entity::deserialize_date_time, Client, ClientError};
+
+macro_rules! migration {
+    ($version:literal, $name:literal) => {
+        Migration {
+            version: $version,
+            name: $name,
+            up: include_str!(concat!(
+                "../../../resources/migrations/",
+                $name,
+                ".up.surql"
+            )),
+            down: include_str!(concat!(
+                "../../../resources/migrations/",
+                $name,
+                ".down.surql"
+            )),
+        }
+    };
+}
+
+/// Every known migration, ordered by version
+pub const MIGRATIONS: &[Migration] = &[
+    migration!(1, "0001_initial"),
+    migration!(2, "0002_user_type_casing"),
+    migration!(3, "0003_user_search"),
+    migration!(4, "0004_user_roles"),
+    migration!(5, "0005_api_keys"),
+    migration!(6, "0006_hashed_session_tokens"),
+    migration!(7, "0007_session_created"),
+    migration!(8, "0008_session_devices"),
+    migration!(9, "0009_verification_tokens"),
+    migration!(10, "0010_email_verified"),
+    migration!(11, "0011_account_tokens"),
+    migration!(12, "0012_account_linked_at"),
+    migration!(13, "0013_account_provider_registry"),
+];
+
+const MIGRATIONS_TABLE: &str = "
+DEFINE TABLE _migrations SCHEMAFULL PERMISSIONS NONE;
+DEFINE FIELD version ON _migrations TYPE int;
+DEFINE FIELD name ON _migrations TYPE string;
+DEFINE FIELD checksum ON _migrations TYPE string;
+DEFINE FIELD applied_at ON _migrations TYPE datetime;
+";
+
+#[derive(Debug, Clone, Copy)]
+pub struct Migration {
+    pub version: u32,
+    pub name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+impl Migration {
+    /// Hex encoded SHA-256 of the `up` script
+    pub fn checksum(&self) -> String {
+        hex::encode(Sha256::digest(self.up.as_bytes()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AppliedMigration {
+    pub version: u32,
+    pub name: String,
+    pub checksum: String,
+    #[serde(deserialize_with = "deserialize_date_time")]
+    pub applied_at: OffsetDateTime,
+}
+
+fn find_migration(version: u32) -> Option<&'static Migration> {
+    MIGRATIONS
+        .iter()
+        .find(|migration| migration.version == version)
+}
+
+fn unknown_migration(version: u32) -> ClientError {
+    ClientError::Migration(format!(
+        "migration {version} is applied but unknown to this build"
+    ))
+}
+
+#[derive(Serialize)]
+struct MigrationRecord<'a> {
+    version: u32,
+    name: &'a str,
+    checksum: String,
+    applied_at: Datetime,
+}
+
+impl Client {
+    /// Migrations recorded in the `_migrations` table, ordered by version
+    #[instrument(skip(self), err(Debug))]
+    pub async fn applied_migrations(&self) -> Result<Vec<AppliedMigration>, ClientError> {
+        self.client.query(MIGRATIONS_TABLE).await?.check()?;
+
+        let applied = self
+            .client
+            .query("SELECT version, name, checksum, applied_at FROM _migrations ORDER BY version")
+            .await?
+            .take(0)?;
+
+        Ok(applied)
+    }
+
+    /// Applies every pending migration in order, returning the versions that were applied.
+    /// Fails without applying anything if an applied migration no longer matches its file.
+    #[instrument(skip(self), err(Debug))]
+    pub async fn migrate(&self) -> Result<Vec<u32>, ClientError> {
+        let applied = self.applied_migrations().await?;
+
+        for record in &applied {
+            let migration =
+                find_migration(record.version).ok_or_else(|| unknown_migration(record.version))?;
+
+            if migration.checksum() != record.checksum {
+                return Err(ClientError::Migration(format!(
+                    "checksum mismatch for migration {} `{}`",
+                    migration.version, migration.name
+                )));
+            }
+        }
+
+        let mut versions = vec![];
+        for migration in MIGRATIONS
+            .iter()
+            .filter(|migration| !applied.iter().any(|r| r.version == migration.version))
+        {
+            trace!(
+                version = migration.version,
+                name = migration.name,
+                "applying migration"
+            );
+            let record = MigrationRecord {
+                version: migration.version,
+                name: migration.name,
+                checksum: migration.checksum(),
+                applied_at: Datetime::default(),
+            };
+
+            self.client
+                .query("BEGIN TRANSACTION")
+                .query(migration.up)
+                .query("CREATE type::thing('_migrations', $version) CONTENT $record")
+                .query("COMMIT TRANSACTION")
+                .bind(("version", migration.version))
+                .bind(("record", record))
+                .await?
+                .check()?;
+
+            info!(
+                version = migration.version,
+                name = migration.name,
+                "migration applied"
+            );
+            versions.push(migration.version);
+        }
+
+        debug!(count = versions.len(), "migrations up to date");
+        Ok(versions)
+    }
+
+    /// Reverts applied migrations newer than `target` in reverse order, returning the versions
+    /// that were reverted
+    #[instrument(skip(self), err(Debug))]
+    pub async fn rollback(&self, target: u32) -> Result<Vec<u32>, ClientError> {
+        let applied = self.applied_migrations().await?;
+
+        let mut versions = vec![];
+        for record in applied.iter().rev().filter(|r| r.version > target) {
+            let migration =
+                find_migration(record.version).ok_or_else(|| unknown_migration(record.version))?;
+
+            trace!(
+                version = migration.version,
+                name = migration.name,
+                "reverting migration"
+            );
+            self.client
+                .query("BEGIN TRANSACTION")
+                .query(migration.down)
+                .query("DELETE type::thing('_migrations', $version)")
+                .query("COMMIT TRANSACTION")
+                .bind(("version", migration.version))
+                .await?
+                .check()?;
+
+            info!(
+                version = migration.version,
+                name = migration.name,
+                "migration reverted"
+            );
+            versions.push(migration.version);
+        }
+
+        Ok(versions)
+    }
+}
@@ -1,11 +1,17 @@
-use api_core::api::{CoreError, MutateAccounts};
-use serde_json::json;
+use api_core::{
+    api::{CoreError, MutateAccounts},
+    Account, AccountTokens,
+};
 use std::fmt::Debug;
 use surrealdb::sql::Thing;
-use tracing::instrument;
+use tracing::{event, instrument, trace, Level};
 use uuid::Uuid;
 
-use crate::{collections::Collection, entity::DatabaseEntityAccountProvider, map_db_error, Client};
+use crate::{
+    collections::Collection,
+    entity::{account_binding, DatabaseEntityAccount, DatabaseEntityAccountTokens},
+    map_db_error, Client,
+};
 
 impl MutateAccounts for Client {
     #[instrument(skip(self), err(Debug))]
@@ -14,88 +20,125 @@ impl MutateAccounts for Client {
         provider: impl AsRef<str> + Send + Debug + Sync,
         provider_account_id: impl AsRef<str> + Send + Debug + Sync,
         user_id: &Uuid,
+        tokens: &AccountTokens,
     ) -> Result<(), CoreError> {
-        let create_id = |id: &Uuid| -> Thing {
-            Thing::from((
-                Collection::User.to_string().as_str(),
-                id.to_string().as_str(),
-            ))
-        };
-
         let provider = provider.as_ref();
+        let provider_account_id = provider_account_id.as_ref();
+        let account_provider = self.enabled_account_provider(provider).await?;
+        let tokens = DatabaseEntityAccountTokens::encrypt(
+            tokens,
+            self.token_cipher.as_ref(),
+            &account_binding(provider, provider_account_id),
+        )?;
+
+        let user_id = Thing::from((
+            Collection::User.to_string().as_str(),
+            user_id.to_string().as_str(),
+        ));
 
         let mut resp = self
             .client
             .query(
-                "SELECT out.* FROM type::table($table) WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider)"
+                "SELECT VALUE in FROM type::table($table) WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider)"
             )
             .bind(("table", Collection::UserAccount))
             .bind(("provider", provider))
-            .bind(("provider_account_id", provider_account_id.as_ref()))
+            .bind(("provider_account_id", provider_account_id))
             .await
             .map_err(map_db_error)?;
 
-        let account: Vec<serde_json::Value> = resp.take(0).map_err(map_db_error)?;
+        let linked: Vec<Thing> = resp.take(0).map_err(map_db_error)?;
 
-        if account.first().is_none() {
-            let user_id = create_id(user_id);
-            let mut resp = self.client
-                .query("SELECT value id FROM type::table($account_provider_table) WHERE name = type::string($name)")
-                .bind(("name", provider))
-                .bind(("account_provider_table", Collection::AccountProvider)).await.map_err(map_db_error)?;
-
-            let create_account = |id: Thing| {
-                let query = format!(
-                    "RELATE {user_id} -> {} -> {id} SET provider_account_id = '{}'",
-                    Collection::UserAccount,
-                    provider_account_id.as_ref()
-                );
-                self.client.query(query)
-            };
-
-            let id: Option<Thing> = resp.take(0).map_err(map_db_error)?;
-            if let Some(id) = id {
-                create_account(id).await.map_err(map_db_error)?;
-            } else {
-                let id = Uuid::now_v7();
-                let account_provider: Option<DatabaseEntityAccountProvider> = self
-                    .client
-                    .create((Collection::AccountProvider.to_string(), id.to_string()))
-                    .content(json!({
-                        "name": provider
-                    }))
+        match linked.first() {
+            // relinking to the same user is a no-op
+            Some(linked) if linked == &user_id => {}
+            Some(_) => {
+                return Err(CoreError::Database(format!(
+                    "account `{provider_account_id}` of `{provider}` is already linked"
+                )))
+            }
+            None => {
+                self.client
+                    .query(format!(
+                        "RELATE $user -> {} -> $provider SET provider_account_id = type::string($provider_account_id), tokens = $tokens",
+                        Collection::UserAccount,
+                    ))
+                    .bind(("user", user_id))
+                    .bind(("provider", account_provider.id))
+                    .bind(("provider_account_id", provider_account_id))
+                    .bind(("tokens", tokens))
                     .await
+                    .map_err(map_db_error)?
+                    .check()
                     .map_err(map_db_error)?;
-
-                if let Some(account_provider) = account_provider {
-                    create_account(account_provider.id)
-                        .await
-                        .map_err(map_db_error)?;
-                } else {
-                    unreachable!("expected account provider to be returned");
-                }
-            };
+            }
         }
         Ok(())
     }
 
+    #[instrument(skip(self), err(Debug))]
+    async fn update_account_tokens(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+        tokens: &AccountTokens,
+    ) -> Result<Option<Account>, CoreError> {
+        trace!("updating account tokens");
+        let provider = provider.as_ref();
+        let provider_account_id = provider_account_id.as_ref();
+        let tokens = DatabaseEntityAccountTokens::encrypt(
+            tokens,
+            self.token_cipher.as_ref(),
+            &account_binding(provider, provider_account_id),
+        )?;
+
+        let mut resp = self
+            .client
+            .query("UPDATE type::table($table) SET tokens = $tokens WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider) RETURN in, out.name AS provider, provider_account_id, tokens")
+            .bind(("table", Collection::UserAccount))
+            .bind(("provider", provider))
+            .bind(("provider_account_id", provider_account_id))
+            .bind(("tokens", tokens))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Option<DatabaseEntityAccount> = resp.take(0).map_err(map_db_error)?;
+        event!(Level::INFO, found = res.is_some(), "account tokens updated");
+
+        res.map(|account| account.into_account(self.token_cipher.as_ref()))
+            .transpose()
+    }
+
     #[instrument(skip(self), err(Debug))]
     async fn unlink_account(
         &self,
         provider: impl AsRef<str> + Send + Debug,
         provider_account_id: impl AsRef<str> + Send + Debug,
+        force: bool,
     ) -> Result<(), CoreError> {
         let provider = provider.as_ref();
         let provider_account_id = provider_account_id.as_ref();
 
-        self.client
-            .query("DELETE type::table($table) WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider)")
+        // the check and the delete run in one transaction so concurrent unlinks can't both pass
+        let mut resp = self
+            .client
+            .query("BEGIN TRANSACTION; LET $account = (SELECT id, in FROM ONLY type::table($table) WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider) LIMIT 1); LET $blocked = $account AND !$force AND $account.in.email_verified IS NONE AND count(SELECT id FROM type::table($table) WHERE in = $account.in) <= 1; IF $account AND !$blocked { DELETE $account.id }; RETURN $blocked; COMMIT TRANSACTION;")
             .bind(("table", Collection::UserAccount))
             .bind(("provider", provider))
             .bind(("provider_account_id", provider_account_id))
+            .bind(("force", force))
             .await
+            .map_err(map_db_error)?
+            .check()
             .map_err(map_db_error)?;
 
+        let last = resp.num_statements() - 1;
+        let blocked: Option<bool> = resp.take(last).map_err(map_db_error)?;
+        if blocked.unwrap_or_default() {
+            return Err(CoreError::LastSignInMethod);
+        }
+        event!(Level::INFO, force, "account unlinked");
+
         Ok(())
     }
 }
@@ -0,0 +1,79 @@
+use api_core::{
+    api::{CoreError, MutateAccountProviders},
+    reexports::uuid::Uuid,
+    AccountProvider, ProviderType,
+};
+use serde::Serialize;
+use tracing::{event, instrument, trace, Level};
+
+use crate::{collections::Collection, entity::DatabaseEntityAccountProvider, map_db_error, Client};
+
+#[derive(Serialize)]
+struct InputAccountProvider<'a> {
+    name: &'a str,
+    display_name: Option<&'a str>,
+    icon_url: Option<&'a str>,
+    #[serde(rename = "type")]
+    provider_type: ProviderType,
+    enabled: bool,
+}
+
+impl<'a> From<&'a AccountProvider> for InputAccountProvider<'a> {
+    fn from(value: &'a AccountProvider) -> Self {
+        Self {
+            name: &value.name,
+            display_name: value.display_name.as_deref(),
+            icon_url: value.icon_url.as_deref(),
+            provider_type: value.provider_type,
+            enabled: value.enabled,
+        }
+    }
+}
+
+impl MutateAccountProviders for Client {
+    #[instrument(skip(self), err(Debug))]
+    async fn create_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<AccountProvider, CoreError> {
+        trace!("creating account provider");
+        let id = Uuid::now_v7().to_string();
+
+        let item: Option<DatabaseEntityAccountProvider> = self
+            .client
+            .create((Collection::AccountProvider.to_string(), &id))
+            .content(InputAccountProvider::from(provider))
+            .await
+            .map_err(map_db_error)?;
+        event!(Level::INFO, id = %id, "account provider created");
+
+        match item {
+            Some(e) => AccountProvider::try_from(e),
+            None => Err(CoreError::Unreachable),
+        }
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn update_account_provider(
+        &self,
+        provider: &AccountProvider,
+    ) -> Result<Option<AccountProvider>, CoreError> {
+        trace!("updating account provider");
+        let mut resp = self
+            .client
+            .query("UPDATE type::table($table) SET display_name = $data.display_name, icon_url = $data.icon_url, type = $data.type, enabled = $data.enabled WHERE name = type::string($data.name)")
+            .bind(("table", Collection::AccountProvider))
+            .bind(("data", InputAccountProvider::from(provider)))
+            .await
+            .map_err(map_db_error)?;
+
+        let item: Option<DatabaseEntityAccountProvider> = resp.take(0).map_err(map_db_error)?;
+        event!(
+            Level::INFO,
+            found = item.is_some(),
+            "account provider updated"
+        );
+
+        item.map(AccountProvider::try_from).transpose()
+    }
+}
@@ -0,0 +1,65 @@
+use api_core::{
+    api::{CoreError, MutateApiKeys},
+    reexports::uuid::Uuid,
+    ApiKey, ApiKeyScope,
+};
+use serde::Serialize;
+use surrealdb::sql::{Datetime, Thing};
+use tracing::{event, instrument, trace, Level};
+
+use crate::{
+    collections::Collection,
+    entity::{to_datetime, DatabaseEntityApiKey},
+    map_db_error, Client,
+};
+
+#[derive(Serialize)]
+struct InputApiKey<'a> {
+    name: &'a str,
+    prefix: &'a str,
+    hash: &'a str,
+    scopes: &'a [ApiKeyScope],
+    expires_at: Option<Datetime>,
+}
+
+impl MutateApiKeys for Client {
+    #[instrument(skip(self, key), fields(name = key.name, prefix = key.prefix), err(Debug))]
+    async fn create_api_key(&self, key: &ApiKey) -> Result<ApiKey, CoreError> {
+        trace!("creating api key");
+        let id = Uuid::now_v7().to_string();
+
+        let item: Option<DatabaseEntityApiKey> = self
+            .client
+            .create((Collection::ApiKey.to_string(), &id))
+            .content(InputApiKey {
+                name: &key.name,
+                prefix: &key.prefix,
+                hash: &key.hash,
+                scopes: &key.scopes,
+                expires_at: key.expires_at.map(to_datetime),
+            })
+            .await
+            .map_err(map_db_error)?;
+        event!(Level::INFO, id = %id, "api key created");
+
+        match item {
+            Some(e) => ApiKey::try_from(e),
+            None => Err(CoreError::Unreachable),
+        }
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn revoke_api_key(&self, id: &Uuid) -> Result<Option<ApiKey>, CoreError> {
+        trace!("revoking api key");
+        let id = Thing::from((
+            Collection::ApiKey.to_string().as_str(),
+            id.to_string().as_str(),
+        ));
+
+        let res: Option<DatabaseEntityApiKey> =
+            self.client.delete(id).await.map_err(map_db_error)?;
+        event!(Level::INFO, revoked = res.is_some(), "api key revoked");
+
+        res.map(ApiKey::try_from).transpose()
+    }
+}
@@ -1,22 +1,31 @@
 mod account;
+mod account_provider;
+mod api_key;
 mod session;
+mod verification_token;
 
-use std::str::FromStr;
+use std::{fmt::Debug, str::FromStr};
 
 use api_core::{
     api::{CoreError, MutateUsers},
+    hash_session_token,
     reexports::uuid::Uuid,
-    User, UserType,
+    AccountTokens, DeletedUser, LinkedAccount, Role, Session, User, UserType,
 };
+use serde::Deserialize;
 use surrealdb::sql::{Datetime, Thing};
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use tracing::{error, event, instrument, trace, Level};
 
 use crate::{
     collections::Collection,
-    entity::DatabaseEntityUser,
+    entity::{
+        account_binding, to_datetime, DatabaseEntityAccountTokens, DatabaseEntityLinkedAccount,
+        DatabaseEntitySession, DatabaseEntityUser,
+    },
     map_db_error,
     redis::{cache_keys::CacheKey, PoolLike, PooledConnectionLike},
+    search::IndexOperation,
     Client,
 };
 
@@ -24,7 +33,11 @@ impl MutateUsers for Client {
     #[instrument(skip(self), err(Debug))]
     async fn create_user(&self, user: &User) -> Result<User, CoreError> {
         trace!("creating user");
-        let input_user = InputUser::from(user);
+        let input_user = InputUser {
+            role: Some(user.role),
+            email_verified: user.email_verified.map(to_datetime),
+            ..InputUser::from(user)
+        };
 
         let id = Uuid::now_v7().to_string();
         trace!(id = %id, "generated id");
@@ -40,6 +53,8 @@ impl MutateUsers for Client {
         match item {
             Some(e) => {
                 let user = User::try_from(e)?;
+                self.sync_user_index(user.id, IndexOperation::Upsert(user.clone()))
+                    .await;
 
                 Ok(user)
             }
@@ -62,16 +77,38 @@ impl MutateUsers for Client {
             .expect("date time conversion");
         input_user.updated = Some(Datetime::from_str(&now).expect("date time conversion"));
 
-        let item: Option<DatabaseEntityUser> = self
+        #[derive(Deserialize)]
+        struct Updated {
+            user: Option<DatabaseEntityUser>,
+            previous_email: Option<String>,
+        }
+
+        // a new email has not been verified yet, and the old one has to leave the cache
+        let mut resp = self
             .client
-            .update(id)
-            .merge(input_user)
+            .query(
+                "BEGIN TRANSACTION; \
+                LET $previous = (SELECT VALUE email FROM $id)[0]; \
+                UPDATE $id SET email_verified = NONE WHERE id AND email != $data.email; \
+                LET $updated = (UPDATE $id MERGE $data WHERE id); \
+                RETURN { user: $updated[0], previous_email: $previous }; \
+                COMMIT TRANSACTION;",
+            )
+            .bind(("id", id))
+            .bind(("data", input_user))
             .await
+            .map_err(map_db_error)?
+            .check()
             .map_err(map_db_error)?;
+        let last = resp.num_statements() - 1;
+        let item: Option<Updated> = resp.take(last).map_err(map_db_error)?;
         event!(Level::INFO, "updated user");
 
         let res = match item {
-            Some(e) => {
+            Some(Updated {
+                user: Some(e),
+                previous_email,
+            }) => {
                 let user = User::try_from(e)?;
 
                 if let Some((ref redis, _ttl)) = self.redis {
@@ -85,6 +122,9 @@ impl MutateUsers for Client {
                     let refs = pipeline.del(user_key).del(user_key_2);
 
                     refs.del(user_key_3);
+                    if let Some(email) = previous_email.as_deref().filter(|e| *e != user.email) {
+                        refs.del(CacheKey::UserByEmail { email });
+                    }
 
                     if let Err(e) = redis.query_async_pipeline::<()>(pipeline).await {
                         error!("{e}");
@@ -92,44 +132,204 @@ impl MutateUsers for Client {
                         event!(Level::INFO, keys = ?[user_key, user_key_2, user_key_3], "cache cleared due to update");
                     }
                 }
+                self.sync_user_index(user.id, IndexOperation::Upsert(user.clone()))
+                    .await;
                 Some(user)
             }
-            None => None,
+            _ => None,
         };
 
         Ok(res)
     }
 
     #[instrument(skip(self, id), err(Debug))]
-    async fn delete_user(&self, id: &Uuid) -> Result<Option<User>, CoreError> {
+    async fn delete_user(&self, id: &Uuid) -> Result<Option<DeletedUser>, CoreError> {
         trace!("deleting user");
         let id = Thing::from((
             Collection::User.to_string().as_str(),
             id.to_string().as_ref(),
         ));
 
-        let res: Option<DatabaseEntityUser> = self.client.delete(id).await.map_err(map_db_error)?;
-        event!(Level::INFO, "user deleted");
+        #[derive(Deserialize)]
+        struct Removed {
+            user: Option<DatabaseEntityUser>,
+            accounts: Vec<DatabaseEntityLinkedAccount>,
+            sessions: usize,
+            listings: usize,
+        }
+
+        // the edges go with the user so no session or account is left pointing at nothing
+        let mut resp = self
+            .client
+            .query(format!(
+                "BEGIN TRANSACTION; \
+                LET $accounts = (SELECT out.name AS provider, provider_account_id, linked_at FROM $user->{account} ORDER BY linked_at); \
+                DELETE type::table($account_table) WHERE in = $user; \
+                LET $sessions = (DELETE type::table($session_table) WHERE in = $user RETURN BEFORE); \
+                LET $listings = (DELETE type::table($sells_table) WHERE in = $user RETURN BEFORE); \
+                LET $deleted = (DELETE $user RETURN BEFORE); \
+                RETURN {{ user: $deleted[0], accounts: $accounts, sessions: count($sessions), listings: count($listings) }}; \
+                COMMIT TRANSACTION;",
+                account = Collection::UserAccount,
+            ))
+            .bind(("user", id))
+            .bind(("account_table", Collection::UserAccount))
+            .bind(("session_table", Collection::UserSession))
+            .bind(("sells_table", Collection::Sells))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
+
+        let last = resp.num_statements() - 1;
+        let resp: Option<Removed> = resp.take(last).map_err(map_db_error)?;
+        let Some(Removed {
+            user: Some(user),
+            accounts,
+            sessions,
+            listings,
+        }) = resp
+        else {
+            return Ok(None);
+        };
+        event!(
+            Level::INFO,
+            accounts = accounts.len(),
+            sessions,
+            listings,
+            "user deleted"
+        );
+
+        let user = User::try_from(user)?;
+        let accounts: Vec<LinkedAccount> = accounts.into_iter().map(LinkedAccount::from).collect();
+        if let Some((ref redis, _ttl)) = self.redis {
+            let user_key = CacheKey::UserById { id: &user.id };
+            let user_key_2 = CacheKey::AllUsers;
+            let user_key_3 = CacheKey::UserByEmail { email: &user.email };
+
+            let mut redis = redis.get().await.expect("cache from pool");
+            let mut pipeline = redis::Pipeline::new();
+            let refs = pipeline.del(user_key).del(user_key_2);
+
+            trace!(keys = ?[user_key, user_key_2, user_key_3], "resetting cache");
+            refs.del(user_key_3);
+            for account in &accounts {
+                refs.del(CacheKey::UserByAccount {
+                    provider: &account.provider,
+                    provider_account_id: &account.provider_account_id,
+                });
+            }
+
+            if let Err(e) = redis.query_async_pipeline::<()>(pipeline).await {
+                error!("{e}");
+            }
+        }
+        self.sync_user_index(user.id, IndexOperation::Delete).await;
+
+        Ok(Some(DeletedUser {
+            user,
+            accounts,
+            sessions,
+            listings,
+        }))
+    }
+
+    #[instrument(skip(self, id), err(Debug))]
+    async fn set_user_role(&self, id: &Uuid, role: Role) -> Result<Option<User>, CoreError> {
+        trace!("setting user role");
+        let id = Thing::from((
+            Collection::User.to_string().as_str(),
+            id.to_string().as_str(),
+        ));
+
+        let now = OffsetDateTime::now_utc()
+            .format(&Rfc3339)
+            .expect("date time conversion");
 
-        let res = match res {
+        // the condition keeps the update from creating a record for an unknown id
+        let mut resp = self
+            .client
+            .query("UPDATE $id MERGE $data WHERE id")
+            .bind(("id", id))
+            .bind((
+                "data",
+                InputRole {
+                    role,
+                    updated: Datetime::from_str(&now).expect("date time conversion"),
+                },
+            ))
+            .await
+            .map_err(map_db_error)?;
+        let item: Option<DatabaseEntityUser> = resp.take(0).map_err(map_db_error)?;
+        event!(Level::INFO, ?role, "user role set");
+
+        let res = match item {
             Some(e) => {
                 let user = User::try_from(e)?;
+
                 if let Some((ref redis, _ttl)) = self.redis {
                     let user_key = CacheKey::UserById { id: &user.id };
                     let user_key_2 = CacheKey::AllUsers;
                     let user_key_3 = CacheKey::UserByEmail { email: &user.email };
+                    trace!(keys = ?[user_key, user_key_2, user_key_3], "resetting cache");
 
                     let mut redis = redis.get().await.expect("cache from pool");
                     let mut pipeline = redis::Pipeline::new();
-                    let refs = pipeline.del(user_key).del(user_key_2);
+                    pipeline.del(user_key).del(user_key_2).del(user_key_3);
 
+                    if let Err(e) = redis.query_async_pipeline::<()>(pipeline).await {
+                        error!("{e}");
+                    }
+                }
+                self.sync_user_index(user.id, IndexOperation::Upsert(user.clone()))
+                    .await;
+                Some(user)
+            }
+            None => None,
+        };
+
+        Ok(res)
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn set_email_verified(
+        &self,
+        email: impl AsRef<str> + Send + Debug,
+        verified_at: &OffsetDateTime,
+    ) -> Result<Option<User>, CoreError> {
+        trace!("setting email verified");
+
+        let mut resp = self
+            .client
+            .query("UPDATE type::table($table) SET email_verified = $verified_at, updated = time::now() WHERE email = type::string($email)")
+            .bind(("table", Collection::User))
+            .bind(("email", email.as_ref()))
+            .bind(("verified_at", to_datetime(*verified_at)))
+            .await
+            .map_err(map_db_error)?;
+        let item: Option<DatabaseEntityUser> = resp.take(0).map_err(map_db_error)?;
+        event!(Level::INFO, found = item.is_some(), "email verified");
+
+        let res = match item {
+            Some(e) => {
+                let user = User::try_from(e)?;
+
+                if let Some((ref redis, _ttl)) = self.redis {
+                    let user_key = CacheKey::UserById { id: &user.id };
+                    let user_key_2 = CacheKey::AllUsers;
+                    let user_key_3 = CacheKey::UserByEmail { email: &user.email };
                     trace!(keys = ?[user_key, user_key_2, user_key_3], "resetting cache");
-                    refs.del(user_key_3);
+
+                    let mut redis = redis.get().await.expect("cache from pool");
+                    let mut pipeline = redis::Pipeline::new();
+                    pipeline.del(user_key).del(user_key_2).del(user_key_3);
 
                     if let Err(e) = redis.query_async_pipeline::<()>(pipeline).await {
                         error!("{e}");
                     }
                 }
+                self.sync_user_index(user.id, IndexOperation::Upsert(user.clone()))
+                    .await;
                 Some(user)
             }
             None => None,
@@ -137,6 +337,107 @@ impl MutateUsers for Client {
 
         Ok(res)
     }
+
+    #[instrument(skip(self, tokens, session), err(Debug))]
+    async fn register_user(
+        &self,
+        user: &User,
+        provider: impl AsRef<str> + Send + Debug + Sync,
+        provider_account_id: impl AsRef<str> + Send + Debug + Sync,
+        tokens: &AccountTokens,
+        session: &Session,
+    ) -> Result<(User, Session), CoreError> {
+        trace!("registering user");
+        let provider = provider.as_ref();
+        let provider_account_id = provider_account_id.as_ref();
+        let account_provider = self.enabled_account_provider(provider).await?;
+        let tokens = DatabaseEntityAccountTokens::encrypt(
+            tokens,
+            self.token_cipher.as_ref(),
+            &account_binding(provider, provider_account_id),
+        )?;
+
+        let input_user = InputUser {
+            role: Some(user.role),
+            email_verified: user.email_verified.map(to_datetime),
+            ..InputUser::from(user)
+        };
+        let user_id = Thing::from((
+            Collection::User.to_string().as_str(),
+            Uuid::now_v7().to_string().as_str(),
+        ));
+        let session_id = Uuid::now_v7().to_string();
+        let created = OffsetDateTime::now_utc();
+
+        #[derive(Deserialize)]
+        struct Registered {
+            user: Option<DatabaseEntityUser>,
+            session: Option<DatabaseEntitySession>,
+        }
+
+        // the user, account and session are committed together, an account that is already
+        // linked skips every write instead of failing the transaction so it can be reported
+        let mut resp = self
+            .client
+            .query(format!(
+                "BEGIN TRANSACTION; \
+                LET $linked = (SELECT id FROM type::table($account_table) WHERE provider_account_id = type::string($provider_account_id) AND out = $provider LIMIT 1); \
+                IF !$linked {{ \
+                    CREATE $user CONTENT $data; \
+                    RELATE $user -> {account} -> $provider SET provider_account_id = type::string($provider_account_id), tokens = $tokens; \
+                    RELATE $user -> {session} -> $provider SET id = type::string($session_id), session_token = type::string($session_token), expires_at = $expires, created = $created, user_agent = $user_agent, ip_address = $ip_address, device_label = $device_label; \
+                }}; \
+                RETURN {{ user: (SELECT * FROM ONLY $user), session: (SELECT * FROM ONLY type::thing($session_table, $session_id) FETCH out) }}; \
+                COMMIT TRANSACTION;",
+                account = Collection::UserAccount,
+                session = Collection::UserSession,
+            ))
+            .bind(("account_table", Collection::UserAccount))
+            .bind(("session_table", Collection::UserSession))
+            .bind(("provider", account_provider.id))
+            .bind(("provider_account_id", provider_account_id))
+            .bind(("user", user_id))
+            .bind(("data", input_user))
+            .bind(("tokens", tokens))
+            .bind(("session_id", session_id))
+            .bind(("session_token", hash_session_token(&session.session_token)))
+            .bind((
+                "expires",
+                to_datetime(self.session_policy.clamp(created, session.expires_at)),
+            ))
+            .bind(("created", to_datetime(created)))
+            .bind(("user_agent", &session.user_agent))
+            .bind(("ip_address", &session.ip_address))
+            .bind(("device_label", &session.device_label))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
+
+        let last = resp.num_statements() - 1;
+        let resp: Option<Registered> = resp.take(last).map_err(map_db_error)?;
+        let (user, session) = match resp {
+            Some(Registered {
+                user: Some(user),
+                session: Some(session),
+            }) => (
+                User::try_from(user)?,
+                session.into_session(&self.session_policy)?,
+            ),
+            Some(_) => {
+                return Err(CoreError::Database(format!(
+                    "account `{provider_account_id}` of `{provider}` is already linked"
+                )))
+            }
+            None => return Err(CoreError::Unreachable),
+        };
+        event!(Level::INFO, id = %user.id, "user registered");
+
+        self.sync_user_index(user.id, IndexOperation::Upsert(user.clone()))
+            .await;
+
+        Ok((user, session))
+    }
 }
 
 #[derive(serde::Serialize)]
@@ -147,6 +448,12 @@ struct InputUser<'a> {
     avatar: Option<&'a str>,
     #[serde(rename = "type")]
     user_type: UserType,
+    /// Only set on creation, roles change through `set_user_role`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<Role>,
+    /// Only set on creation, afterwards through `set_email_verified`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email_verified: Option<Datetime>,
     updated: Option<Datetime>,
 }
 
@@ -158,7 +465,15 @@ impl<'a> From<&'a User> for InputUser<'a> {
             name: value.name.as_deref(),
             avatar: value.avatar.as_deref(),
             user_type: value.user_type,
+            role: None,
+            email_verified: None,
             updated: None,
         }
     }
 }
+
+#[derive(serde::Serialize)]
+struct InputRole {
+    role: Role,
+    updated: Datetime,
+}
@@ -1,6 +1,6 @@
 use api_core::{
     api::{CoreError, MutateSessions},
-    Session,
+    hash_session_token, Session,
 };
 use serde::{Deserialize, Serialize};
 use std::{fmt::Debug, str::FromStr};
@@ -12,11 +12,15 @@ use time::{format_description::well_known::Rfc3339, OffsetDateTime};
 use tracing::{debug, instrument, warn};
 use uuid::Uuid;
 
-use crate::{collections::Collection, entity::DatabaseEntitySession, map_db_error, Client};
+use crate::{
+    collections::Collection,
+    entity::{deserialize_date_time, to_datetime, DatabaseEntitySession},
+    map_db_error, Client,
+};
 
 impl MutateSessions for Client {
-    #[instrument(skip(self), err(Debug))]
-    async fn create_session(&self, session: &Session) -> Result<(), CoreError> {
+    #[instrument(skip(self, session), err(Debug))]
+    async fn create_session(&self, session: &Session) -> Result<Session, CoreError> {
         let create_id = |id: &Uuid| -> Thing {
             Thing::from((
                 Collection::User.to_string().as_str(),
@@ -28,14 +32,15 @@ impl MutateSessions for Client {
 
         let stmt = Collection::UserSession;
 
-        let mut resp = self
-            .client
-            .query("SELECT VALUE id FROM type::table($account_provider_table) WHERE name = type::string($provider)")
-            .bind(("account_provider_table",Collection::AccountProvider))
-            .bind(("provider", &session.account_provider.name)).await.map_err(map_db_error)?;
+        let id = self
+            .enabled_account_provider(&session.account_provider.name)
+            .await?
+            .id;
 
-        let dt = session
-            .expires_at
+        let created = OffsetDateTime::now_utc();
+        let dt = self
+            .session_policy
+            .clamp(created, session.expires_at)
             .format(&Rfc3339)
             .map_err(|e| CoreError::Other(e.to_string()))
             .map(|val| {
@@ -44,53 +49,76 @@ impl MutateSessions for Client {
                 })
             })??;
 
-        let id: Option<Thing> = resp.take(0).map_err(map_db_error)?;
-        if let Some(id) = id {
-            self.client.query(format!("RELATE {user_id} -> {stmt} -> {id} SET session_token = type::string($session_token), expires_at = <datetime>type::datetime($expires);"))
-            .bind(("session_token", &session.session_token))
-            .bind(("expires", dt)).await.map_err(map_db_error)?;
-        } else {
-            warn!("session not created");
-        };
+        let mut resp = self.client.query(format!("RELATE {user_id} -> {stmt} -> {id} SET id = type::string($id), session_token = type::string($session_token), expires_at = <datetime>type::datetime($expires), created = $created, user_agent = $user_agent, ip_address = $ip_address, device_label = $device_label; SELECT * FROM type::thing($table, $id) FETCH out;"))
+            .bind(("id", Uuid::now_v7().to_string()))
+            .bind(("table", stmt))
+            .bind(("session_token", hash_session_token(&session.session_token)))
+            .bind(("expires", dt))
+            .bind(("created", to_datetime(created)))
+            .bind(("user_agent", &session.user_agent))
+            .bind(("ip_address", &session.ip_address))
+            .bind(("device_label", &session.device_label))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
 
-        Ok(())
+        let resp: Option<DatabaseEntitySession> = resp.take(1).map_err(map_db_error)?;
+        match resp {
+            Some(session) => session.into_session(&self.session_policy),
+            None => Err(CoreError::Unreachable),
+        }
     }
 
-    #[instrument(skip(self), err(Debug))]
+    #[instrument(skip(self, id), err(Debug))]
     async fn update_session(
         &self,
         id: impl AsRef<str> + Send + Debug,
         expires_at: &OffsetDateTime,
     ) -> Result<Option<Session>, CoreError> {
-        #[derive(Serialize, Deserialize)]
+        #[derive(Serialize)]
         struct Document {
-            expires_at: OffsetDateTime,
+            expires_at: Datetime,
         }
 
-        let session_token = id.as_ref();
+        let session_token = hash_session_token(id.as_ref());
+
+        #[derive(Deserialize)]
+        struct Found {
+            id: RecordId,
+            #[serde(deserialize_with = "deserialize_date_time")]
+            created: OffsetDateTime,
+        }
 
         let mut resp = self
             .client
             .query(
-                "SELECT VALUE id FROM type::table($table) WHERE session_token = type::string($session_token) LIMIT 1",
+                "SELECT id, created FROM type::table($table) WHERE session_token = type::string($session_token) AND expires_at > time::now() LIMIT 1",
             ).bind(("table", Collection::UserSession)).bind(("session_token", session_token))
             .await
             .map_err(map_db_error)?;
 
-        let resp: Option<RecordId> = resp.take(0).map_err(map_db_error)?;
+        let resp: Option<Found> = resp.take(0).map_err(map_db_error)?;
 
         if let Some(session) = resp {
-            let resp: Option<DatabaseEntitySession> = self
+            let mut resp = self
                 .client
-                .update(session)
-                .merge(Document {
-                    expires_at: expires_at.to_owned(),
-                })
+                .query("UPDATE $record MERGE $data; SELECT * FROM $record FETCH out")
+                .bind(("record", &session.id))
+                .bind((
+                    "data",
+                    Document {
+                        expires_at: to_datetime(
+                            self.session_policy.clamp(session.created, *expires_at),
+                        ),
+                    },
+                ))
                 .await
                 .map_err(map_db_error)?;
+            let resp: Option<DatabaseEntitySession> = resp.take(1).map_err(map_db_error)?;
 
             let res = match resp {
-                Some(res) => Some(Session::try_from(res)?),
+                Some(res) => Some(res.into_session(&self.session_policy)?),
                 None => None,
             };
 
@@ -103,13 +131,13 @@ impl MutateSessions for Client {
         }
     }
 
-    #[instrument(skip(self), err(Debug))]
+    #[instrument(skip(self, id), err(Debug))]
     async fn delete_session(&self, id: impl AsRef<str> + Send + Debug) -> Result<(), CoreError> {
         self
             .client
             .query("DELETE FROM type::table($table) WHERE session_token = type::string($session_token)")
             .bind(("table", Collection::UserSession))
-            .bind(("session_token", id.as_ref()))
+            .bind(("session_token", hash_session_token(id.as_ref())))
             .await
             .map_err(map_db_error)?;
 
@@ -140,6 +168,29 @@ impl MutateSessions for Client {
         Ok(())
     }
 
+    #[instrument(skip(self), err(Debug))]
+    async fn revoke_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError> {
+        let mut resp = self
+            .client
+            .query("SELECT * FROM type::thing($table, $id) FETCH out; DELETE type::thing($table, $id);")
+            .bind(("table", Collection::UserSession))
+            .bind(("id", id.to_string()))
+            .await
+            .map_err(map_db_error)?
+            .check()
+            .map_err(map_db_error)?;
+
+        let resp: Option<DatabaseEntitySession> = resp.take(0).map_err(map_db_error)?;
+        let res = match resp {
+            Some(session) => Some(session.into_session(&self.session_policy)?),
+            None => None,
+        };
+
+        debug!(revoked = res.is_some(), "session revoked");
+
+        Ok(res)
+    }
+
     #[instrument(skip(self), err(Debug))]
     async fn delete_expired_sessions(&self) -> Result<(), CoreError> {
         self.client
@@ -152,4 +203,21 @@ impl MutateSessions for Client {
 
         Ok(())
     }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn purge_expired_sessions(&self, batch_size: usize) -> Result<usize, CoreError> {
+        let purged: Vec<Thing> = self
+            .client
+            .query("DELETE (SELECT VALUE id FROM type::table($table) WHERE expires_at <= time::now() LIMIT $limit) RETURN BEFORE")
+            .bind(("table", Collection::UserSession))
+            .bind(("limit", batch_size))
+            .await
+            .map_err(map_db_error)?
+            .take((0, "id"))
+            .map_err(map_db_error)?;
+
+        debug!(count = purged.len(), "expired sessions purged");
+
+        Ok(purged.len())
+    }
 }
@@ -0,0 +1,80 @@
+use std::fmt::Debug;
+
+use api_core::{
+    api::{CoreError, MutateVerificationTokens},
+    hash_verification_token,
+    reexports::uuid::Uuid,
+    VerificationToken,
+};
+use serde::Serialize;
+use surrealdb::sql::Datetime;
+use time::OffsetDateTime;
+use tracing::{event, instrument, trace, Level};
+
+use crate::{
+    collections::Collection,
+    entity::{to_datetime, DatabaseEntityVerificationToken},
+    map_db_error, Client,
+};
+
+#[derive(Serialize)]
+struct InputVerificationToken<'a> {
+    identifier: &'a str,
+    token: String,
+    expires_at: Datetime,
+}
+
+impl MutateVerificationTokens for Client {
+    #[instrument(skip(self, token), fields(identifier = token.identifier), err(Debug))]
+    async fn create_verification_token(
+        &self,
+        token: &VerificationToken,
+    ) -> Result<VerificationToken, CoreError> {
+        trace!("creating verification token");
+        let id = Uuid::now_v7().to_string();
+
+        let item: Option<DatabaseEntityVerificationToken> = self
+            .client
+            .create((Collection::VerificationToken.to_string(), &id))
+            .content(InputVerificationToken {
+                identifier: &token.identifier,
+                token: hash_verification_token(&token.token),
+                expires_at: to_datetime(token.expires_at),
+            })
+            .await
+            .map_err(map_db_error)?;
+        event!(Level::INFO, id = %id, "verification token created");
+
+        match item {
+            Some(e) => Ok(VerificationToken::from(e)),
+            None => Err(CoreError::Unreachable),
+        }
+    }
+
+    #[instrument(skip(self, token), err(Debug))]
+    async fn use_verification_token(
+        &self,
+        identifier: impl AsRef<str> + Send + Debug,
+        token: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<VerificationToken>, CoreError> {
+        trace!("using verification token");
+
+        // deleting in a single statement means only one caller gets the token back
+        let mut resp = self
+            .client
+            .query("DELETE type::table($table) WHERE identifier = type::string($identifier) AND token = type::string($verification_token) RETURN BEFORE")
+            .bind(("table", Collection::VerificationToken))
+            .bind(("identifier", identifier.as_ref()))
+            .bind(("verification_token", hash_verification_token(token.as_ref())))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Option<DatabaseEntityVerificationToken> = resp.take(0).map_err(map_db_error)?;
+        let res = res
+            .map(VerificationToken::from)
+            .filter(|token| !token.is_expired(OffsetDateTime::now_utc()));
+        event!(Level::INFO, used = res.is_some(), "verification token used");
+
+        Ok(res)
+    }
+}
@@ -0,0 +1,68 @@
+use std::fmt::Debug;
+
+use api_core::{
+    api::{CoreError, QueryAccounts},
+    reexports::uuid::Uuid,
+    Account, LinkedAccount,
+};
+use surrealdb::sql::Thing;
+use tracing::{event, instrument, trace, Level};
+
+use crate::{
+    collections::Collection,
+    entity::{DatabaseEntityAccount, DatabaseEntityLinkedAccount},
+    map_db_error, Client,
+};
+
+impl QueryAccounts for Client {
+    #[instrument(skip(self), err(Debug))]
+    async fn get_account(
+        &self,
+        provider: impl AsRef<str> + Send + Debug,
+        provider_account_id: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<Account>, CoreError> {
+        trace!("getting account");
+        let mut resp = self
+            .client
+            .query("SELECT in, out.name AS provider, provider_account_id, tokens FROM type::table($table) WHERE provider_account_id = type::string($provider_account_id) AND out.name = type::string($provider) LIMIT 1")
+            .bind(("table", Collection::UserAccount))
+            .bind(("provider", provider.as_ref()))
+            .bind(("provider_account_id", provider_account_id.as_ref()))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Option<DatabaseEntityAccount> = resp.take(0).map_err(map_db_error)?;
+        event!(Level::INFO, found = res.is_some(), "account queried");
+
+        res.map(|account| account.into_account(self.token_cipher.as_ref()))
+            .transpose()
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_user_accounts(
+        &self,
+        user_id: &Uuid,
+    ) -> Result<impl ExactSizeIterator<Item = LinkedAccount>, CoreError> {
+        trace!("getting user accounts");
+        let user = Thing::from((
+            Collection::User.to_string().as_str(),
+            user_id.to_string().as_str(),
+        ));
+
+        let mut resp = self
+            .client
+            .query(format!("SELECT out.name AS provider, provider_account_id, linked_at FROM $user->{} ORDER BY linked_at", Collection::UserAccount))
+            .bind(("user", user))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Vec<DatabaseEntityLinkedAccount> = resp.take(0).map_err(map_db_error)?;
+        event!(Level::INFO, accounts = res.len(), "found user accounts");
+
+        Ok(res
+            .into_iter()
+            .map(LinkedAccount::from)
+            .collect::<Vec<_>>()
+            .into_iter())
+    }
+}
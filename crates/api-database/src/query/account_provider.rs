@@ -0,0 +1,78 @@
+use std::fmt::Debug;
+
+use api_core::{
+    api::{CoreError, QueryAccountProviders},
+    AccountProvider,
+};
+use tracing::{event, instrument, trace, Level};
+
+use crate::{collections::Collection, entity::DatabaseEntityAccountProvider, map_db_error, Client};
+
+impl QueryAccountProviders for Client {
+    #[instrument(skip(self), err(Debug))]
+    async fn get_account_providers(
+        &self,
+    ) -> Result<impl ExactSizeIterator<Item = AccountProvider>, CoreError> {
+        trace!("getting account providers");
+        let mut resp = self
+            .client
+            .query("SELECT * FROM type::table($table) ORDER BY name")
+            .bind(("table", Collection::AccountProvider))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Vec<DatabaseEntityAccountProvider> = resp.take(0).map_err(map_db_error)?;
+        event!(
+            Level::INFO,
+            providers = res.len(),
+            "found account providers"
+        );
+
+        let res = res
+            .into_iter()
+            .map(AccountProvider::try_from)
+            .collect::<Result<Vec<AccountProvider>, CoreError>>()?;
+
+        Ok(res.into_iter())
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_account_provider(
+        &self,
+        name: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<AccountProvider>, CoreError> {
+        trace!("getting account provider");
+        let res = self.account_provider_entity(name.as_ref()).await?;
+
+        res.map(AccountProvider::try_from).transpose()
+    }
+}
+
+impl Client {
+    pub(crate) async fn account_provider_entity(
+        &self,
+        name: &str,
+    ) -> Result<Option<DatabaseEntityAccountProvider>, CoreError> {
+        let mut resp = self
+            .client
+            .query("SELECT * FROM type::table($table) WHERE name = type::string($name) LIMIT 1")
+            .bind(("table", Collection::AccountProvider))
+            .bind(("name", name))
+            .await
+            .map_err(map_db_error)?;
+
+        resp.take(0).map_err(map_db_error)
+    }
+
+    /// The provider accounts can be linked and sessions created through
+    pub(crate) async fn enabled_account_provider(
+        &self,
+        name: &str,
+    ) -> Result<DatabaseEntityAccountProvider, CoreError> {
+        match self.account_provider_entity(name).await? {
+            Some(provider) if provider.enabled => Ok(provider),
+            Some(_) => Err(CoreError::ProviderDisabled(name.to_owned())),
+            None => Err(CoreError::UnknownProvider(name.to_owned())),
+        }
+    }
+}
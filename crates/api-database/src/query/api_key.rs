@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+
+use api_core::{
+    api::{CoreError, QueryApiKeys},
+    ApiKey,
+};
+use tracing::{event, instrument, trace, Level};
+
+use crate::{collections::Collection, entity::DatabaseEntityApiKey, map_db_error, Client};
+
+impl QueryApiKeys for Client {
+    #[instrument(skip(self), err(Debug))]
+    async fn get_api_keys(&self) -> Result<impl ExactSizeIterator<Item = ApiKey>, CoreError> {
+        trace!("getting api keys");
+        let res: Vec<DatabaseEntityApiKey> = self
+            .client
+            .select(Collection::ApiKey)
+            .await
+            .map_err(map_db_error)?;
+        event!(Level::INFO, keys = %res.len(), "found api keys from db");
+
+        let res = res
+            .into_iter()
+            .map(ApiKey::try_from)
+            .collect::<Result<Vec<ApiKey>, CoreError>>()?;
+
+        Ok(res.into_iter())
+    }
+
+    #[instrument(skip(self, hash), err(Debug))]
+    async fn get_api_key_by_hash(
+        &self,
+        hash: impl AsRef<str> + Send + Debug,
+    ) -> Result<Option<ApiKey>, CoreError> {
+        trace!("getting api key");
+        let mut resp = self
+            .client
+            .query("SELECT * FROM type::table($table) WHERE hash = type::string($hash) LIMIT 1")
+            .bind(("table", Collection::ApiKey))
+            .bind(("hash", hash.as_ref()))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Option<DatabaseEntityApiKey> = resp.take(0).map_err(map_db_error)?;
+
+        res.map(ApiKey::try_from).transpose()
+    }
+}
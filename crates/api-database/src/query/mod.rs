@@ -1,2 +1,5 @@
+mod account;
+mod account_provider;
+mod api_key;
 mod session;
 mod users;
@@ -24,7 +24,7 @@ impl QuerySessions for Client {
 
         let mut resp = self
             .client
-            .query("SELECT * FROM type::table($table) WHERE in = type::record($user)")
+            .query("SELECT * FROM type::table($table) WHERE in = $user AND expires_at > time::now() FETCH out")
             .bind(("table", Collection::UserSession))
             .bind(("user", create_id(user_id)))
             .await
@@ -36,9 +36,31 @@ impl QuerySessions for Client {
 
         let res = res
             .into_iter()
-            .map(Session::try_from)
+            .map(|session| session.into_session(&self.session_policy))
             .collect::<Result<Vec<Session>, CoreError>>()?;
 
         Ok(res.into_iter())
     }
+
+    #[tracing::instrument(skip(self))]
+    async fn get_session(&self, id: &Uuid) -> Result<Option<Session>, CoreError> {
+        trace!("getting session");
+
+        let mut resp = self
+            .client
+            .query(
+                "SELECT * FROM type::thing($table, $id) WHERE expires_at > time::now() FETCH out",
+            )
+            .bind(("table", Collection::UserSession))
+            .bind(("id", id.to_string()))
+            .await
+            .map_err(map_db_error)?;
+
+        let res: Option<DatabaseEntitySession> = resp.take(0).map_err(map_db_error)?;
+
+        match res {
+            Some(session) => Ok(Some(session.into_session(&self.session_policy)?)),
+            None => Ok(None),
+        }
+    }
 }
@@ -1,28 +1,35 @@
 use api_core::{
-    api::{CoreError, QueryUsers},
+    api::{CoreError, Direction, Page, Paged, QueryUsers},
+    hash_session_token,
     reexports::uuid::Uuid,
-    Session, User,
+    FacetCount, Session, User, UserFilter, UserHighlight, UserOrderBy, UserSearch,
+    UserSearchFacets, UserSearchHit, UserSearchResults,
 };
-use meilisearch_sdk::search::{SearchQuery, SearchResults};
+use meilisearch_sdk::search::{SearchQuery, SearchResults, Selectors};
 use serde::{Deserialize, Serialize};
-use std::fmt::Debug;
+use std::{collections::HashMap, fmt::Debug};
 use surrealdb::sql::Thing;
-use time::{format_description::well_known::Iso8601, OffsetDateTime};
+use time::OffsetDateTime;
 use tracing::{debug, error, event, instrument, trace, Level};
 
 use crate::{
     collections::Collection,
-    entity::{record_id_to_uuid, DatabaseEntityAccountProvider, DatabaseEntityUser},
+    entity::{
+        deserialize_date_time, deserialize_optional_date_time, record_id_to_uuid, to_datetime,
+        DatabaseEntityAccountProvider, DatabaseEntityUser,
+    },
     map_db_error,
     redis::{cache_keys::CacheKey, redis_query},
+    search::{
+        map_search_error, search_facets, search_filter, search_hit, IndexedUser, FACET_USER_TYPE,
+        HIGHLIGHTED_ATTRIBUTES,
+    },
+    session_policy::LAST_SEEN_RESOLUTION,
     Client,
 };
 
 #[tracing::instrument(skip(db))]
-async fn db_get_users(
-    db: &Client,
-    wait_for_completion: bool,
-) -> Result<std::vec::IntoIter<User>, CoreError> {
+async fn db_get_users(db: &Client) -> Result<std::vec::IntoIter<User>, CoreError> {
     event!(Level::TRACE, "getting all users");
     let users = if let Some((ref redis, _ttl)) = db.redis {
         let cache_key = CacheKey::AllUsers;
@@ -76,34 +83,272 @@ async fn db_get_users(
         users
     };
 
-    if let Some(ref client) = db.search_client {
-        trace!("indexing users for search");
-        let index = client.index("users");
-        let pk = "id";
-        trace!(primary_key = pk, "adding documents");
+    Ok(users.into_iter())
+}
 
-        let task = index
-            .add_documents(&users, Some(pk))
-            .await
-            .map_err(|e| CoreError::Other(e.to_string()))?;
+/// Full-text search through the database indexes, for deployments without a search server
+#[tracing::instrument(skip(db))]
+async fn db_search(db: &Client, search: &UserSearch) -> Result<UserSearchResults, CoreError> {
+    trace!("searching users in database");
+    let mut conditions = vec!["(username @0@ $query OR name @1@ $query)"];
+    if search.filter.user_type.is_some() {
+        conditions.push("type = $user_type");
+    }
+    if search.filter.created_after.is_some() {
+        conditions.push("created > $created_after");
+    }
+    if search.filter.created_before.is_some() {
+        conditions.push("created < $created_before");
+    }
+    let conditions = where_clause(&conditions);
+
+    let mut response = db
+        .client
+        .query(format!(
+            "SELECT search::score(0) + search::score(1) AS score, \
+             search::highlight('<em>', '</em>', 0) AS username_highlight, \
+             search::highlight('<em>', '</em>', 1) AS name_highlight, \
+             search::offsets(0) AS username_offsets, search::offsets(1) AS name_offsets, \
+             $this AS user FROM type::table($table) {conditions} \
+             ORDER BY score DESC LIMIT $limit START $offset"
+        ))
+        .query(format!(
+            "SELECT count() AS count FROM type::table($table) {conditions} GROUP ALL"
+        ))
+        .query(format!(
+            "SELECT type, count() AS count FROM type::table($table) {conditions} GROUP BY type"
+        ))
+        .bind(("table", Collection::User))
+        .bind(("query", &search.query))
+        .bind(("limit", search.limit))
+        .bind(("offset", search.offset))
+        .bind(("user_type", search.filter.user_type))
+        .bind((
+            "created_after",
+            search.filter.created_after.map(to_datetime),
+        ))
+        .bind((
+            "created_before",
+            search.filter.created_before.map(to_datetime),
+        ))
+        .await
+        .map_err(map_db_error)?;
+    event!(Level::DEBUG, "database queried");
+
+    #[derive(Deserialize)]
+    struct Offset {
+        s: usize,
+        e: usize,
+    }
 
-        if wait_for_completion {
-            if let Err(e) = task.wait_for_completion(client, None, None).await {
-                error!("{e}");
-            } else {
-                event!(Level::INFO, user_count = %users.len(), primary_key = pk, "finished meilisearch indexing for users");
-            }
-        }
-        event!(Level::INFO, user_count = %users.len(), primary_key = pk, "started meilisearch indexing for users");
+    #[derive(Deserialize)]
+    struct Hit {
+        user: DatabaseEntityUser,
+        username_highlight: Option<String>,
+        name_highlight: Option<String>,
+        username_offsets: Option<HashMap<String, Vec<Offset>>>,
+        name_offsets: Option<HashMap<String, Vec<Offset>>>,
     }
 
-    Ok(users.into_iter())
+    #[derive(Deserialize)]
+    struct TypeCount {
+        #[serde(rename = "type")]
+        value: String,
+        count: usize,
+    }
+
+    let hits: Vec<Hit> = response.take(0).map_err(map_db_error)?;
+    let total_hits: Option<usize> = response.take((1, "count")).map_err(map_db_error)?;
+    let mut type_counts: Vec<TypeCount> = response.take(2).map_err(map_db_error)?;
+    type_counts.sort_by(|a, b| a.value.cmp(&b.value));
+
+    // offsets count characters rather than bytes
+    let terms = |value: &str, offsets: Option<HashMap<String, Vec<Offset>>>| -> Vec<String> {
+        offsets
+            .into_iter()
+            .flat_map(HashMap::into_values)
+            .flatten()
+            .map(|offset| {
+                value
+                    .chars()
+                    .skip(offset.s)
+                    .take(offset.e.saturating_sub(offset.s))
+                    .collect::<String>()
+                    .to_lowercase()
+            })
+            .collect()
+    };
+
+    trace!("mapping entities");
+    let hits = hits
+        .into_iter()
+        .map(|hit| {
+            let user = User::try_from(hit.user)?;
+
+            let mut matched_terms = terms(&user.username, hit.username_offsets);
+            if let Some(ref name) = user.name {
+                matched_terms.extend(terms(name, hit.name_offsets));
+            }
+            matched_terms.sort();
+            matched_terms.dedup();
+
+            Ok(UserSearchHit {
+                highlight: UserHighlight {
+                    username: hit
+                        .username_highlight
+                        .unwrap_or_else(|| user.username.clone()),
+                    name: hit.name_highlight.or_else(|| user.name.clone()),
+                },
+                matched_terms,
+                user,
+            })
+        })
+        .collect::<Result<Vec<_>, CoreError>>()?;
+    event!(Level::INFO, hits = hits.len(), "query results");
+
+    Ok(UserSearchResults {
+        hits,
+        total_hits: total_hits.unwrap_or_default(),
+        offset: search.offset,
+        limit: search.limit,
+        facets: UserSearchFacets {
+            user_type: type_counts
+                .into_iter()
+                .map(|facet| FacetCount {
+                    value: facet.value,
+                    count: facet.count,
+                })
+                .collect(),
+        },
+    })
+}
+
+fn where_clause(conditions: &[&str]) -> String {
+    if conditions.is_empty() {
+        String::new()
+    } else {
+        format!("WHERE {}", conditions.join(" AND "))
+    }
 }
 
 impl QueryUsers for Client {
     #[instrument(skip(self), err(Debug))]
     async fn get_users(&self) -> Result<impl ExactSizeIterator<Item = User>, CoreError> {
-        db_get_users(self, false).await
+        db_get_users(self).await
+    }
+
+    #[instrument(skip(self), err(Debug))]
+    async fn get_users_page(
+        &self,
+        page: Page,
+        filter: &UserFilter,
+        order_by: UserOrderBy,
+    ) -> Result<Paged<User>, CoreError> {
+        trace!("getting users page");
+        let mut conditions = vec![];
+        if filter.user_type.is_some() {
+            conditions.push("type = $user_type");
+        }
+        if filter.created_after.is_some() {
+            conditions.push("created > $created_after");
+        }
+        if filter.created_before.is_some() {
+            conditions.push("created < $created_before");
+        }
+        if filter.updated_after.is_some() {
+            conditions.push("updated > $updated_after");
+        }
+        if filter.updated_before.is_some() {
+            conditions.push("updated < $updated_before");
+        }
+        match filter.has_phone_number {
+            Some(true) => conditions.push("(phone_number != NONE AND phone_number != NULL)"),
+            Some(false) => conditions.push("(phone_number = NONE OR phone_number = NULL)"),
+            None => {}
+        }
+        match filter.has_avatar {
+            Some(true) => conditions.push("(avatar != NONE AND avatar != NULL)"),
+            Some(false) => conditions.push("(avatar = NONE OR avatar = NULL)"),
+            None => {}
+        }
+        let count_conditions = where_clause(&conditions);
+
+        let field = match order_by {
+            UserOrderBy::Created => "created",
+            UserOrderBy::Updated => "updated",
+            UserOrderBy::Username => "username",
+        };
+        let (side, order) = match page.direction {
+            Direction::Forward => (">", "ASC"),
+            Direction::Backward => ("<", "DESC"),
+        };
+        // ties on the ordered field are broken by id, so the cursor only needs the id
+        let keyset = format!(
+            "({field} {side} $anchor.{field} OR ({field} = $anchor.{field} AND id {side} $anchor.id))"
+        );
+        if page.cursor.is_some() {
+            conditions.push(&keyset);
+        }
+
+        let mut statements = vec![];
+        if page.cursor.is_some() {
+            statements
+                .push("LET $anchor = (SELECT * FROM type::thing($table, $cursor))[0]".to_owned());
+            // the user behind the cursor may have been deleted since the page was served
+            statements.push("RETURN $anchor IS NOT NONE".to_owned());
+        }
+        // the cursor statements take up the first response slots
+        let offset = statements.len();
+        statements.push(format!(
+            "SELECT * FROM type::table($table) {} ORDER BY {field} {order}, id {order} LIMIT $limit",
+            where_clause(&conditions)
+        ));
+        statements.push(format!(
+            "SELECT count() AS count FROM type::table($table) {count_conditions} GROUP ALL"
+        ));
+
+        let mut response = self
+            .client
+            .query(statements.join(";\n"))
+            .bind(("table", Collection::User))
+            .bind(("cursor", page.cursor.map(|cursor| cursor.to_string())))
+            .bind(("limit", page.limit.saturating_add(1)))
+            .bind(("user_type", filter.user_type))
+            .bind(("created_after", filter.created_after.map(to_datetime)))
+            .bind(("created_before", filter.created_before.map(to_datetime)))
+            .bind(("updated_after", filter.updated_after.map(to_datetime)))
+            .bind(("updated_before", filter.updated_before.map(to_datetime)))
+            .await
+            .map_err(map_db_error)?;
+        event!(Level::DEBUG, "database queried");
+
+        if let Some(cursor) = page.cursor {
+            let found: Option<bool> = response.take(1).map_err(map_db_error)?;
+            if found != Some(true) {
+                return Err(CoreError::InvalidCursor(cursor));
+            }
+        }
+
+        let users: Vec<DatabaseEntityUser> = response.take(offset).map_err(map_db_error)?;
+        let total_count: Option<usize> =
+            response.take((offset + 1, "count")).map_err(map_db_error)?;
+
+        trace!("mapping entities");
+        let users = users
+            .into_iter()
+            .map(User::try_from)
+            .collect::<Result<Vec<User>, CoreError>>()?;
+        event!(
+            Level::INFO,
+            user_count = users.len(),
+            "found users page from database"
+        );
+
+        Ok(Paged::from_overfetch(
+            users,
+            &page,
+            total_count.unwrap_or_default(),
+        ))
     }
 
     #[instrument(skip(self), err(Debug))]
@@ -329,60 +574,55 @@ impl QueryUsers for Client {
     }
 
     #[instrument(skip(self), err(Debug))]
-    async fn search(
-        &self,
-        query: impl AsRef<str> + Send + Debug,
-    ) -> Result<impl ExactSizeIterator<Item = User>, CoreError> {
-        if let Some(ref client) = self.search_client {
-            let mut index = None;
-            for retries in 0..3 {
-                trace!("checking user search indexing retry {} of 3", {
-                    retries + 1
-                });
-                if let Ok(idx) = client.get_index("users").await {
-                    index = Some(idx);
-                    trace!("found search index");
-                    break;
+    async fn search(&self, search: &UserSearch) -> Result<UserSearchResults, CoreError> {
+        match self.user_search_index().await? {
+            Some(index) => {
+                // send changes that failed to index earlier before reading from the index
+                self.flush_search_queue().await;
+
+                trace!("searching index");
+                let filter = search_filter(&search.filter);
+                let mut query = SearchQuery::new(&index);
+                query
+                    .with_query(&search.query)
+                    .with_offset(search.offset)
+                    .with_limit(search.limit)
+                    .with_facets(Selectors::Some(&[FACET_USER_TYPE]))
+                    .with_attributes_to_highlight(Selectors::Some(&HIGHLIGHTED_ATTRIBUTES))
+                    .with_show_matches_position(true);
+                if !filter.is_empty() {
+                    query.with_filter(&filter);
                 }
-                let _users = db_get_users(self, true).await?;
-            }
-            match index {
-                Some(index) => {
-                    trace!("searching index");
-                    let query = SearchQuery::new(&index).with_query(query.as_ref()).build();
-
-                    let results: SearchResults<User> = index
-                        .execute_query(&query)
-                        .await
-                        .map_err(|e| CoreError::Other(e.to_string()))?;
-                    event!(Level::INFO, hits = results.hits.len(), "query results");
-
-                    let search_results: Vec<User> = results
+
+                let results: SearchResults<IndexedUser> = index
+                    .execute_query(&query)
+                    .await
+                    .map_err(map_search_error)?;
+                event!(Level::INFO, hits = results.hits.len(), "query results");
+
+                // hits whose user was deleted before the index caught up are dropped
+                let ids: Vec<Uuid> = results.hits.iter().map(|hit| hit.result.id).collect();
+                let mut users = self.users_by_ids(&ids).await?;
+
+                Ok(UserSearchResults {
+                    total_hits: results
+                        .total_hits
+                        .or(results.estimated_total_hits)
+                        .unwrap_or(results.hits.len()),
+                    offset: results.offset.unwrap_or(search.offset),
+                    limit: results.limit.unwrap_or(search.limit),
+                    facets: search_facets(results.facet_distribution),
+                    hits: results
                         .hits
                         .into_iter()
-                        .map(|hit| User {
-                            id: hit.result.id,
-                            name: hit.result.name,
-                            username: hit.result.username,
-                            email: hit.result.email,
-                            avatar: hit.result.avatar,
-                            user_type: hit.result.user_type,
-                            phone_number: hit.result.phone_number,
-                            created: hit.result.created,
-                            updated: hit.result.updated,
+                        .filter_map(|hit| {
+                            let user = users.remove(&hit.result.id)?;
+                            Some(search_hit(hit, user))
                         })
-                        .collect();
-
-                    Ok(search_results.into_iter())
-                }
-                None => Err(CoreError::Other(
-                    "items could not be indexed for search".into(),
-                )),
+                        .collect(),
+                })
             }
-        } else {
-            Err(CoreError::Other(String::from(
-                "no client configured for search",
-            )))
+            None => db_search(self, search).await,
         }
     }
 
@@ -392,11 +632,11 @@ impl QueryUsers for Client {
         session_token: impl AsRef<str> + Send + Debug,
     ) -> Result<Option<(User, Session)>, CoreError> {
         trace!("getting session and user by session token");
-        let session_token = session_token.as_ref();
+        let session_token = hash_session_token(session_token.as_ref());
 
         let mut session = self
             .client
-            .query("SELECT in.*,out.*,* FROM type::table($table) WHERE session_token = type::string($session_token)")
+            .query("SELECT in.*,out.*,* FROM type::table($table) WHERE session_token = type::string($session_token) AND expires_at > time::now()")
             .bind(("table", Collection::UserSession))
             .bind(("session_token", session_token))
             .await
@@ -405,29 +645,83 @@ impl QueryUsers for Client {
 
         #[derive(Debug, Deserialize)]
         struct Root {
-            #[serde(rename = "id")]
-            _id: Thing,
-            expires_at: String,
+            id: Thing,
+            #[serde(deserialize_with = "deserialize_date_time")]
+            expires_at: OffsetDateTime,
+            #[serde(deserialize_with = "deserialize_date_time")]
+            created: OffsetDateTime,
             #[serde(rename = "in")]
             in_field: DatabaseEntityUser,
             out: DatabaseEntityAccountProvider,
             session_token: String,
+            #[serde(default)]
+            user_agent: Option<String>,
+            #[serde(default)]
+            ip_address: Option<String>,
+            #[serde(default)]
+            device_label: Option<String>,
+            #[serde(default, deserialize_with = "deserialize_optional_date_time")]
+            last_seen_at: Option<OffsetDateTime>,
         }
 
         trace!("mapping entity");
         let user: Option<Root> = session.take(0).map_err(map_db_error)?;
 
         if let Some(val) = user {
-            event!(Level::INFO, id = %val._id, "found user");
+            event!(Level::INFO, id = %val.id, "found user");
+            let policy = &self.session_policy;
+            let now = OffsetDateTime::now_utc();
+
+            if now >= policy.deadline(val.created) {
+                debug!("session is past its maximum lifetime");
+                self.client
+                    .query("DELETE $record")
+                    .bind(("record", &val.id))
+                    .await
+                    .map_err(map_db_error)?;
+                return Ok(None);
+            }
+
+            let mut expires_at = val.expires_at;
+            let due = policy
+                .refresh_at(val.created, expires_at)
+                .is_some_and(|refresh_at| refresh_at <= now);
+            if due && expires_at > now {
+                expires_at = policy.extend(val.created, now);
+                debug!(expires_at = %expires_at, "session extended");
+            }
+
+            // only written once per resolution to keep lookups cheap
+            let mut last_seen_at = val.last_seen_at;
+            let stale = last_seen_at
+                .map(|seen| now - seen >= LAST_SEEN_RESOLUTION)
+                .unwrap_or(true);
+            if stale || expires_at != val.expires_at {
+                last_seen_at = Some(now);
+                self.client
+                    .query(
+                        "UPDATE $record SET expires_at = $expires_at, last_seen_at = $last_seen_at",
+                    )
+                    .bind(("record", &val.id))
+                    .bind(("expires_at", to_datetime(expires_at)))
+                    .bind(("last_seen_at", to_datetime(now)))
+                    .await
+                    .map_err(map_db_error)?;
+            }
+
             let user_id = record_id_to_uuid(&val.in_field.id)?;
             let session = Session {
-                expires_at: OffsetDateTime::parse(&val.expires_at, &Iso8601::DEFAULT).expect(""),
+                id: record_id_to_uuid(&val.id)?,
+                expires_at,
                 session_token: val.session_token,
-                account_provider: api_core::AccountProvider {
-                    id: record_id_to_uuid(&val.out.id)?,
-                    name: val.out.name,
-                },
+                account_provider: api_core::AccountProvider::try_from(val.out)?,
                 user_id,
+                refresh_at: policy.refresh_at(val.created, expires_at),
+                user_agent: val.user_agent,
+                ip_address: val.ip_address,
+                device_label: val.device_label,
+                created: val.created,
+                last_seen_at,
             };
 
             let user = User {
@@ -438,6 +732,8 @@ impl QueryUsers for Client {
                 avatar: val.in_field.avatar,
                 user_type: val.in_field.user_type,
                 phone_number: val.in_field.phone_number,
+                role: val.in_field.role,
+                email_verified: val.in_field.email_verified,
                 created: val.in_field.created,
                 updated: val.in_field.updated,
             };
@@ -449,3 +745,34 @@ impl QueryUsers for Client {
         }
     }
 }
+
+impl Client {
+    /// Loads the users with the given ids, skipping ids that don't exist
+    pub(crate) async fn users_by_ids(
+        &self,
+        ids: &[Uuid],
+    ) -> Result<HashMap<Uuid, User>, CoreError> {
+        let ids: Vec<Thing> = ids
+            .iter()
+            .map(|id| {
+                Thing::from((
+                    Collection::User.to_string().as_str(),
+                    id.to_string().as_str(),
+                ))
+            })
+            .collect();
+
+        let mut resp = self
+            .client
+            .query("SELECT * FROM $ids")
+            .bind(("ids", ids))
+            .await
+            .map_err(map_db_error)?;
+        let users: Vec<DatabaseEntityUser> = resp.take(0).map_err(map_db_error)?;
+
+        users
+            .into_iter()
+            .map(|user| User::try_from(user).map(|user| (user.id, user)))
+            .collect()
+    }
+}
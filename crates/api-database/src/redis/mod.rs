@@ -225,12 +225,12 @@ impl<'a> ClusteredPooledConnection<'a> {
 
 #[async_trait]
 pub trait PoolLike {
-    async fn get(&self) -> Result<PooledConnection, RunError<RedisError>>;
+    async fn get<'a>(&'a self) -> Result<PooledConnection<'a>, RunError<RedisError>>;
 }
 
 #[async_trait]
 impl PoolLike for RedisPool {
-    async fn get(&self) -> Result<PooledConnection, RunError<RedisError>> {
+    async fn get<'a>(&'a self) -> Result<PooledConnection<'a>, RunError<RedisError>> {
         match self {
             Self::Clustered(pool) => pool.get().await,
             Self::NonClustered(pool) => pool.get().await,
@@ -240,7 +240,7 @@ impl PoolLike for RedisPool {
 
 #[async_trait]
 impl PoolLike for NonClusteredRedisPool {
-    async fn get(&self) -> Result<PooledConnection, RunError<RedisError>> {
+    async fn get<'a>(&'a self) -> Result<PooledConnection<'a>, RunError<RedisError>> {
         let con = self.pool.get().await?;
         let con = NonClusteredPooledConnection { con };
         Ok(PooledConnection::NonClustered(con))
@@ -249,7 +249,7 @@ impl PoolLike for NonClusteredRedisPool {
 
 #[async_trait]
 impl PoolLike for ClusteredRedisPool {
-    async fn get(&self) -> Result<PooledConnection, RunError<RedisError>> {
+    async fn get<'a>(&'a self) -> Result<PooledConnection<'a>, RunError<RedisError>> {
         let con = ClusteredPooledConnection {
             con: self.pool.get().await?,
         };
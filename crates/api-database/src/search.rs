@@ -0,0 +1,629 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{atomic::Ordering, Arc, Mutex},
+    time::Duration,
+};
+
+use api_core::{
+    api::{CoreError, Page, QueryUsers},
+    reexports::uuid::Uuid,
+    FacetCount, Role, User, UserFilter, UserHighlight, UserOrderBy, UserSearchFacets,
+    UserSearchFilter, UserSearchHit, UserType,
+};
+use meilisearch_sdk::{
+    client::SwapIndexes,
+    errors::{Error, ErrorCode},
+    indexes::Index,
+    search::SearchResult,
+    settings::{Settings, TypoToleranceSettings},
+    task_info::TaskInfo,
+    tasks::Task,
+};
+use serde::{Deserialize, Serialize};
+use time::{format_description::well_known::Rfc3339, OffsetDateTime};
+use tracing::{debug, error, event, instrument, trace, Level};
+
+use crate::{map_db_error, Client};
+
+pub(crate) const USER_INDEX: &str = "users";
+/// Index a full reindex is built in before it is swapped with [`USER_INDEX`]
+const USER_REINDEX: &str = "users_reindex";
+const PRIMARY_KEY: &str = "id";
+pub(crate) const FACET_USER_TYPE: &str = "type";
+pub(crate) const HIGHLIGHTED_ATTRIBUTES: [&str; 2] = ["username", "name"];
+const TASK_TIMEOUT: Duration = Duration::from_secs(60);
+/// Number of users read from the database per request during a reindex
+pub const REINDEX_BATCH_SIZE: usize = 500;
+
+/// Document stored in the users index. Contact details are left out, hits are read back from the
+/// database. The creation time is also kept as a number, as search filters can only compare
+/// numbers
+#[derive(Serialize)]
+pub(crate) struct UserDocument<'a> {
+    id: &'a Uuid,
+    username: &'a str,
+    name: Option<&'a str>,
+    avatar: Option<&'a str>,
+    #[serde(rename = "type")]
+    user_type: UserType,
+    role: Role,
+    created: OffsetDateTime,
+    updated: OffsetDateTime,
+    created_timestamp: i64,
+}
+
+impl<'a> From<&'a User> for UserDocument<'a> {
+    fn from(user: &'a User) -> Self {
+        Self {
+            id: &user.id,
+            username: &user.username,
+            name: user.name.as_deref(),
+            avatar: user.avatar.as_deref(),
+            user_type: user.user_type,
+            role: user.role,
+            created: user.created,
+            updated: user.updated,
+            created_timestamp: timestamp(user.created),
+        }
+    }
+}
+
+/// The part of a search hit needed to load the user it points at
+#[derive(Deserialize)]
+pub(crate) struct IndexedUser {
+    pub(crate) id: Uuid,
+}
+
+/// Milliseconds since the unix epoch, which are exactly representable in a search index number
+pub(crate) fn timestamp(value: OffsetDateTime) -> i64 {
+    (value.unix_timestamp_nanos() / 1_000_000) as i64
+}
+
+/// Change to apply to a document in the users index
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum IndexOperation {
+    Upsert(User),
+    Delete,
+}
+
+/// Index changes that have not been applied by the search server yet. Only the latest change for
+/// a user is kept, as it supersedes anything queued before it
+#[derive(Debug, Default, Clone)]
+pub(crate) struct IndexQueue(Arc<Mutex<QueueState>>);
+
+#[derive(Debug, Default)]
+struct QueueState {
+    pending: BTreeMap<Uuid, IndexOperation>,
+    /// Changes the search server accepted but has not processed yet, by task uid. They are
+    /// checked on the next flush as a task can still fail after it was accepted
+    in_flight: BTreeMap<u32, (TaskInfo, Vec<(Uuid, IndexOperation)>)>,
+    /// Uid of the last task sent for each user with a task in flight
+    latest_task: BTreeMap<Uuid, u32>,
+    /// Changes made while a reindex is running, replayed once it completes
+    journal: Option<BTreeMap<Uuid, IndexOperation>>,
+}
+
+impl IndexQueue {
+    pub(crate) fn push(&self, id: Uuid, operation: IndexOperation) {
+        let mut state = self.0.lock().expect("index queue lock");
+        if let Some(ref mut journal) = state.journal {
+            journal.insert(id, operation.clone());
+        }
+        state.pending.insert(id, operation);
+    }
+
+    pub(crate) fn take(&self) -> BTreeMap<Uuid, IndexOperation> {
+        std::mem::take(&mut self.0.lock().expect("index queue lock").pending)
+    }
+
+    /// Puts back operations that failed, unless a newer change was queued in the meantime
+    fn requeue(&self, operations: impl IntoIterator<Item = (Uuid, IndexOperation)>) {
+        let mut state = self.0.lock().expect("index queue lock");
+        for (id, operation) in operations {
+            state.pending.entry(id).or_insert(operation);
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.lock().expect("index queue lock").pending.len()
+    }
+
+    /// Remembers the operations sent with an accepted task until its outcome is known
+    pub(crate) fn track(&self, task: TaskInfo, operations: Vec<(Uuid, IndexOperation)>) {
+        let mut state = self.0.lock().expect("index queue lock");
+        for (id, _) in &operations {
+            state.latest_task.insert(*id, task.task_uid);
+        }
+        state.in_flight.insert(task.task_uid, (task, operations));
+    }
+
+    fn in_flight(&self) -> Vec<TaskInfo> {
+        let state = self.0.lock().expect("index queue lock");
+        state
+            .in_flight
+            .values()
+            .map(|(task, _)| task.clone())
+            .collect()
+    }
+
+    /// Forgets a finished task. The operations of a failed task are queued again, except for
+    /// users a later task or a queued change already covers
+    pub(crate) fn settle(&self, task_uid: u32, failed: bool) {
+        let mut state = self.0.lock().expect("index queue lock");
+        let Some((_, operations)) = state.in_flight.remove(&task_uid) else {
+            return;
+        };
+
+        for (id, operation) in operations {
+            if state.latest_task.get(&id) != Some(&task_uid) {
+                continue;
+            }
+            state.latest_task.remove(&id);
+            if failed {
+                state.pending.entry(id).or_insert(operation);
+            }
+        }
+    }
+
+    /// Starts recording changes. Returns `false` if a recording is already running
+    fn start_journal(&self) -> bool {
+        let mut state = self.0.lock().expect("index queue lock");
+        if state.journal.is_some() {
+            return false;
+        }
+        state.journal = Some(BTreeMap::new());
+        true
+    }
+
+    /// Stops recording and queues the recorded changes again
+    fn replay_journal(&self) {
+        let mut state = self.0.lock().expect("index queue lock");
+        if let Some(journal) = state.journal.take() {
+            for (id, operation) in journal {
+                state.pending.entry(id).or_insert(operation);
+            }
+        }
+    }
+}
+
+pub(crate) fn map_search_error(error: meilisearch_sdk::errors::Error) -> CoreError {
+    CoreError::Other(error.to_string())
+}
+
+/// Searchable, filterable and sortable attributes of the users index
+fn user_index_settings() -> Settings {
+    Settings::new()
+        .with_searchable_attributes(HIGHLIGHTED_ATTRIBUTES)
+        .with_filterable_attributes([FACET_USER_TYPE, "created_timestamp"])
+        .with_sortable_attributes(["created_timestamp"])
+        .with_typo_tolerance(TypoToleranceSettings {
+            enabled: Some(true),
+            ..Default::default()
+        })
+}
+
+/// Builds a search filter expression. Empty when nothing is filtered
+pub(crate) fn search_filter(filter: &UserSearchFilter) -> String {
+    let mut conditions = vec![];
+    if let Some(user_type) = filter.user_type {
+        conditions.push(format!("{FACET_USER_TYPE} = {user_type:?}"));
+    }
+    if let Some(created_after) = filter.created_after {
+        conditions.push(format!("created_timestamp > {}", timestamp(created_after)));
+    }
+    if let Some(created_before) = filter.created_before {
+        conditions.push(format!("created_timestamp < {}", timestamp(created_before)));
+    }
+
+    conditions.join(" AND ")
+}
+
+/// Builds a hit for `user`, the database record behind the indexed document
+pub(crate) fn search_hit(hit: SearchResult<IndexedUser>, user: User) -> UserSearchHit {
+    let formatted = |attribute: &str| {
+        hit.formatted_result
+            .as_ref()
+            .and_then(|formatted| formatted.get(attribute))
+            .and_then(|value| value.as_str())
+            .map(ToOwned::to_owned)
+    };
+    let highlight = UserHighlight {
+        username: formatted("username").unwrap_or_else(|| user.username.clone()),
+        name: formatted("name").or_else(|| user.name.clone()),
+    };
+
+    let mut matched_terms = vec![];
+    for (attribute, ranges) in hit.matches_position.iter().flatten() {
+        let value = match attribute.as_str() {
+            "username" => Some(user.username.as_str()),
+            "name" => user.name.as_deref(),
+            _ => None,
+        };
+        let Some(value) = value else {
+            continue;
+        };
+
+        for range in ranges {
+            if let Some(term) = value.get(range.start..range.start + range.length) {
+                let term = term.to_lowercase();
+                if !matched_terms.contains(&term) {
+                    matched_terms.push(term);
+                }
+            }
+        }
+    }
+
+    UserSearchHit {
+        user,
+        highlight,
+        matched_terms,
+    }
+}
+
+pub(crate) fn search_facets(
+    distribution: Option<HashMap<String, HashMap<String, usize>>>,
+) -> UserSearchFacets {
+    let mut user_type: Vec<FacetCount> = distribution
+        .and_then(|mut distribution| distribution.remove(FACET_USER_TYPE))
+        .into_iter()
+        .flatten()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    user_type.sort_by(|a, b| a.value.cmp(&b.value));
+
+    UserSearchFacets { user_type }
+}
+
+async fn wait_for_task(
+    client: &meilisearch_sdk::client::Client,
+    task: TaskInfo,
+) -> Result<(), CoreError> {
+    let task = task
+        .wait_for_completion(client, None, Some(TASK_TIMEOUT))
+        .await
+        .map_err(map_search_error)?;
+
+    if task.is_failure() {
+        Err(CoreError::Other(task.unwrap_failure().to_string()))
+    } else {
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Queues a change to the users index and sends everything pending
+    pub(crate) async fn sync_user_index(&self, id: Uuid, operation: IndexOperation) {
+        if self.search_client.is_some() {
+            self.index_queue.push(id, operation);
+            self.flush_search_queue().await;
+        }
+    }
+
+    /// Sends queued index changes to the search server. Changes that fail stay queued for the
+    /// next attempt. Returns the number of changes still pending
+    #[instrument(skip(self))]
+    pub async fn flush_search_queue(&self) -> usize {
+        let Some(ref client) = self.search_client else {
+            return 0;
+        };
+
+        self.reconcile_search_tasks(client).await;
+
+        let pending = self.index_queue.take();
+        if pending.is_empty() {
+            return 0;
+        }
+        trace!(count = pending.len(), "flushing search index queue");
+
+        let (upserts, deletes): (Vec<_>, Vec<_>) = pending
+            .into_iter()
+            .partition(|(_, operation)| matches!(operation, IndexOperation::Upsert(_)));
+        let index = client.index(USER_INDEX);
+
+        if !upserts.is_empty() {
+            let users: Vec<UserDocument> = upserts
+                .iter()
+                .filter_map(|(_, operation)| match operation {
+                    IndexOperation::Upsert(user) => Some(UserDocument::from(user)),
+                    IndexOperation::Delete => None,
+                })
+                .collect();
+
+            match index.add_or_replace(&users, Some(PRIMARY_KEY)).await {
+                Ok(task) => {
+                    event!(
+                        Level::INFO,
+                        count = users.len(),
+                        "queued user documents for indexing"
+                    );
+                    self.index_queue.track(task, upserts);
+                }
+                Err(e) => {
+                    error!(count = users.len(), "[search index]: {e}");
+                    self.index_queue.requeue(upserts);
+                }
+            }
+        }
+
+        if !deletes.is_empty() {
+            let ids: Vec<Uuid> = deletes.iter().map(|(id, _)| *id).collect();
+
+            match index.delete_documents(&ids).await {
+                Ok(task) => {
+                    event!(
+                        Level::INFO,
+                        count = ids.len(),
+                        "queued user documents for removal"
+                    );
+                    self.index_queue.track(task, deletes);
+                }
+                Err(e) => {
+                    error!(count = ids.len(), "[search index]: {e}");
+                    self.index_queue.requeue(deletes);
+                }
+            }
+        }
+
+        self.index_queue.len()
+    }
+
+    /// Checks the outcome of index tasks sent by earlier flushes. Changes of failed tasks are
+    /// queued again, tasks that are still running or cannot be checked are kept for later
+    async fn reconcile_search_tasks(&self, client: &meilisearch_sdk::client::Client) {
+        for task in self.index_queue.in_flight() {
+            match client.get_task(&task).await {
+                Ok(Task::Succeeded { .. }) => self.index_queue.settle(task.task_uid, false),
+                Ok(Task::Failed { content }) => {
+                    error!(
+                        task_uid = task.task_uid,
+                        "[search index]: {}", content.error
+                    );
+                    self.index_queue.settle(task.task_uid, true);
+                }
+                Ok(Task::Enqueued { .. } | Task::Processing { .. }) => {}
+                Err(e) => error!(task_uid = task.task_uid, "[search index]: {e}"),
+            }
+        }
+    }
+
+    /// Number of index changes waiting to be sent to the search server
+    pub fn pending_search_operations(&self) -> usize {
+        self.index_queue.len()
+    }
+
+    /// Returns the users index, creating and filling it on first use. Settings are applied once
+    /// per client
+    pub(crate) async fn user_search_index(&self) -> Result<Option<Index>, CoreError> {
+        let Some(ref client) = self.search_client else {
+            return Ok(None);
+        };
+
+        if !self.search_ready.load(Ordering::Acquire) {
+            match client.get_index(USER_INDEX).await {
+                Ok(_) => self.bootstrap_search_index().await?,
+                Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::IndexNotFound => {
+                    trace!("users index not found, building it");
+                    self.reindex_users(REINDEX_BATCH_SIZE).await?;
+                }
+                Err(e) => return Err(map_search_error(e)),
+            }
+            self.search_ready.store(true, Ordering::Release);
+        }
+
+        Ok(Some(client.index(USER_INDEX)))
+    }
+
+    /// Creates the users index if it does not exist and applies its settings. Safe to run
+    /// repeatedly
+    #[instrument(skip(self), err(Debug))]
+    pub async fn bootstrap_search_index(&self) -> Result<(), CoreError> {
+        match self.search_client {
+            Some(ref client) => configure_index(client, USER_INDEX).await,
+            None => Err(CoreError::Other(String::from(
+                "no client configured for search",
+            ))),
+        }
+    }
+
+    /// Rebuilds the users index from the database. Users are read in batches of `batch_size`
+    /// into a new index, which then replaces the live one in a single swap. Users written or
+    /// deleted while the reindex runs, by this or any other process, are applied to the new
+    /// index afterwards. Returns the number of indexed users
+    #[instrument(skip(self), err(Debug))]
+    pub async fn reindex_users(&self, batch_size: usize) -> Result<usize, CoreError> {
+        let Some(ref client) = self.search_client else {
+            return Err(CoreError::Other(String::from(
+                "no client configured for search",
+            )));
+        };
+        if !self.index_queue.start_journal() {
+            return Err(CoreError::Other(String::from(
+                "a reindex is already running",
+            )));
+        }
+
+        let res = self.build_reindex(client, batch_size.max(1)).await;
+        self.index_queue.replay_journal();
+
+        let count = res?;
+        self.search_ready.store(true, Ordering::Release);
+        self.flush_search_queue().await;
+        event!(Level::INFO, user_count = count, "finished reindexing users");
+
+        Ok(count)
+    }
+
+    async fn build_reindex(
+        &self,
+        client: &meilisearch_sdk::client::Client,
+        batch_size: usize,
+    ) -> Result<usize, CoreError> {
+        // leftovers from an interrupted reindex
+        let task = client
+            .delete_index(USER_REINDEX)
+            .await
+            .map_err(map_search_error)?;
+        let _ = task
+            .wait_for_completion(client, None, Some(TASK_TIMEOUT))
+            .await;
+
+        configure_index(client, USER_REINDEX).await?;
+        let index = client.index(USER_REINDEX);
+        let started = self.database_now().await?;
+
+        let mut indexed = vec![];
+        let mut cursor = None;
+        loop {
+            let page = self
+                .get_users_page(
+                    Page::forward(cursor, batch_size),
+                    &UserFilter::default(),
+                    UserOrderBy::Created,
+                )
+                .await?;
+            if !page.items.is_empty() {
+                let documents: Vec<UserDocument> =
+                    page.items.iter().map(UserDocument::from).collect();
+                let task = index
+                    .add_or_replace(&documents, Some(PRIMARY_KEY))
+                    .await
+                    .map_err(map_search_error)?;
+                wait_for_task(client, task).await?;
+            }
+            indexed.extend(page.items.iter().map(|user| user.id));
+            trace!(user_count = indexed.len(), "indexed batch");
+
+            match page.items.last() {
+                Some(user) if page.has_next => cursor = Some(user.id),
+                _ => break,
+            }
+        }
+
+        // the live index has to exist for the swap
+        configure_index(client, USER_INDEX).await?;
+        let task = client
+            .swap_indexes([&SwapIndexes {
+                indexes: (USER_INDEX.to_owned(), USER_REINDEX.to_owned()),
+            }])
+            .await
+            .map_err(map_search_error)?;
+        wait_for_task(client, task).await?;
+
+        let task = client
+            .delete_index(USER_REINDEX)
+            .await
+            .map_err(map_search_error)?;
+        wait_for_task(client, task).await?;
+
+        self.catch_up_reindex(client, started, &indexed, batch_size)
+            .await?;
+
+        Ok(indexed.len())
+    }
+
+    /// Applies writes made while the batches were copied, which went to the index that was
+    /// swapped out. They are read back from the database rather than the index queue, as the
+    /// writes may come from other processes
+    async fn catch_up_reindex(
+        &self,
+        client: &meilisearch_sdk::client::Client,
+        started: OffsetDateTime,
+        indexed: &[Uuid],
+        batch_size: usize,
+    ) -> Result<(), CoreError> {
+        let index = client.index(USER_INDEX);
+        let filter = UserFilter {
+            updated_after: Some(started),
+            ..Default::default()
+        };
+
+        let mut changed = 0;
+        let mut cursor = None;
+        loop {
+            let page = self
+                .get_users_page(
+                    Page::forward(cursor, batch_size),
+                    &filter,
+                    UserOrderBy::Created,
+                )
+                .await?;
+            if !page.items.is_empty() {
+                let documents: Vec<UserDocument> =
+                    page.items.iter().map(UserDocument::from).collect();
+                let task = index
+                    .add_or_replace(&documents, Some(PRIMARY_KEY))
+                    .await
+                    .map_err(map_search_error)?;
+                wait_for_task(client, task).await?;
+            }
+            changed += page.items.len();
+
+            match page.items.last() {
+                Some(user) if page.has_next => cursor = Some(user.id),
+                _ => break,
+            }
+        }
+
+        let mut deleted: Vec<&Uuid> = vec![];
+        for ids in indexed.chunks(batch_size) {
+            let existing = self.users_by_ids(ids).await?;
+            deleted.extend(ids.iter().filter(|id| !existing.contains_key(id)));
+        }
+        if !deleted.is_empty() {
+            let task = index
+                .delete_documents(&deleted)
+                .await
+                .map_err(map_search_error)?;
+            wait_for_task(client, task).await?;
+        }
+        debug!(
+            changed,
+            deleted = deleted.len(),
+            "caught up with writes made during the reindex"
+        );
+
+        Ok(())
+    }
+
+    /// The database clock, which stamps `updated` on every write
+    async fn database_now(&self) -> Result<OffsetDateTime, CoreError> {
+        let now: Option<String> = self
+            .client
+            .query("RETURN <string> time::now()")
+            .await
+            .map_err(map_db_error)?
+            .take(0)
+            .map_err(map_db_error)?;
+        let now = now.ok_or(CoreError::Unreachable)?;
+
+        OffsetDateTime::parse(&now, &Rfc3339).map_err(|e| CoreError::Other(e.to_string()))
+    }
+}
+
+async fn configure_index(
+    client: &meilisearch_sdk::client::Client,
+    uid: &str,
+) -> Result<(), CoreError> {
+    match client.get_index(uid).await {
+        Ok(_) => {}
+        Err(Error::Meilisearch(e)) if e.error_code == ErrorCode::IndexNotFound => {
+            trace!(index = uid, "creating search index");
+            let task = client
+                .create_index(uid, Some(PRIMARY_KEY))
+                .await
+                .map_err(map_search_error)?;
+            wait_for_task(client, task).await?;
+        }
+        Err(e) => return Err(map_search_error(e)),
+    }
+
+    let task = client
+        .index(uid)
+        .set_settings(&user_index_settings())
+        .await
+        .map_err(map_search_error)?;
+    wait_for_task(client, task).await?;
+    debug!(index = uid, "search index settings applied");
+
+    Ok(())
+}
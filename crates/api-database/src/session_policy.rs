@@ -0,0 +1,51 @@
+use time::{Duration, OffsetDateTime};
+
+/// How stale `last_seen_at` may get before a lookup writes it again
+pub(crate) const LAST_SEEN_RESOLUTION: Duration = Duration::MINUTE;
+
+/// How long sessions stay valid. A session in use is extended to `idle_timeout` from now once
+/// `refresh_threshold` has passed since its last extension, but never beyond `max_lifetime` after
+/// it was created
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SessionPolicy {
+    pub idle_timeout: Duration,
+    pub max_lifetime: Duration,
+    pub refresh_threshold: Duration,
+}
+
+impl Default for SessionPolicy {
+    fn default() -> Self {
+        Self {
+            idle_timeout: Duration::days(30),
+            max_lifetime: Duration::days(90),
+            refresh_threshold: Duration::DAY,
+        }
+    }
+}
+
+impl SessionPolicy {
+    /// The latest a session created at `created` can be valid until
+    pub fn deadline(&self, created: OffsetDateTime) -> OffsetDateTime {
+        created + self.max_lifetime
+    }
+
+    /// Caps an expiry to the lifetime of the session
+    pub fn clamp(&self, created: OffsetDateTime, expires_at: OffsetDateTime) -> OffsetDateTime {
+        expires_at.min(self.deadline(created))
+    }
+
+    /// The expiry of a session used at `now`
+    pub fn extend(&self, created: OffsetDateTime, now: OffsetDateTime) -> OffsetDateTime {
+        self.clamp(created, now + self.idle_timeout)
+    }
+
+    /// When the session should next be extended. `None` once it has reached its deadline
+    pub fn refresh_at(
+        &self,
+        created: OffsetDateTime,
+        expires_at: OffsetDateTime,
+    ) -> Option<OffsetDateTime> {
+        (expires_at < self.deadline(created))
+            .then(|| expires_at - self.idle_timeout + self.refresh_threshold)
+    }
+}
@@ -0,0 +1,278 @@
+use anyhow::Result;
+use api_core::{
+    api::{
+        CoreError, MutateAccountProviders, MutateAccounts, MutateSessions, MutateUsers,
+        QueryAccountProviders, QueryAccounts,
+    },
+    reexports::uuid::Uuid,
+    AccountProvider, AccountTokens, ProviderType, Session,
+};
+use time::{Duration, OffsetDateTime};
+
+use super::{create_client, mutation::create_user_item, register_providers};
+use crate::TokenCipher;
+
+#[tokio::test]
+async fn account_tokens_are_encrypted() -> Result<()> {
+    let client = create_client(Some("ns_account_tokens"), false, false).await?;
+    register_providers(&client).await?;
+    let user = client.create_user(&create_user_item()).await?;
+    let tokens = AccountTokens {
+        access_token: Some("access".to_owned()),
+        refresh_token: Some("refresh".to_owned()),
+        expires_at: Some(OffsetDateTime::now_utc().replace_nanosecond(0)? + Duration::HOUR),
+        token_type: Some("bearer".to_owned()),
+        scope: Some("read:user".to_owned()),
+        id_token: Some("id".to_owned()),
+        session_state: Some("state".to_owned()),
+    };
+
+    // secrets are refused until a key is configured
+    assert!(client
+        .link_account("github", "tokens", &user.id, &tokens)
+        .await
+        .is_err());
+
+    let client = client.with_token_cipher(TokenCipher::new(&[7; 32]));
+    client
+        .link_account("github", "tokens", &user.id, &tokens)
+        .await?;
+
+    let account = client
+        .get_account("github", "tokens")
+        .await?
+        .expect("account to exist");
+    assert_eq!(account.user_id, user.id);
+    assert_eq!(account.provider, "github");
+    assert_eq!(account.tokens, tokens);
+    assert!(client.get_account("gitlab", "tokens").await?.is_none());
+
+    let stored: Option<String> = client
+        .client
+        .query("SELECT VALUE tokens.access_token FROM ONLY user_account WHERE provider_account_id = 'tokens' LIMIT 1")
+        .await?
+        .take(0)?;
+    assert_ne!(stored.as_deref(), Some("access"));
+
+    let refreshed = AccountTokens {
+        access_token: Some("refreshed".to_owned()),
+        refresh_token: None,
+        ..tokens.clone()
+    };
+    let account = client
+        .update_account_tokens("github", "tokens", &refreshed)
+        .await?
+        .expect("account to exist");
+    assert_eq!(account.tokens, refreshed);
+    assert_eq!(
+        client
+            .get_account("github", "tokens")
+            .await?
+            .map(|account| account.tokens),
+        Some(refreshed.clone())
+    );
+    assert!(client
+        .update_account_tokens("github", "missing", &refreshed)
+        .await?
+        .is_none());
+
+    // a different key can't read the tokens
+    let client = client.with_token_cipher(TokenCipher::new(&[8; 32]));
+    assert!(client.get_account("github", "tokens").await.is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn user_accounts() -> Result<()> {
+    let client = create_client(Some("ns_user_accounts"), false, false).await?;
+    register_providers(&client).await?;
+    let user = client.create_user(&create_user_item()).await?;
+    let other = client.create_user(&create_user_item()).await?;
+
+    client
+        .link_account("github", "linked", &user.id, &AccountTokens::default())
+        .await?;
+    client
+        .link_account("gitlab", "linked", &user.id, &AccountTokens::default())
+        .await?;
+    client
+        .link_account("github", "other", &other.id, &AccountTokens::default())
+        .await?;
+
+    // relinking is a no-op but an account belongs to one user only
+    client
+        .link_account("github", "linked", &user.id, &AccountTokens::default())
+        .await?;
+    assert!(client
+        .link_account("github", "linked", &other.id, &AccountTokens::default())
+        .await
+        .is_err());
+    assert_eq!(client.get_user_accounts(&other.id).await?.len(), 1);
+
+    let accounts: Vec<_> = client.get_user_accounts(&user.id).await?.collect();
+    assert_eq!(
+        accounts
+            .iter()
+            .map(|account| account.provider.as_str())
+            .collect::<Vec<_>>(),
+        vec!["github", "gitlab"]
+    );
+    assert!(accounts
+        .iter()
+        .all(|account| account.provider_account_id == "linked"));
+    assert!(accounts[0].linked_at <= accounts[1].linked_at);
+
+    client.unlink_account("github", "linked", false).await?;
+    assert_eq!(client.get_user_accounts(&user.id).await?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn last_sign_in_method_is_kept() -> Result<()> {
+    let client = create_client(Some("ns_last_sign_in_method"), false, false).await?;
+    register_providers(&client).await?;
+    let user = client.create_user(&create_user_item()).await?;
+
+    client
+        .link_account("github", "last", &user.id, &AccountTokens::default())
+        .await?;
+    assert!(matches!(
+        client.unlink_account("github", "last", false).await,
+        Err(CoreError::LastSignInMethod)
+    ));
+    assert_eq!(client.get_user_accounts(&user.id).await?.len(), 1);
+
+    // a verified email is another way to sign in
+    client
+        .set_email_verified(&user.email, &OffsetDateTime::now_utc())
+        .await?;
+    client.unlink_account("github", "last", false).await?;
+    assert_eq!(client.get_user_accounts(&user.id).await?.len(), 0);
+
+    let other = client.create_user(&create_user_item()).await?;
+    client
+        .link_account("github", "forced", &other.id, &AccountTokens::default())
+        .await?;
+    client.unlink_account("github", "forced", true).await?;
+    assert_eq!(client.get_user_accounts(&other.id).await?.len(), 0);
+
+    // unknown accounts are ignored
+    client.unlink_account("github", "missing", false).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn account_provider_registry() -> Result<()> {
+    let client = create_client(Some("ns_account_provider_registry"), false, false).await?;
+    let user = client.create_user(&create_user_item()).await?;
+
+    let provider = client
+        .create_account_provider(&AccountProvider {
+            name: "registry".to_owned(),
+            display_name: Some("Registry".to_owned()),
+            icon_url: Some("https://example.com/icon.svg".to_owned()),
+            provider_type: ProviderType::Oidc,
+            ..Default::default()
+        })
+        .await?;
+    assert!(provider.enabled);
+    assert_eq!(
+        client.get_account_provider("registry").await?,
+        Some(provider.clone())
+    );
+    assert!(client
+        .get_account_providers()
+        .await?
+        .any(|listed| listed == provider));
+    // names are unique
+    assert!(client
+        .create_account_provider(&AccountProvider {
+            name: "registry".to_owned(),
+            ..Default::default()
+        })
+        .await
+        .is_err());
+
+    assert!(matches!(
+        client
+            .link_account("unknown", "registry", &user.id, &AccountTokens::default())
+            .await,
+        Err(CoreError::UnknownProvider(_))
+    ));
+    client
+        .link_account("registry", "registry", &user.id, &AccountTokens::default())
+        .await?;
+
+    let disabled = client
+        .update_account_provider(&AccountProvider {
+            display_name: None,
+            enabled: false,
+            ..provider.clone()
+        })
+        .await?
+        .expect("provider to exist");
+    assert_eq!(disabled.id, provider.id);
+    assert_eq!(disabled.display_name, None);
+    assert!(!disabled.enabled);
+
+    let session = Session {
+        id: Uuid::nil(),
+        expires_at: OffsetDateTime::now_utc() + Duration::HOUR,
+        session_token: "registry".to_owned(),
+        account_provider: AccountProvider {
+            name: "registry".to_owned(),
+            ..Default::default()
+        },
+        user_id: user.id,
+        refresh_at: None,
+        user_agent: None,
+        ip_address: None,
+        device_label: None,
+        created: OffsetDateTime::now_utc(),
+        last_seen_at: None,
+    };
+    assert!(matches!(
+        client.create_session(&session).await,
+        Err(CoreError::ProviderDisabled(_))
+    ));
+    assert!(matches!(
+        client
+            .create_session(&Session {
+                account_provider: AccountProvider {
+                    name: "unknown".to_owned(),
+                    ..Default::default()
+                },
+                ..session.clone()
+            })
+            .await,
+        Err(CoreError::UnknownProvider(_))
+    ));
+
+    client
+        .update_account_provider(&AccountProvider {
+            enabled: true,
+            ..disabled
+        })
+        .await?;
+    let created = client.create_session(&session).await?;
+    assert_eq!(created.account_provider.provider_type, ProviderType::Oidc);
+
+    Ok(())
+}
+
+#[test]
+fn token_cipher_binds_account() -> Result<()> {
+    let cipher = TokenCipher::from_hex(&"ab".repeat(32))?;
+
+    let encrypted = cipher.encrypt("secret", "github:1")?;
+    assert_eq!(cipher.decrypt(&encrypted, "github:1")?, "secret");
+    assert!(cipher.decrypt(&encrypted, "github:2").is_err());
+    assert_ne!(cipher.encrypt("secret", "github:1")?, encrypted);
+
+    assert!(TokenCipher::from_hex("abcd").is_err());
+
+    Ok(())
+}
@@ -0,0 +1,54 @@
+use anyhow::Result;
+use api_core::{
+    api::{MutateApiKeys, QueryApiKeys},
+    reexports::uuid::Uuid,
+    ApiKey, ApiKeyScope,
+};
+use time::{Duration, OffsetDateTime};
+
+use super::create_client;
+
+#[tokio::test]
+async fn api_key_lifecycle() -> Result<()> {
+    let client = create_client(Some("ns_api_key"), false, false).await?;
+    client.migrate().await?;
+
+    let hash = Uuid::now_v7().to_string();
+    let key = ApiKey {
+        id: Uuid::nil(),
+        name: String::from("auth frontend"),
+        prefix: String::from("ak_1234"),
+        hash: hash.clone(),
+        scopes: vec![ApiKeyScope::Accounts, ApiKeyScope::Sessions],
+        expires_at: Some(OffsetDateTime::now_utc() + Duration::DAY),
+        created: OffsetDateTime::now_utc(),
+    };
+
+    let created = client.create_api_key(&key).await?;
+    assert_ne!(created.id, key.id);
+    assert_eq!(created.scopes, key.scopes);
+    assert!(!created.is_expired(OffsetDateTime::now_utc()));
+    // hashes are unique
+    assert!(client.create_api_key(&key).await.is_err());
+
+    assert_eq!(
+        client.get_api_key_by_hash(&hash).await?,
+        Some(created.clone())
+    );
+    assert!(client.get_api_keys().await?.any(|key| key.id == created.id));
+
+    let no_expiry = client
+        .create_api_key(&ApiKey {
+            hash: Uuid::now_v7().to_string(),
+            expires_at: None,
+            ..key
+        })
+        .await?;
+    assert_eq!(no_expiry.expires_at, None);
+
+    assert_eq!(client.revoke_api_key(&created.id).await?, Some(created));
+    assert_eq!(client.get_api_key_by_hash(&hash).await?, None);
+    client.revoke_api_key(&no_expiry.id).await?;
+
+    Ok(())
+}
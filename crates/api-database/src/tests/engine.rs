@@ -0,0 +1,45 @@
+use anyhow::Result;
+use api_core::{api::MutateUsers, reexports::uuid::Uuid, Role, User, UserType};
+use time::OffsetDateTime;
+
+use crate::Client;
+
+fn user(email: &str) -> User {
+    User {
+        id: Uuid::now_v7(),
+        username: String::from("embedded"),
+        email: email.to_owned(),
+        name: None,
+        avatar: None,
+        user_type: UserType::Individual,
+        phone_number: None,
+        role: Role::User,
+        email_verified: None,
+        created: OffsetDateTime::now_utc(),
+        updated: OffsetDateTime::now_utc(),
+    }
+}
+
+#[tokio::test]
+async fn embedded_engine_applies_schema() -> Result<()> {
+    let client = Client::try_new("mem://", "", "", "embedded", "embedded", None, None).await?;
+
+    let created = client.create_user(&user("embedded@email.com")).await?;
+    assert_eq!(created.email, "embedded@email.com");
+
+    // the email assertion and unique index come from the schema file
+    assert!(client.create_user(&user("not-an-email")).await.is_err());
+    assert!(client
+        .create_user(&user("embedded@email.com"))
+        .await
+        .is_err());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn unsupported_scheme() {
+    let client = Client::try_new("unknown://", "", "", "embedded", "embedded", None, None).await;
+
+    assert!(client.is_err());
+}
@@ -0,0 +1,203 @@
+use anyhow::Result;
+use api_core::{
+    api::{MutateUsers, QueryAccountProviders},
+    reexports::uuid::Uuid,
+    ProviderType, Role, User, UserType,
+};
+use time::OffsetDateTime;
+
+use crate::{Client, ClientError, MIGRATIONS};
+
+async fn embedded_client(database: &str) -> Result<Client> {
+    let client = Client::try_new("mem://", "", "", "migrations", database, None, None).await?;
+
+    Ok(client)
+}
+
+#[test]
+fn migrations_are_ordered() {
+    assert!(MIGRATIONS
+        .windows(2)
+        .all(|pair| pair[0].version < pair[1].version));
+}
+
+#[tokio::test]
+async fn migrate_is_idempotent() -> Result<()> {
+    let client = embedded_client("idempotent").await?;
+
+    // the embedded engine applies everything on start
+    assert!(client.migrate().await?.is_empty());
+
+    let applied = client.applied_migrations().await?;
+    assert_eq!(applied.len(), MIGRATIONS.len());
+    for (record, migration) in applied.iter().zip(MIGRATIONS) {
+        assert_eq!(record.version, migration.version);
+        assert_eq!(record.checksum, migration.checksum());
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn migrate_detects_checksum_mismatch() -> Result<()> {
+    let client = embedded_client("checksum").await?;
+
+    client
+        .client
+        .query("UPDATE _migrations:1 SET checksum = 'tampered'")
+        .await?
+        .check()?;
+
+    assert!(matches!(
+        client.migrate().await,
+        Err(ClientError::Migration(_))
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rollback_and_reapply() -> Result<()> {
+    let client = embedded_client("rollback").await?;
+
+    let reverted = client.rollback(0).await?;
+    let mut expected: Vec<_> = MIGRATIONS.iter().map(|m| m.version).collect();
+    expected.reverse();
+    assert_eq!(reverted, expected);
+    assert!(client.applied_migrations().await?.is_empty());
+
+    expected.reverse();
+    assert_eq!(client.migrate().await?, expected);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn user_type_default_matches_enum() -> Result<()> {
+    let client = embedded_client("user_type").await?;
+
+    let user = client
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: String::from("casing"),
+            email: String::from("casing@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Company,
+            phone_number: None,
+            role: Role::User,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await?;
+    assert_eq!(user.user_type, UserType::Company);
+
+    let default: Option<String> = client
+        .client
+        .query("(CREATE user SET username = 'defaults', email = 'defaults@email.com').type")
+        .await?
+        .take(0)?;
+    assert_eq!(default.as_deref(), Some("Individual"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn session_tokens_are_hashed() -> Result<()> {
+    let client = embedded_client("session_tokens").await?;
+
+    let mut reverted = client.rollback(5).await?;
+    assert_eq!(reverted.last(), Some(&6));
+    client
+        .client
+        .query("CREATE user_session SET session_token = 'abc', expires_at = time::now(), in = user:a, out = account_provider:a")
+        .await?
+        .check()?;
+    reverted.reverse();
+    assert_eq!(client.migrate().await?, reverted);
+
+    let token: Option<String> = client
+        .client
+        .query("SELECT VALUE session_token FROM ONLY user_session LIMIT 1")
+        .await?
+        .take(0)?;
+    assert_eq!(
+        token.as_deref(),
+        Some("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn sessions_are_rekeyed() -> Result<()> {
+    let client = embedded_client("session_devices").await?;
+
+    let mut reverted = client.rollback(7).await?;
+    assert_eq!(reverted.last(), Some(&8));
+    client
+        .client
+        .query("RELATE user:a->user_session->account_provider:a SET session_token = 'abc', expires_at = time::now(), created = time::now()")
+        .await?
+        .check()?;
+    reverted.reverse();
+    assert_eq!(client.migrate().await?, reverted);
+
+    let id: Option<String> = client
+        .client
+        .query("SELECT VALUE meta::id(id) FROM ONLY user_session LIMIT 1")
+        .await?
+        .take(0)?;
+    assert!(Uuid::parse_str(&id.expect("session to exist")).is_ok());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn accounts_get_linked_at() -> Result<()> {
+    let client = embedded_client("account_linked_at").await?;
+
+    let mut reverted = client.rollback(11).await?;
+    assert_eq!(reverted.last(), Some(&12));
+    client
+        .client
+        .query("RELATE user:a->user_account->account_provider:a SET provider_account_id = 'abc'")
+        .await?
+        .check()?;
+    reverted.reverse();
+    assert_eq!(client.migrate().await?, reverted);
+
+    let linked: Option<bool> = client
+        .client
+        .query("SELECT VALUE type::is::datetime(linked_at) FROM ONLY user_account LIMIT 1")
+        .await?
+        .take(0)?;
+    assert_eq!(linked, Some(true));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn providers_are_registered() -> Result<()> {
+    let client = embedded_client("account_provider_registry").await?;
+
+    let mut reverted = client.rollback(12).await?;
+    assert_eq!(reverted.last(), Some(&13));
+    client
+        .client
+        .query("CREATE type::thing('account_provider', rand::uuid::v7()) SET name = 'github'")
+        .await?
+        .check()?;
+    reverted.reverse();
+    assert_eq!(client.migrate().await?, reverted);
+
+    let provider = client
+        .get_account_provider("github")
+        .await?
+        .expect("provider to exist");
+    assert!(provider.enabled);
+    assert_eq!(provider.provider_type, ProviderType::Oauth);
+
+    Ok(())
+}
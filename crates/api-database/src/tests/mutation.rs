@@ -1,9 +1,9 @@
-use super::create_client;
+use super::{create_client, register_providers};
 use anyhow::Result;
 use api_core::{
-    api::{MutateUsers, QueryUsers},
+    api::{MutateSessions, MutateUsers, QueryAccounts, QueryUsers},
     reexports::uuid::Uuid,
-    User, UserType,
+    AccountProvider, AccountTokens, Role, Session, User, UserType,
 };
 use fake::{
     faker::{
@@ -13,9 +13,10 @@ use fake::{
     locales::EN,
     Fake,
 };
-use time::OffsetDateTime;
+use surrealdb::sql::Thing;
+use time::{Duration, OffsetDateTime};
 
-fn create_user_item() -> User {
+pub(super) fn create_user_item() -> User {
     User {
         id: Uuid::now_v7(),
         name: Name(EN).fake(),
@@ -24,6 +25,8 @@ fn create_user_item() -> User {
         avatar: None,
         user_type: UserType::Individual,
         phone_number: None,
+        role: Role::User,
+        email_verified: None,
         created: OffsetDateTime::now_utc(),
         updated: OffsetDateTime::now_utc(),
     }
@@ -39,7 +42,7 @@ fn check_similarities(source: &User, dest: &User) {
 #[tokio::test]
 async fn create_user() -> Result<()> {
     dotenvy::dotenv().ok();
-    let namespace = std::env::var("TESTS_NS_CREATE")?;
+    let namespace = std::env::var("TESTS_NS_CREATE").unwrap_or_else(|_| "ns_create".to_owned());
 
     let client = create_client(Some(&namespace), false, false).await?;
 
@@ -63,7 +66,7 @@ async fn create_user() -> Result<()> {
 async fn create_get_by_id() -> Result<()> {
     dotenvy::dotenv().ok();
     let user = create_user_item();
-    let namespace = std::env::var("TESTS_NS_UPDATE")?;
+    let namespace = std::env::var("TESTS_NS_UPDATE").unwrap_or_else(|_| "ns_update".to_owned());
 
     let client = create_client(Some(&namespace), false, false).await?;
 
@@ -83,7 +86,7 @@ async fn update_user() -> Result<()> {
     dotenvy::dotenv().ok();
     let user = create_user_item();
 
-    let namespace = std::env::var("TESTS_NS_UPDATE")?;
+    let namespace = std::env::var("TESTS_NS_UPDATE").unwrap_or_else(|_| "ns_update".to_owned());
 
     let client = create_client(Some(&namespace), false, false).await?;
 
@@ -101,6 +104,11 @@ async fn update_user() -> Result<()> {
     assert_eq!(&update_res.id, &input.id);
     check_similarities(&update, &update_res);
 
+    // An unknown ID is not created by the update
+    let unknown = Uuid::now_v7();
+    assert!(client.update_user(&unknown, &update).await?.is_none());
+    assert!(client.get_user_by_id(&unknown).await?.is_none());
+
     client.delete_user(&input.id).await?;
 
     Ok(())
@@ -110,7 +118,7 @@ async fn update_user() -> Result<()> {
 async fn delete_user() -> Result<()> {
     dotenvy::dotenv().ok();
     let user = create_user_item();
-    let namespace = std::env::var("TESTS_NS_DELETE")?;
+    let namespace = std::env::var("TESTS_NS_DELETE").unwrap_or_else(|_| "ns_delete".to_owned());
 
     let client = create_client(Some(&namespace), false, false).await?;
 
@@ -125,7 +133,7 @@ async fn delete_user() -> Result<()> {
         .await?
         .expect("user to be deleted");
 
-    assert_eq!(input, deleted_user);
+    assert_eq!(input, deleted_user.user);
 
     let final_count = client.get_users().await?.count();
     assert_eq!(base_count, final_count);
@@ -133,3 +141,264 @@ async fn delete_user() -> Result<()> {
     client.delete_user(&input.id).await?;
     Ok(())
 }
+
+#[tokio::test]
+async fn delete_user_cascades() -> Result<()> {
+    let client = create_client(Some("ns_delete_cascade"), false, false).await?;
+    register_providers(&client).await?;
+
+    let session = Session {
+        expires_at: OffsetDateTime::now_utc() + Duration::DAY,
+        session_token: Uuid::now_v7().to_string(),
+        account_provider: AccountProvider::default(),
+        user_id: Uuid::nil(),
+        id: Uuid::nil(),
+        refresh_at: None,
+        user_agent: None,
+        ip_address: None,
+        device_label: None,
+        created: OffsetDateTime::now_utc(),
+        last_seen_at: None,
+    };
+    let provider_account_id = Uuid::now_v7().to_string();
+    let (user, _) = client
+        .register_user(
+            &create_user_item(),
+            "github",
+            &provider_account_id,
+            &AccountTokens::default(),
+            &session,
+        )
+        .await?;
+    client
+        .create_session(&Session {
+            session_token: Uuid::now_v7().to_string(),
+            account_provider: AccountProvider {
+                name: "github".to_owned(),
+                ..Default::default()
+            },
+            user_id: user.id,
+            ..session.clone()
+        })
+        .await?;
+    client
+        .client
+        .query("LET $listing = type::thing('listing', rand::uuid::v7()); CREATE $listing; RELATE $user -> sells -> $listing SET quantity = 1")
+        .bind(("user", Thing::from(("user", user.id.to_string().as_str()))))
+        .await?
+        .check()?;
+
+    let deleted = client
+        .delete_user(&user.id)
+        .await?
+        .expect("user to be deleted");
+    assert_eq!(deleted.user, user);
+    assert_eq!(deleted.accounts.len(), 1);
+    assert_eq!(deleted.accounts[0].provider, "github");
+    assert_eq!(deleted.accounts[0].provider_account_id, provider_account_id);
+    assert_eq!(deleted.sessions, 2);
+    assert_eq!(deleted.listings, 1);
+
+    assert!(client
+        .get_session_and_user(&session.session_token)
+        .await?
+        .is_none());
+    assert!(client
+        .get_account("github", &provider_account_id)
+        .await?
+        .is_none());
+    let dangling: Option<usize> = client
+        .client
+        .query("SELECT VALUE count() FROM ONLY sells WHERE in = type::thing('user', $user) GROUP ALL LIMIT 1")
+        .bind(("user", user.id.to_string()))
+        .await?
+        .take(0)?;
+    assert_eq!(dangling, None);
+
+    assert!(client.delete_user(&user.id).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn set_user_role() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let user = create_user_item();
+    let namespace = std::env::var("TESTS_NS_UPDATE").unwrap_or_else(|_| "ns_update".to_owned());
+
+    let client = create_client(Some(&namespace), false, false).await?;
+
+    let input = client.create_user(&user).await?;
+    assert_eq!(input.role, Role::User);
+
+    let updated = client
+        .set_user_role(&input.id, Role::Admin)
+        .await?
+        .expect("user to exist in db");
+    assert_eq!(updated.role, Role::Admin);
+    assert_eq!(
+        client
+            .get_user_by_id(&input.id)
+            .await?
+            .map(|user| user.role),
+        Some(Role::Admin)
+    );
+
+    // regular updates leave the role alone
+    let updated = client
+        .update_user(&input.id, &input)
+        .await?
+        .expect("user to exist in db");
+    assert_eq!(updated.role, Role::Admin);
+
+    client.delete_user(&input.id).await?;
+    assert!(client.set_user_role(&input.id, Role::User).await?.is_none());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn email_verification() -> Result<()> {
+    dotenvy::dotenv().ok();
+    let user = create_user_item();
+    let namespace = std::env::var("TESTS_NS_UPDATE").unwrap_or_else(|_| "ns_update".to_owned());
+
+    let client = create_client(Some(&namespace), false, false).await?;
+
+    let input = client.create_user(&user).await?;
+    assert_eq!(input.email_verified, None);
+
+    let verified_at = OffsetDateTime::now_utc();
+    let verified = client
+        .set_email_verified(&input.email, &verified_at)
+        .await?
+        .expect("user to exist in db");
+    assert_eq!(verified.id, input.id);
+    assert!(verified.email_verified.is_some());
+
+    // keeping the email keeps it verified
+    let updated = client
+        .update_user(&input.id, &input)
+        .await?
+        .expect("user to exist in db");
+    assert_eq!(updated.email_verified, verified.email_verified);
+
+    let changed = client
+        .update_user(
+            &input.id,
+            &User {
+                email: FreeEmail(EN).fake(),
+                ..input.clone()
+            },
+        )
+        .await?
+        .expect("user to exist in db");
+    assert_eq!(changed.email_verified, None);
+    assert!(client
+        .set_email_verified(&input.email, &verified_at)
+        .await?
+        .is_none());
+
+    client.delete_user(&input.id).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn register_user() -> Result<()> {
+    let client = create_client(Some("ns_register"), false, false).await?;
+    register_providers(&client).await?;
+
+    let session = Session {
+        expires_at: OffsetDateTime::now_utc() + Duration::DAY,
+        session_token: Uuid::now_v7().to_string(),
+        account_provider: AccountProvider::default(),
+        user_id: Uuid::nil(),
+        id: Uuid::nil(),
+        refresh_at: None,
+        user_agent: Some("register".to_owned()),
+        ip_address: None,
+        device_label: None,
+        created: OffsetDateTime::now_utc(),
+        last_seen_at: None,
+    };
+    let provider_account_id = Uuid::now_v7().to_string();
+
+    let (user, created) = client
+        .register_user(
+            &create_user_item(),
+            "github",
+            &provider_account_id,
+            &AccountTokens::default(),
+            &session,
+        )
+        .await?;
+    assert_eq!(created.user_id, user.id);
+    assert_eq!(created.account_provider.name, "github");
+    assert_eq!(created.user_agent.as_deref(), Some("register"));
+    let accounts: Vec<_> = client.get_user_accounts(&user.id).await?.collect();
+    assert_eq!(accounts.len(), 1);
+    assert_eq!(accounts[0].provider_account_id, provider_account_id);
+    let (found, _) = client
+        .get_session_and_user(&session.session_token)
+        .await?
+        .expect("session to exist");
+    assert_eq!(found.id, user.id);
+
+    // an account that is already linked creates nothing
+    let taken = create_user_item();
+    let other_session = Session {
+        session_token: Uuid::now_v7().to_string(),
+        ..session.clone()
+    };
+    assert!(client
+        .register_user(
+            &taken,
+            "github",
+            &provider_account_id,
+            &AccountTokens::default(),
+            &other_session,
+        )
+        .await
+        .is_err());
+    assert!(client.get_user_by_email(&taken.email).await?.is_none());
+
+    // a taken email rolls back the account and the session
+    let other_account_id = Uuid::now_v7().to_string();
+    assert!(client
+        .register_user(
+            &User {
+                email: user.email.clone(),
+                ..create_user_item()
+            },
+            "gitlab",
+            &other_account_id,
+            &AccountTokens::default(),
+            &other_session,
+        )
+        .await
+        .is_err());
+    assert!(client
+        .get_account("gitlab", &other_account_id)
+        .await?
+        .is_none());
+    assert!(client
+        .get_session_and_user(&other_session.session_token)
+        .await?
+        .is_none());
+
+    assert!(client
+        .register_user(
+            &create_user_item(),
+            "bitbucket",
+            &other_account_id,
+            &AccountTokens::default(),
+            &other_session,
+        )
+        .await
+        .is_err());
+
+    client.delete_user(&user.id).await?;
+
+    Ok(())
+}
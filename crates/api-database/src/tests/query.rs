@@ -1,6 +1,16 @@
-use crate::{collections::Collection, entity::DatabaseEntityUser, tests::create_client, Client};
+use crate::{
+    collections::Collection,
+    entity::DatabaseEntityUser,
+    tests::{create_client, mutation::create_user_item},
+    Client,
+};
 use anyhow::Result;
-use api_core::{api::QueryUsers, reexports::uuid::Uuid, User};
+use api_core::{
+    api::{CoreError, MutateUsers, Page, QueryUsers},
+    reexports::uuid::Uuid,
+    User, UserFilter, UserOrderBy, UserSearch, UserType,
+};
+use time::Duration;
 
 async fn check_users_by_id(client: Client, id: &Uuid, expected_result: bool) -> Result<()> {
     match client.get_user_by_id(id).await {
@@ -46,7 +56,7 @@ async fn query_by_available_id() -> Result<()> {
         .await?;
 
     let resp: Vec<DatabaseEntityUser> = res.take(0)?;
-    let resp: Result<Vec<User>, _> = resp.into_iter().map(|db| User::try_from(db)).collect();
+    let resp: Result<Vec<User>, _> = resp.into_iter().map(User::try_from).collect();
 
     let values = resp?;
 
@@ -63,3 +73,160 @@ async fn query_all() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn query_users_page() -> Result<()> {
+    let client = create_client(Some("ns_page"), false, false).await?;
+    client.migrate().await?;
+
+    let mut created = vec![];
+    for _ in 0..3 {
+        created.push(client.create_user(&create_user_item()).await?);
+    }
+    created.sort_by_key(|user| (user.created, user.id));
+
+    let page = client
+        .get_users_page(
+            Page::forward(Some(created[0].id), 1),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await?;
+    assert_eq!(page.items, vec![created[1].clone()]);
+    assert!(page.has_previous);
+    assert!(page.has_next);
+    assert!(page.total_count >= created.len());
+
+    let page = client
+        .get_users_page(
+            Page::backward(Some(created[2].id), 2),
+            &UserFilter::default(),
+            UserOrderBy::Created,
+        )
+        .await?;
+    assert_eq!(page.items, created[..2]);
+    assert!(page.has_next);
+
+    // a cursor whose user was deleted since can't bound the page
+    client.delete_user(&created[1].id).await?;
+    assert!(matches!(
+        client
+            .get_users_page(
+                Page::forward(Some(created[1].id), 1),
+                &UserFilter::default(),
+                UserOrderBy::Created,
+            )
+            .await,
+        Err(CoreError::InvalidCursor(cursor)) if cursor == created[1].id
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_users_page_filtered() -> Result<()> {
+    let client = create_client(Some("ns_filter"), false, false).await?;
+    client.migrate().await?;
+
+    let mut expected = vec![];
+    for username in ["zed", "amy", "bob"] {
+        let mut user = create_user_item();
+        user.username = username.to_owned();
+        user.avatar = Some(String::from("https://avatar"));
+        user.user_type = UserType::Company;
+        expected.push(client.create_user(&user).await?);
+    }
+    // not matching the filter
+    client.create_user(&create_user_item()).await?;
+    expected.sort_by(|a, b| a.username.cmp(&b.username).then(a.id.cmp(&b.id)));
+
+    let filter = UserFilter {
+        user_type: Some(UserType::Company),
+        has_avatar: Some(true),
+        created_after: Some(expected.iter().map(|u| u.created).min().unwrap() - Duration::SECOND),
+        ..Default::default()
+    };
+
+    let page = client
+        .get_users_page(Page::forward(None, 2), &filter, UserOrderBy::Username)
+        .await?;
+    assert_eq!(page.items, expected[..2]);
+    assert_eq!(page.total_count, 3);
+    assert!(page.has_next);
+
+    let page = client
+        .get_users_page(
+            Page::forward(Some(page.items[1].id), 2),
+            &filter,
+            UserOrderBy::Username,
+        )
+        .await?;
+    assert_eq!(page.items, expected[2..]);
+    assert!(!page.has_next);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_search_without_search_client() -> Result<()> {
+    let client = create_client(Some("ns_search_db"), false, false).await?;
+    client.migrate().await?;
+
+    for (username, name, user_type) in [
+        ("finder", "Jo Path", UserType::Company),
+        ("other", "Finder Smith", UserType::Company),
+        ("seeker", "Finder Jones", UserType::Individual),
+        ("unrelated", "Sam Lee", UserType::Company),
+    ] {
+        let mut user = create_user_item();
+        user.username = username.to_owned();
+        user.name = Some(name.to_owned());
+        user.user_type = user_type;
+        client.create_user(&user).await?;
+    }
+
+    let mut search = UserSearch {
+        query: String::from("find"),
+        limit: 10,
+        ..Default::default()
+    };
+    let results = client.search(&search).await?;
+    assert_eq!(results.total_hits, 3);
+    assert_eq!(results.facets.user_type[0].value, "Company");
+    assert_eq!(results.facets.user_type[0].count, 2);
+
+    let finder = results
+        .hits
+        .iter()
+        .find(|hit| hit.user.username == "finder")
+        .expect("matching username");
+    assert_eq!(finder.highlight.username, "<em>finder</em>");
+    assert_eq!(finder.matched_terms, ["finder"]);
+
+    search.filter.user_type = Some(UserType::Individual);
+    let results = client.search(&search).await?;
+    assert_eq!(results.total_hits, 1);
+    assert_eq!(results.hits[0].user.username, "seeker");
+    assert_eq!(
+        results.hits[0].highlight.name.as_deref(),
+        Some("<em>Finder</em> Jones")
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn query_users_by_ids_skips_missing() -> Result<()> {
+    let client = create_client(Some("ns_users_by_ids"), false, false).await?;
+    client.migrate().await?;
+
+    let user = client.create_user(&create_user_item()).await?;
+    let missing = Uuid::now_v7();
+
+    let users = client.users_by_ids(&[user.id, missing]).await?;
+    assert_eq!(users.len(), 1);
+    assert_eq!(users.get(&user.id), Some(&user));
+    assert!(!users.contains_key(&missing));
+
+    Ok(())
+}
@@ -1,4 +1,9 @@
 use anyhow::Result;
+use api_core::{
+    api::{MutateUsers, QueryUsers},
+    reexports::uuid::Uuid,
+    User,
+};
 
 use crate::redis::{PoolLike, PooledConnectionLike, RedisPool};
 
@@ -111,3 +116,27 @@ async fn redis_sets_check() -> Result<()> {
 
     Ok(())
 }
+
+#[tokio::test]
+async fn redis_email_change_clears_previous_email() -> Result<()> {
+    let client = super::create_client(Some("ns_redis_email"), true, false).await?;
+    let user = client
+        .create_user(&super::mutation::create_user_item())
+        .await?;
+
+    // caches the user under its current email
+    assert!(client.get_user_by_email(&user.email).await?.is_some());
+
+    let update = User {
+        email: format!("{}@email.com", Uuid::now_v7()),
+        ..user.clone()
+    };
+    client.update_user(&user.id, &update).await?;
+
+    assert!(client.get_user_by_email(&user.email).await?.is_none());
+    assert!(client.get_user_by_email(&update.email).await?.is_some());
+
+    client.delete_user(&user.id).await?;
+
+    Ok(())
+}
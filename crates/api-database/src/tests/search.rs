@@ -0,0 +1,219 @@
+use anyhow::Result;
+use api_core::{api::MutateUsers, reexports::uuid::Uuid};
+
+use super::{create_client, mutation::create_user_item};
+use crate::{
+    search::{IndexOperation, IndexQueue},
+    Client,
+};
+
+#[test]
+fn index_queue_keeps_latest_operation() {
+    let queue = IndexQueue::default();
+    let user = create_user_item();
+
+    queue.push(user.id, IndexOperation::Upsert(user.clone()));
+    queue.push(user.id, IndexOperation::Delete);
+    queue.push(Uuid::now_v7(), IndexOperation::Upsert(user));
+
+    assert_eq!(queue.len(), 2);
+}
+
+#[test]
+fn failed_index_tasks_are_requeued() -> Result<()> {
+    use meilisearch_sdk::task_info::TaskInfo;
+
+    let task = |uid: u32| -> Result<TaskInfo> {
+        Ok(serde_json::from_value(serde_json::json!({
+            "enqueuedAt": "2024-01-01T00:00:00Z",
+            "indexUid": "users",
+            "status": "enqueued",
+            "type": "documentAdditionOrUpdate",
+            "taskUid": uid
+        }))?)
+    };
+    let queue = IndexQueue::default();
+    let first = create_user_item();
+    let second = create_user_item();
+
+    queue.track(
+        task(1)?,
+        vec![
+            (first.id, IndexOperation::Upsert(first.clone())),
+            (second.id, IndexOperation::Upsert(second.clone())),
+        ],
+    );
+    queue.track(task(2)?, vec![(second.id, IndexOperation::Delete)]);
+    queue.track(task(3)?, vec![(first.id, IndexOperation::Delete)]);
+
+    // a succeeded task leaves nothing to retry
+    queue.settle(3, false);
+    assert_eq!(queue.len(), 0);
+
+    // both users were sent again later, so the failed upserts are stale
+    queue.settle(1, true);
+    assert_eq!(queue.len(), 0);
+
+    queue.settle(2, true);
+    assert_eq!(queue.len(), 1);
+
+    // settling twice does nothing
+    queue.settle(2, true);
+    assert_eq!(queue.len(), 1);
+
+    // a newer queued change is kept over the failed one
+    let third = create_user_item();
+    queue.track(task(4)?, vec![(third.id, IndexOperation::Delete)]);
+    queue.push(third.id, IndexOperation::Upsert(third.clone()));
+    queue.settle(4, true);
+    assert_eq!(queue.len(), 2);
+    assert_eq!(
+        queue.take().get(&third.id),
+        Some(&IndexOperation::Upsert(third))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_index_changes_stay_queued() -> Result<()> {
+    // nothing listens on this port, so every request to the search server fails
+    let client = Client::try_new(
+        "mem://",
+        "",
+        "",
+        "ns_search",
+        "root",
+        None,
+        Some(("http://127.0.0.1:1", None)),
+    )
+    .await?;
+
+    let user = client.create_user(&create_user_item()).await?;
+    assert_eq!(client.pending_search_operations(), 1);
+
+    client.update_user(&user.id, &user).await?;
+    assert_eq!(client.pending_search_operations(), 1);
+
+    client.create_user(&create_user_item()).await?;
+    client.delete_user(&user.id).await?;
+    assert_eq!(client.pending_search_operations(), 2);
+    assert_eq!(client.flush_search_queue().await, 2);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn failed_reindex_can_be_retried() -> Result<()> {
+    let client = Client::try_new(
+        "mem://",
+        "",
+        "",
+        "ns_search",
+        "root",
+        None,
+        Some(("http://127.0.0.1:1", None)),
+    )
+    .await?;
+
+    for _ in 0..2 {
+        let error = client.reindex_users(10).await.unwrap_err();
+        assert!(!error.to_string().contains("already running"));
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn bootstrap_requires_search_client() -> Result<()> {
+    let client = create_client(Some("ns_search"), false, false).await?;
+
+    assert!(client.bootstrap_search_index().await.is_err());
+
+    Ok(())
+}
+
+#[test]
+fn search_filter_expression() {
+    use api_core::{UserSearchFilter, UserType};
+    use time::OffsetDateTime;
+
+    use crate::search::search_filter;
+
+    assert_eq!(search_filter(&UserSearchFilter::default()), "");
+
+    let filter = UserSearchFilter {
+        user_type: Some(UserType::Company),
+        created_after: Some(OffsetDateTime::from_unix_timestamp(1).unwrap()),
+        created_before: None,
+    };
+    assert_eq!(
+        search_filter(&filter),
+        "type = Company AND created_timestamp > 1000"
+    );
+}
+
+#[test]
+fn user_document_leaves_out_contact_details() -> Result<()> {
+    use crate::search::UserDocument;
+
+    let mut user = create_user_item();
+    user.phone_number = Some(String::from("+15555550100"));
+
+    let document = serde_json::to_value(UserDocument::from(&user))?;
+    assert_eq!(document["id"], serde_json::json!(user.id));
+    assert_eq!(document["username"], serde_json::json!(user.username));
+    assert!(document.get("email").is_none());
+    assert!(document.get("phone_number").is_none());
+    assert!(document.get("email_verified").is_none());
+
+    Ok(())
+}
+
+#[test]
+fn search_hit_highlights_and_terms() -> Result<()> {
+    use meilisearch_sdk::search::SearchResult;
+
+    use crate::search::{search_facets, search_hit, IndexedUser};
+
+    let mut user = create_user_item();
+    user.username = String::from("FooBar");
+    user.name = Some(String::from("Foo Baz"));
+
+    let hit: SearchResult<IndexedUser> = serde_json::from_value(serde_json::json!({
+        "id": user.id,
+        "username": user.username,
+        "name": user.name,
+        "avatar": null,
+        "type": "Individual",
+        "created": user.created,
+        "updated": user.updated,
+        "_formatted": { "username": "<em>Foo</em>Bar", "name": "<em>Foo</em> Baz" },
+        "_matchesPosition": {
+            "username": [{ "start": 0, "length": 3 }],
+            "name": [{ "start": 0, "length": 3 }]
+        }
+    }))?;
+
+    let hit = search_hit(hit, user.clone());
+    assert_eq!(hit.user, user);
+    assert_eq!(hit.highlight.username, "<em>Foo</em>Bar");
+    assert_eq!(hit.highlight.name.as_deref(), Some("<em>Foo</em> Baz"));
+    assert_eq!(hit.matched_terms, ["foo"]);
+
+    let facets = search_facets(Some(
+        [(
+            String::from("type"),
+            [
+                (String::from("Individual"), 3),
+                (String::from("Company"), 1),
+            ]
+            .into(),
+        )]
+        .into(),
+    ));
+    assert_eq!(facets.user_type[0].value, "Company");
+    assert_eq!(facets.user_type[1].count, 3);
+
+    Ok(())
+}
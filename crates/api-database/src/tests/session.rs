@@ -0,0 +1,242 @@
+use anyhow::Result;
+use api_core::{
+    api::{MutateAccounts, MutateSessions, MutateUsers, QuerySessions, QueryUsers},
+    hash_session_token,
+    reexports::uuid::Uuid,
+    AccountProvider, AccountTokens, Session,
+};
+use time::{Duration, OffsetDateTime};
+
+use super::{create_client, mutation::create_user_item, register_providers};
+use crate::SessionPolicy;
+
+#[tokio::test]
+async fn session_policy() -> Result<()> {
+    let policy = SessionPolicy {
+        idle_timeout: Duration::HOUR,
+        max_lifetime: Duration::DAY,
+        refresh_threshold: Duration::minutes(10),
+    };
+    let client = create_client(Some("ns_session_policy"), false, false)
+        .await?
+        .with_session_policy(policy);
+    register_providers(&client).await?;
+
+    let user = client.create_user(&create_user_item()).await?;
+    client
+        .link_account("github", "policy", &user.id, &AccountTokens::default())
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+    let created = client
+        .create_session(&Session {
+            expires_at: now + Duration::weeks(1),
+            session_token: String::from("policy"),
+            account_provider: AccountProvider {
+                name: String::from("github"),
+                ..Default::default()
+            },
+            user_id: user.id,
+            id: Uuid::nil(),
+            refresh_at: None,
+            user_agent: None,
+            ip_address: None,
+            device_label: None,
+            created: OffsetDateTime::now_utc(),
+            last_seen_at: None,
+        })
+        .await?;
+
+    // only the digest is stored, and it does not sign in itself
+    assert_eq!(created.session_token, hash_session_token("policy"));
+    assert!(client
+        .get_session_and_user(&created.session_token)
+        .await?
+        .is_none());
+
+    // expiries are capped to the lifetime of the session
+    let (_, session) = client
+        .get_session_and_user("policy")
+        .await?
+        .expect("session to exist");
+    assert!(session.expires_at <= OffsetDateTime::now_utc() + Duration::DAY);
+    assert_eq!(session.refresh_at, None);
+
+    let session = client
+        .update_session("policy", &(now + Duration::HOUR))
+        .await?
+        .expect("session to exist");
+    let refresh_at = session.refresh_at.expect("session to be refreshable");
+    assert!(refresh_at > now);
+
+    // not due yet
+    let (_, session) = client
+        .get_session_and_user("policy")
+        .await?
+        .expect("session to exist");
+    assert_eq!(session.expires_at, now + Duration::HOUR);
+    assert_eq!(session.refresh_at, Some(refresh_at));
+
+    // used after the refresh threshold
+    client
+        .update_session("policy", &(now + Duration::minutes(30)))
+        .await?;
+    let (_, session) = client
+        .get_session_and_user("policy")
+        .await?
+        .expect("session to exist");
+    assert!(session.expires_at >= now + Duration::HOUR);
+    assert!(session.refresh_at.expect("session to be refreshable") > now);
+    let stored = client
+        .get_user_sessions(&user.id)
+        .await?
+        .next()
+        .expect("session to exist");
+    assert_eq!(stored.expires_at, session.expires_at);
+
+    // past the maximum lifetime
+    let expired = client.clone().with_session_policy(SessionPolicy {
+        max_lifetime: Duration::ZERO,
+        ..policy
+    });
+    assert!(expired.get_session_and_user("policy").await?.is_none());
+    assert_eq!(client.get_user_sessions(&user.id).await?.len(), 0);
+
+    client.delete_user(&user.id).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn purge_expired_sessions() -> Result<()> {
+    let client = create_client(Some("ns_session_purge"), false, false).await?;
+    register_providers(&client).await?;
+
+    let user = client.create_user(&create_user_item()).await?;
+    client
+        .link_account("github", "purge", &user.id, &AccountTokens::default())
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+    for (token, expires_at) in [
+        ("purge_1", now - Duration::HOUR),
+        ("purge_2", now - Duration::HOUR),
+        ("purge_3", now - Duration::HOUR),
+        ("purge_4", now + Duration::HOUR),
+    ] {
+        client
+            .create_session(&Session {
+                expires_at,
+                session_token: String::from(token),
+                account_provider: AccountProvider {
+                    name: String::from("github"),
+                    ..Default::default()
+                },
+                user_id: user.id,
+                id: Uuid::nil(),
+                refresh_at: None,
+                user_agent: None,
+                ip_address: None,
+                device_label: None,
+                created: OffsetDateTime::now_utc(),
+                last_seen_at: None,
+            })
+            .await?;
+    }
+
+    // expired rows are treated as absent before they are purged
+    assert!(client.get_session_and_user("purge_1").await?.is_none());
+    assert!(client
+        .update_session("purge_1", &(now + Duration::HOUR))
+        .await?
+        .is_none());
+    assert!(client.get_session_and_user("purge_1").await?.is_none());
+    let (_, live) = client
+        .get_session_and_user("purge_4")
+        .await?
+        .expect("session to exist");
+    let sessions: Vec<_> = client.get_user_sessions(&user.id).await?.collect();
+    assert_eq!(
+        client.get_session(&live.id).await?.as_ref(),
+        sessions.first()
+    );
+    assert_eq!(sessions.len(), 1);
+    let expired: Option<Uuid> = client
+        .client
+        .query("SELECT VALUE meta::id(id) FROM ONLY type::table($table) WHERE session_token = $session_token LIMIT 1")
+        .bind(("table", crate::collections::Collection::UserSession))
+        .bind(("session_token", hash_session_token("purge_1")))
+        .await?
+        .take(0)?;
+    let expired = expired.expect("expired row to be stored");
+    assert!(client.get_session(&expired).await?.is_none());
+
+    assert_eq!(client.purge_expired_sessions(2).await?, 2);
+    assert_eq!(client.purge_expired_sessions(2).await?, 1);
+    assert_eq!(client.purge_expired_sessions(2).await?, 0);
+    assert_eq!(client.get_user_sessions(&user.id).await?.len(), 1);
+
+    client.delete_user(&user.id).await?;
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn session_devices() -> Result<()> {
+    let client = create_client(Some("ns_session_devices"), false, false).await?;
+    register_providers(&client).await?;
+
+    let user = client.create_user(&create_user_item()).await?;
+    client
+        .link_account("github", "devices", &user.id, &AccountTokens::default())
+        .await?;
+
+    let created = client
+        .create_session(&Session {
+            id: Uuid::nil(),
+            expires_at: OffsetDateTime::now_utc() + Duration::HOUR,
+            session_token: String::from("devices"),
+            account_provider: AccountProvider {
+                name: String::from("github"),
+                ..Default::default()
+            },
+            user_id: user.id,
+            refresh_at: None,
+            user_agent: Some(String::from("Firefox")),
+            ip_address: Some(String::from("203.0.113.7")),
+            device_label: None,
+            created: OffsetDateTime::now_utc(),
+            last_seen_at: None,
+        })
+        .await?;
+    assert_ne!(created.id, Uuid::nil());
+    assert_eq!(created.user_agent.as_deref(), Some("Firefox"));
+    assert_eq!(created.ip_address.as_deref(), Some("203.0.113.7"));
+    assert_eq!(created.device_label, None);
+    assert_eq!(created.last_seen_at, None);
+
+    let (_, session) = client
+        .get_session_and_user("devices")
+        .await?
+        .expect("session to exist");
+    assert_eq!(session.id, created.id);
+    let last_seen_at = session.last_seen_at.expect("session to be seen");
+
+    let found = client
+        .get_session(&created.id)
+        .await?
+        .expect("session to exist");
+    assert_eq!(found.last_seen_at, Some(last_seen_at));
+
+    let revoked = client
+        .revoke_session(&created.id)
+        .await?
+        .expect("session to be revoked");
+    assert_eq!(revoked.id, created.id);
+    assert!(client.revoke_session(&created.id).await?.is_none());
+    assert!(client.get_session_and_user("devices").await?.is_none());
+
+    client.delete_user(&user.id).await?;
+
+    Ok(())
+}
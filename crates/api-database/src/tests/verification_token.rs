@@ -0,0 +1,59 @@
+use anyhow::Result;
+use api_core::{
+    api::MutateVerificationTokens, hash_verification_token, reexports::uuid::Uuid,
+    VerificationToken,
+};
+use time::{Duration, OffsetDateTime};
+
+use super::create_client;
+
+#[tokio::test]
+async fn verification_token_is_single_use() -> Result<()> {
+    let client = create_client(Some("ns_verification_token"), false, false).await?;
+
+    let identifier = format!("{}@email.com", Uuid::now_v7());
+    let token = VerificationToken {
+        identifier: identifier.clone(),
+        token: Uuid::now_v7().to_string(),
+        expires_at: OffsetDateTime::now_utc() + Duration::HOUR,
+    };
+
+    let created = client.create_verification_token(&token).await?;
+    // only the digest is stored
+    assert_eq!(
+        created,
+        VerificationToken {
+            token: hash_verification_token(&token.token),
+            ..token.clone()
+        }
+    );
+    assert!(client
+        .use_verification_token("other@email.com", &token.token)
+        .await?
+        .is_none());
+
+    // only one of two concurrent attempts gets the token
+    let (first, second) = tokio::join!(
+        client.use_verification_token(&identifier, &token.token),
+        client.use_verification_token(&identifier, &token.token),
+    );
+    let used: Vec<_> = [first?, second?].into_iter().flatten().collect();
+    assert_eq!(used, vec![created]);
+    assert!(client
+        .use_verification_token(&identifier, &token.token)
+        .await?
+        .is_none());
+
+    let expired = VerificationToken {
+        token: Uuid::now_v7().to_string(),
+        expires_at: OffsetDateTime::now_utc() - Duration::HOUR,
+        ..token
+    };
+    client.create_verification_token(&expired).await?;
+    assert!(client
+        .use_verification_token(&identifier, &expired.token)
+        .await?
+        .is_none());
+
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use std::fmt::{self, Debug};
+
+use aes_gcm::{
+    aead::{Aead, AeadCore, KeyInit, OsRng, Payload},
+    Aes256Gcm, Key, Nonce,
+};
+use api_core::api::CoreError;
+
+const NONCE_LEN: usize = 12;
+
+/// Encrypts account tokens before they are written. Each value is stored as hex encoded
+/// `nonce || ciphertext` and is bound to the account it belongs to, so it can't be moved to
+/// another row
+#[derive(Clone)]
+pub struct TokenCipher(Aes256Gcm);
+
+impl TokenCipher {
+    pub fn new(key: &[u8; 32]) -> Self {
+        Self(Aes256Gcm::new(Key::<Aes256Gcm>::from_slice(key)))
+    }
+
+    /// Reads a 256 bit key written as 64 hex characters
+    pub fn from_hex(key: &str) -> Result<Self, CoreError> {
+        let key: [u8; 32] = hex::decode(key.trim())
+            .ok()
+            .and_then(|key| key.try_into().ok())
+            .ok_or_else(|| CoreError::Other("token key must be 64 hex characters".to_owned()))?;
+
+        Ok(Self::new(&key))
+    }
+
+    pub(crate) fn encrypt(&self, plaintext: &str, account: &str) -> Result<String, CoreError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .0
+            .encrypt(
+                &nonce,
+                Payload {
+                    msg: plaintext.as_bytes(),
+                    aad: account.as_bytes(),
+                },
+            )
+            .map_err(|_| CoreError::Other("failed to encrypt account token".to_owned()))?;
+
+        let mut value = nonce.to_vec();
+        value.extend(ciphertext);
+        Ok(hex::encode(value))
+    }
+
+    pub(crate) fn decrypt(&self, value: &str, account: &str) -> Result<String, CoreError> {
+        let error = || CoreError::Other("failed to decrypt account token".to_owned());
+
+        let value = hex::decode(value).map_err(|_| error())?;
+        if value.len() < NONCE_LEN {
+            return Err(error());
+        }
+        let (nonce, ciphertext) = value.split_at(NONCE_LEN);
+
+        let plaintext = self
+            .0
+            .decrypt(
+                Nonce::from_slice(nonce),
+                Payload {
+                    msg: ciphertext,
+                    aad: account.as_bytes(),
+                },
+            )
+            .map_err(|_| error())?;
+
+        String::from_utf8(plaintext).map_err(|_| error())
+    }
+}
+
+impl Debug for TokenCipher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TokenCipher(..)")
+    }
+}
@@ -0,0 +1,114 @@
+use api_core::api::CoreError;
+pub use api_core::{hash_session_token, hash_verification_token, Viewer};
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use time::OffsetDateTime;
+use tracing::{debug, instrument, trace};
+
+use crate::Backend;
+
+#[derive(Error, Debug)]
+pub enum AuthError {
+    #[error("authorization header is not a bearer token")]
+    Malformed,
+    #[error("session token is invalid or expired")]
+    InvalidToken,
+    #[error("api key is invalid or expired")]
+    InvalidApiKey,
+    #[error(transparent)]
+    Database(#[from] CoreError),
+}
+
+/// Marks keys issued by this API
+pub const API_KEY_PREFIX: &str = "ak_";
+/// Characters of a key that are stored in the clear and shown back to callers
+pub const API_KEY_VISIBLE_LEN: usize = API_KEY_PREFIX.len() + 8;
+const API_KEY_LEN: usize = 40;
+
+const SESSION_TOKEN_LEN: usize = 48;
+const VERIFICATION_TOKEN_LEN: usize = 32;
+
+/// Creates a new random API key. Only its hash and prefix should be persisted
+pub fn generate_api_key() -> String {
+    format!("{API_KEY_PREFIX}{}", random_string(API_KEY_LEN))
+}
+
+pub fn hash_api_key(key: &str) -> String {
+    sha256_hex(key)
+}
+
+/// Creates a new random session token. Storage backends only persist its digest
+pub fn generate_session_token() -> String {
+    random_string(SESSION_TOKEN_LEN)
+}
+
+/// Creates a new random verification token, such as the secret in a sign in link. Storage
+/// backends only persist its digest
+pub fn generate_verification_token() -> String {
+    random_string(VERIFICATION_TOKEN_LEN)
+}
+
+fn random_string(len: usize) -> String {
+    // `thread_rng` is a CSPRNG seeded from the operating system
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(len)
+        .map(char::from)
+        .collect()
+}
+
+fn sha256_hex(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+/// Extracts the token from an `Authorization: Bearer <token>` header value
+pub fn bearer_token(header: &str) -> Option<&str> {
+    let (scheme, token) = header.split_once(' ')?;
+    let token = token.trim();
+
+    (scheme.eq_ignore_ascii_case("bearer") && !token.is_empty()).then_some(token)
+}
+
+/// Resolves the caller from the value of the `Authorization` header. Requests without the header
+/// are anonymous
+#[instrument(skip_all, err(Debug))]
+pub async fn authenticate<D: Backend>(
+    database: &D,
+    authorization: Option<&str>,
+) -> Result<Viewer, AuthError> {
+    let Some(authorization) = authorization else {
+        trace!("no credentials provided");
+        return Ok(Viewer::Anonymous);
+    };
+    let token = bearer_token(authorization).ok_or(AuthError::Malformed)?;
+
+    match database.get_session_and_user(token).await? {
+        Some((user, session)) if session.expires_at > OffsetDateTime::now_utc() => {
+            debug!(user_id = %user.id, "request authenticated");
+            Ok(Viewer::User {
+                user: Box::new(user),
+                session,
+            })
+        }
+        _ => Err(AuthError::InvalidToken),
+    }
+}
+
+/// Resolves the service calling with the value of the `X-Api-Key` header
+#[instrument(skip_all, err(Debug))]
+pub async fn authenticate_api_key<D: Backend>(
+    database: &D,
+    key: &str,
+) -> Result<Viewer, AuthError> {
+    match database
+        .get_api_key_by_hash(hash_api_key(key.trim()))
+        .await?
+    {
+        Some(key) if !key.is_expired(OffsetDateTime::now_utc()) => {
+            debug!(prefix = key.prefix, "request authenticated with api key");
+            Ok(Viewer::ApiKey(key))
+        }
+        _ => Err(AuthError::InvalidApiKey),
+    }
+}
@@ -0,0 +1,71 @@
+use api_core::{reexports::uuid::Uuid, ApiKeyScope, Role, Viewer};
+use async_graphql::{Context, Guard};
+
+/// Allows callers signed in with one of the given roles
+pub(crate) struct RoleGuard(&'static [Role]);
+
+impl RoleGuard {
+    pub(crate) const fn new(roles: &'static [Role]) -> Self {
+        Self(roles)
+    }
+}
+
+impl Guard for RoleGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        match Viewer::from_context(ctx).role() {
+            Some(role) if self.0.contains(&role) => Ok(()),
+            _ => Err("forbidden".into()),
+        }
+    }
+}
+
+/// Allows callers signed in as a user, whatever their role
+pub(crate) struct SignedInGuard;
+
+impl Guard for SignedInGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if Viewer::from_context(ctx).user().is_some() {
+            Ok(())
+        } else {
+            Err("forbidden".into())
+        }
+    }
+}
+
+/// Allows the user the data belongs to
+pub(crate) struct OwnerGuard(Uuid);
+
+impl OwnerGuard {
+    pub(crate) const fn new(id: Uuid) -> Self {
+        Self(id)
+    }
+}
+
+impl Guard for OwnerGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if Viewer::from_context(ctx).is_user(&self.0) {
+            Ok(())
+        } else {
+            Err("forbidden".into())
+        }
+    }
+}
+
+/// Restricts API keys to the scopes they were issued with
+pub(crate) struct ScopeGuard(ApiKeyScope);
+
+impl ScopeGuard {
+    pub(crate) const fn new(scope: ApiKeyScope) -> Self {
+        Self(scope)
+    }
+}
+
+impl Guard for ScopeGuard {
+    async fn check(&self, ctx: &Context<'_>) -> async_graphql::Result<()> {
+        if Viewer::from_context(ctx).has_scope(self.0) {
+            Ok(())
+        } else {
+            Err("forbidden".into())
+        }
+    }
+}
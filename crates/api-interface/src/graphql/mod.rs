@@ -1,13 +1,15 @@
+pub(crate) mod guard;
 pub(crate) mod mutation;
 pub(crate) mod query;
 pub(crate) mod subscription;
 
-use api_database::Client;
 use async_graphql::Context;
 use tracing::error;
 
-pub(crate) fn extract_db<'a>(context: &'a Context) -> async_graphql::Result<&'a Client> {
-    context.data::<Client>().map_err(|db| {
+use crate::Backend;
+
+pub(crate) fn extract_db<'a, D: Backend>(context: &'a Context) -> async_graphql::Result<&'a D> {
+    context.data::<D>().map_err(|db| {
         error!("{}", db.message);
         "Internal database error".into()
     })
@@ -1,53 +1,93 @@
-use api_core::api::LocalMutateAccounts;
-use api_database::Client;
-use async_graphql::{Context, Object, SimpleObject};
+use std::marker::PhantomData;
+
+use api_core::{Account, AccountTokens, ApiKeyScope, Role};
+use async_graphql::{Context, Guard, Object};
 use tracing::instrument;
 use uuid::Uuid;
 
-#[derive(Default, Debug)]
-pub struct AccountMutation;
+use crate::{
+    graphql::{
+        extract_db,
+        guard::{RoleGuard, ScopeGuard},
+    },
+    Backend,
+};
+
+pub struct AccountMutation<D>(PhantomData<D>);
 
-#[derive(SimpleObject)]
-struct Account {
-    user_id: Uuid,
-    provider: String,
-    account_id: String,
+impl<D> Default for AccountMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
 }
 
 #[Object]
-impl AccountMutation {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Backend> AccountMutation<D> {
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Accounts))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn create_account(
         &self,
         ctx: &Context<'_>,
         provider_name: String,
         provider_account_id: String,
         user_id: Uuid,
+        tokens: Option<AccountTokens>,
     ) -> async_graphql::Result<Account> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
+        let tokens = tokens.unwrap_or_default();
 
         database
-            .link_account(&provider_name, &provider_account_id, &user_id)
+            .link_account(&provider_name, &provider_account_id, &user_id, &tokens)
             .await?;
 
         Ok(Account {
             provider: provider_name,
-            account_id: provider_account_id,
+            provider_account_id,
             user_id,
+            tokens,
         })
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    /// Replaces the tokens of an account, typically after the provider refreshed them
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Accounts))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn update_account_tokens(
+        &self,
+        ctx: &Context<'_>,
+        provider: String,
+        provider_account_id: String,
+        tokens: AccountTokens,
+    ) -> async_graphql::Result<Option<Account>> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database
+            .update_account_tokens(provider, provider_account_id, &tokens)
+            .await?)
+    }
+
+    /// Refuses to unlink the user's last sign-in method unless an admin sets `force`
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Accounts))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn delete_account(
         &self,
         ctx: &Context<'_>,
         provider_account_id: String,
         provider: String,
+        #[graphql(default)] force: bool,
     ) -> async_graphql::Result<String> {
-        let database = ctx.data::<Client>()?;
+        if force {
+            RoleGuard::new(&[Role::Admin]).check(ctx).await?;
+        }
+        let database = extract_db::<D>(ctx)?;
 
         database
-            .unlink_account(provider, provider_account_id)
+            .unlink_account(provider, provider_account_id, force)
             .await?;
         Ok(String::from("item deleted"))
     }
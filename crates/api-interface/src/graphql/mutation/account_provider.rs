@@ -0,0 +1,75 @@
+use std::marker::PhantomData;
+
+use api_core::{AccountProvider, ProviderType, Role};
+use async_graphql::{Context, MaybeUndefined, Object};
+use tracing::instrument;
+
+use crate::{
+    graphql::{extract_db, guard::RoleGuard},
+    Backend,
+};
+
+pub struct AccountProviderMutation<D>(PhantomData<D>);
+
+impl<D> Default for AccountProviderMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Backend> AccountProviderMutation<D> {
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn create_account_provider(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(min_length = 1, max_length = 100))] name: String,
+        display_name: Option<String>,
+        #[graphql(validator(url))] icon_url: Option<String>,
+        provider_type: ProviderType,
+        #[graphql(default = true)] enabled: bool,
+    ) -> async_graphql::Result<AccountProvider> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database
+            .create_account_provider(&AccountProvider {
+                name,
+                display_name,
+                icon_url,
+                provider_type,
+                enabled,
+                ..Default::default()
+            })
+            .await?)
+    }
+
+    /// Omitted arguments are left unchanged, `null` clears the display name or icon
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn update_account_provider(
+        &self,
+        ctx: &Context<'_>,
+        name: String,
+        display_name: MaybeUndefined<String>,
+        #[graphql(validator(url))] icon_url: MaybeUndefined<String>,
+        provider_type: Option<ProviderType>,
+        enabled: Option<bool>,
+    ) -> async_graphql::Result<Option<AccountProvider>> {
+        let database = extract_db::<D>(ctx)?;
+
+        let Some(mut provider) = database.get_account_provider(&name).await? else {
+            return Ok(None);
+        };
+        display_name.update_to(&mut provider.display_name);
+        icon_url.update_to(&mut provider.icon_url);
+        if let Some(provider_type) = provider_type {
+            provider.provider_type = provider_type;
+        }
+        if let Some(enabled) = enabled {
+            provider.enabled = enabled;
+        }
+
+        Ok(database.update_account_provider(&provider).await?)
+    }
+}
@@ -0,0 +1,73 @@
+use std::marker::PhantomData;
+
+use api_core::{reexports::uuid::Uuid, ApiKey, ApiKeyScope, Role};
+use async_graphql::{Context, Object, SimpleObject};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    auth::{generate_api_key, hash_api_key, API_KEY_VISIBLE_LEN},
+    graphql::{extract_db, guard::RoleGuard},
+    Backend,
+};
+
+pub struct ApiKeyMutation<D>(PhantomData<D>);
+
+impl<D> Default for ApiKeyMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[derive(SimpleObject)]
+struct CreatedApiKey {
+    /// The full key. It is not stored and cannot be retrieved again
+    key: String,
+    api_key: ApiKey,
+}
+
+#[Object]
+impl<D: Backend> ApiKeyMutation<D> {
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn create_api_key(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(min_length = 1, max_length = 100))] name: String,
+        #[graphql(validator(min_items = 1))] scopes: Vec<ApiKeyScope>,
+        expires_at: Option<OffsetDateTime>,
+    ) -> async_graphql::Result<CreatedApiKey> {
+        let database = extract_db::<D>(ctx)?;
+
+        let mut scopes = scopes;
+        scopes.sort();
+        scopes.dedup();
+
+        let key = generate_api_key();
+        let api_key = database
+            .create_api_key(&ApiKey {
+                id: Uuid::nil(),
+                name,
+                prefix: key[..API_KEY_VISIBLE_LEN].to_owned(),
+                hash: hash_api_key(&key),
+                scopes,
+                expires_at,
+                created: OffsetDateTime::now_utc(),
+            })
+            .await?;
+
+        Ok(CreatedApiKey { key, api_key })
+    }
+
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn revoke_api_key(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<ApiKey>> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database.revoke_api_key(&id).await?)
+    }
+}
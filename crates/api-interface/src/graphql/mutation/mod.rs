@@ -2,17 +2,38 @@ use std::fmt::Display;
 
 use async_graphql::Enum;
 
+use crate::Backend;
+
 pub(crate) mod account;
+pub(crate) mod account_provider;
+pub(crate) mod api_key;
 pub(crate) mod session;
 pub(crate) mod user;
+pub(crate) mod verification_token;
 
-#[derive(async_graphql::MergedObject, Default)]
-pub struct Mutation(
-    user::UserMutation,
-    account::AccountMutation,
-    session::SessionMutation,
+#[derive(async_graphql::MergedObject)]
+pub struct Mutation<D: Backend>(
+    user::UserMutation<D>,
+    account::AccountMutation<D>,
+    account_provider::AccountProviderMutation<D>,
+    session::SessionMutation<D>,
+    api_key::ApiKeyMutation<D>,
+    verification_token::VerificationTokenMutation<D>,
 );
 
+impl<D: Backend> Default for Mutation<D> {
+    fn default() -> Self {
+        Self(
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
+}
+
 #[derive(Enum, Eq, PartialEq, Copy, Clone, Debug)]
 pub(crate) enum MutationType {
     Created,
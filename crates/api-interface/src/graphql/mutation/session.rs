@@ -1,65 +1,136 @@
-use api_core::{api::LocalMutateSessions, Session};
-use api_database::Client;
-use async_graphql::{Context, Object};
+use std::marker::PhantomData;
+
+use api_core::{ApiKeyScope, Role, Session, Viewer};
+use async_graphql::{Context, Object, SimpleObject};
 use time::OffsetDateTime;
 use tracing::instrument;
 use uuid::Uuid;
 
-#[derive(Default, Debug)]
-pub struct SessionMutation;
+use crate::{
+    auth::generate_session_token,
+    graphql::{
+        extract_db,
+        guard::{OwnerGuard, RoleGuard, ScopeGuard, SignedInGuard},
+    },
+    Backend,
+};
+
+pub struct SessionMutation<D>(PhantomData<D>);
+
+/// A new session and the token to sign in with. The token cannot be read back later
+#[derive(SimpleObject)]
+pub struct CreatedSession {
+    session: Session,
+    session_token: String,
+}
+
+impl<D> Default for SessionMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Object]
-impl SessionMutation {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Backend> SessionMutation<D> {
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn create_session(
         &self,
         ctx: &Context<'_>,
         input: Session,
-    ) -> async_graphql::Result<String> {
-        let database = ctx.data::<Client>()?;
+    ) -> async_graphql::Result<CreatedSession> {
+        let database = extract_db::<D>(ctx)?;
 
-        database.create_session(&input).await?;
+        let session_token = generate_session_token();
+        let session = database
+            .create_session(&Session {
+                session_token: session_token.clone(),
+                ..input
+            })
+            .await?;
 
-        Ok(String::from("session created"))
+        // the token is only ever handed out here
+        Ok(CreatedSession {
+            session,
+            session_token,
+        })
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx, id), err(Debug))]
     async fn update_session(
         &self,
         ctx: &Context<'_>,
         id: String,
         expires_at: OffsetDateTime,
     ) -> async_graphql::Result<Option<Session>> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
-        Ok(database.update_session(id, &expires_at).await?)
+        Ok(database.update_session(&id, &expires_at).await?)
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx, id), err(Debug))]
     async fn delete_session(&self, ctx: &Context<'_>, id: String) -> async_graphql::Result<String> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
-        database.delete_session(id).await?;
+        database.delete_session(&id).await?;
         Ok(String::from("item deleted"))
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn delete_expired_sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<String> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
         database.delete_expired_sessions().await?;
         Ok(String::from("expired sessions cleared"))
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "OwnerGuard::new(user_id).or(RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Sessions)))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn delete_user_session(
         &self,
         ctx: &Context<'_>,
         user_id: Uuid,
     ) -> async_graphql::Result<String> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
         database.delete_user_sessions(&user_id).await?;
         Ok(String::from("user sessions cleared"))
     }
+
+    /// Signs a device out. Users can revoke their own sessions, sessions of other users are
+    /// reported as not found
+    #[graphql(
+        guard = "SignedInGuard.or(RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions)))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn revoke_session(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+    ) -> async_graphql::Result<Option<Session>> {
+        let database = extract_db::<D>(ctx)?;
+        let viewer = Viewer::from_context(ctx);
+
+        let staff = matches!(viewer.role(), Some(Role::Service | Role::Admin))
+            && viewer.has_scope(ApiKeyScope::Sessions);
+        match database.get_session(&id).await? {
+            Some(session) if staff || viewer.is_user(&session.user_id) => {
+                Ok(database.revoke_session(&id).await?)
+            }
+            _ => Ok(None),
+        }
+    }
 }
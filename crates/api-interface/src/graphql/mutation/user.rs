@@ -1,28 +1,50 @@
+use std::marker::PhantomData;
+
 use api_core::{
-    api::{MutateUsers, Uuid},
-    User,
+    api::Uuid, AccountProvider, AccountTokens, ApiKeyScope, DeletedUser, Role, Session, User,
 };
-use api_database::Client;
-use async_graphql::{Context, Object};
+use async_graphql::{Context, Object, SimpleObject};
+use time::OffsetDateTime;
 use tracing::instrument;
 
-use crate::graphql::subscription::{broker::SimpleBroker, UserChanged};
+use crate::{
+    auth::generate_session_token,
+    graphql::{
+        extract_db,
+        guard::{OwnerGuard, RoleGuard, ScopeGuard},
+        subscription::{broker::SimpleBroker, UserChanged},
+    },
+    Backend,
+};
 
-#[derive(Default, Debug)]
-pub struct UserMutation;
+pub struct UserMutation<D>(PhantomData<D>);
+
+/// A registered user with their first session and the token to sign in with
+#[derive(SimpleObject)]
+pub struct RegisteredUser {
+    user: User,
+    session: Session,
+    session_token: String,
+}
+
+impl<D> Default for UserMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Object]
-impl UserMutation {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Backend> UserMutation<D> {
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Users))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn create_user(&self, ctx: &Context<'_>, input: User) -> async_graphql::Result<User> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
         match database.create_user(&input).await {
             Ok(user) => {
-                SimpleBroker::publish(UserChanged {
-                    mutation_type: super::MutationType::Created,
-                    id: user.id,
-                });
+                SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Created, user.id));
 
                 Ok(user)
             }
@@ -30,44 +52,140 @@ impl UserMutation {
         }
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    /// Creates the user, links their first account and opens a session in one step. Nothing is
+    /// kept when any of them fails. The session token is only returned here
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Users)).and(ScopeGuard::new(ApiKeyScope::Accounts)).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx, tokens), err(Debug))]
+    async fn register_user(
+        &self,
+        ctx: &Context<'_>,
+        input: User,
+        provider_name: String,
+        provider_account_id: String,
+        tokens: Option<AccountTokens>,
+        expires_at: OffsetDateTime,
+        user_agent: Option<String>,
+        ip_address: Option<String>,
+        device_label: Option<String>,
+    ) -> async_graphql::Result<RegisteredUser> {
+        let database = extract_db::<D>(ctx)?;
+
+        let session_token = generate_session_token();
+        let (user, session) = database
+            .register_user(
+                &input,
+                &provider_name,
+                &provider_account_id,
+                &tokens.unwrap_or_default(),
+                &Session {
+                    id: Uuid::nil(),
+                    expires_at,
+                    session_token: session_token.clone(),
+                    account_provider: AccountProvider::default(),
+                    user_id: Uuid::nil(),
+                    refresh_at: None,
+                    user_agent,
+                    ip_address,
+                    device_label,
+                    created: OffsetDateTime::now_utc(),
+                    last_seen_at: None,
+                },
+            )
+            .await?;
+        SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Created, user.id));
+
+        Ok(RegisteredUser {
+            user,
+            session,
+            session_token,
+        })
+    }
+
+    #[graphql(guard = "OwnerGuard::new(id).or(RoleGuard::new(&[Role::Admin]))")]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn update_user(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
         input: User,
     ) -> async_graphql::Result<Option<User>> {
-        let database = ctx.data::<Client>()?;
+        let database = extract_db::<D>(ctx)?;
 
         match database.update_user(&id, &input).await {
             Ok(user) => {
-                SimpleBroker::publish(UserChanged {
-                    mutation_type: super::MutationType::Updated,
-                    id,
-                });
+                SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Updated, id));
                 Ok(user)
             }
             Err(e) => Err(e.into()),
         }
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(guard = "OwnerGuard::new(id).or(RoleGuard::new(&[Role::Admin]))")]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn delete_user(
         &self,
         ctx: &Context<'_>,
         id: Uuid,
-    ) -> async_graphql::Result<Option<User>> {
-        let database = ctx.data::<Client>()?;
+    ) -> async_graphql::Result<Option<DeletedUser>> {
+        let database = extract_db::<D>(ctx)?;
 
         match database.delete_user(&id).await {
+            Ok(deleted) => {
+                SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Deleted, id));
+                Ok(deleted)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn set_user_role(
+        &self,
+        ctx: &Context<'_>,
+        id: Uuid,
+        role: Role,
+    ) -> async_graphql::Result<Option<User>> {
+        let database = extract_db::<D>(ctx)?;
+
+        match database.set_user_role(&id, role).await {
             Ok(user) => {
-                SimpleBroker::publish(UserChanged {
-                    mutation_type: super::MutationType::Deleted,
-                    id,
-                });
+                SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Updated, id));
                 Ok(user)
             }
             Err(e) => Err(e.into()),
         }
     }
+
+    /// Marks `email` as verified by consuming a token issued for it through
+    /// `createVerificationToken`. Holding the token is enough, so no role is required
+    #[instrument(skip(self, ctx, token), err(Debug))]
+    async fn verify_email(
+        &self,
+        ctx: &Context<'_>,
+        email: String,
+        token: String,
+    ) -> async_graphql::Result<Option<User>> {
+        let database = extract_db::<D>(ctx)?;
+
+        if database
+            .use_verification_token(&email, &token)
+            .await?
+            .is_none()
+        {
+            return Err("verification token is invalid or expired".into());
+        }
+
+        let user = database
+            .set_email_verified(&email, &OffsetDateTime::now_utc())
+            .await?;
+        if let Some(user) = &user {
+            SimpleBroker::publish(UserChanged::<D>::new(super::MutationType::Updated, user.id));
+        }
+
+        Ok(user)
+    }
 }
@@ -0,0 +1,70 @@
+use std::marker::PhantomData;
+
+use api_core::{ApiKeyScope, Role, VerificationToken};
+use async_graphql::{Context, Object};
+use time::OffsetDateTime;
+use tracing::instrument;
+
+use crate::{
+    auth::generate_verification_token,
+    graphql::{
+        extract_db,
+        guard::{RoleGuard, ScopeGuard},
+    },
+    Backend,
+};
+
+pub struct VerificationTokenMutation<D>(PhantomData<D>);
+
+impl<D> Default for VerificationTokenMutation<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Backend> VerificationTokenMutation<D> {
+    /// Issues a token for `identifier`, such as an email address to send a sign in link to
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn create_verification_token(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(validator(min_length = 1, max_length = 320))] identifier: String,
+        expires_at: OffsetDateTime,
+    ) -> async_graphql::Result<VerificationToken> {
+        let database = extract_db::<D>(ctx)?;
+
+        let token = generate_verification_token();
+        let created = database
+            .create_verification_token(&VerificationToken {
+                identifier,
+                token: token.clone(),
+                expires_at,
+            })
+            .await?;
+
+        // the token is only ever handed out here
+        Ok(VerificationToken { token, ..created })
+    }
+
+    /// Consumes a token. It is returned at most once, and only while it has not expired
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx, token), err(Debug))]
+    async fn use_verification_token(
+        &self,
+        ctx: &Context<'_>,
+        identifier: String,
+        token: String,
+    ) -> async_graphql::Result<Option<VerificationToken>> {
+        let database = extract_db::<D>(ctx)?;
+
+        let used = database.use_verification_token(&identifier, &token).await?;
+
+        Ok(used.map(|used| VerificationToken { token, ..used }))
+    }
+}
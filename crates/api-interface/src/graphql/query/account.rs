@@ -0,0 +1,40 @@
+use std::marker::PhantomData;
+
+use api_core::{Account, ApiKeyScope, Role};
+use async_graphql::{Context, Object};
+use tracing::instrument;
+
+use crate::{
+    graphql::{
+        extract_db,
+        guard::{RoleGuard, ScopeGuard},
+    },
+    Backend,
+};
+
+pub struct AccountQuery<D>(PhantomData<D>);
+
+impl<D> Default for AccountQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Backend> AccountQuery<D> {
+    /// Includes the account's tokens, so only services may read it
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Accounts))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn account(
+        &self,
+        ctx: &Context<'_>,
+        provider: String,
+        provider_account_id: String,
+    ) -> async_graphql::Result<Option<Account>> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database.get_account(provider, provider_account_id).await?)
+    }
+}
@@ -0,0 +1,29 @@
+use std::marker::PhantomData;
+
+use api_core::AccountProvider;
+use async_graphql::{Context, Object};
+use tracing::instrument;
+
+use crate::{graphql::extract_db, Backend};
+
+pub struct AccountProviderQuery<D>(PhantomData<D>);
+
+impl<D> Default for AccountProviderQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Backend> AccountProviderQuery<D> {
+    /// Registered providers, for rendering sign in options
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn account_providers(
+        &self,
+        ctx: &Context<'_>,
+    ) -> async_graphql::Result<Vec<AccountProvider>> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database.get_account_providers().await?.collect())
+    }
+}
@@ -0,0 +1,29 @@
+use std::marker::PhantomData;
+
+use api_core::{ApiKey, Role};
+use async_graphql::{Context, Object};
+use tracing::instrument;
+
+use crate::{
+    graphql::{extract_db, guard::RoleGuard},
+    Backend,
+};
+
+pub struct ApiKeyQuery<D>(PhantomData<D>);
+
+impl<D> Default for ApiKeyQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+#[Object]
+impl<D: Backend> ApiKeyQuery<D> {
+    #[graphql(guard = "RoleGuard::new(&[Role::Admin])")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn api_keys(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<ApiKey>> {
+        let database = extract_db::<D>(ctx)?;
+
+        Ok(database.get_api_keys().await?.collect())
+    }
+}
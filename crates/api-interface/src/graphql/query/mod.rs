@@ -1,11 +1,34 @@
 use async_graphql::connection::{Connection, EmptyFields};
 
+use crate::Backend;
+
+pub(crate) mod account;
+pub(crate) mod account_provider;
+pub(crate) mod api_key;
 pub(crate) mod pagination;
 pub(crate) mod session;
 pub(crate) mod user;
 
-#[derive(async_graphql::MergedObject, Default)]
-pub struct Query(user::UserQuery, session::SessionQuery);
+#[derive(async_graphql::MergedObject)]
+pub struct Query<D: Backend>(
+    user::UserQuery<D>,
+    session::SessionQuery<D>,
+    api_key::ApiKeyQuery<D>,
+    account::AccountQuery<D>,
+    account_provider::AccountProviderQuery<D>,
+);
+
+impl<D: Backend> Default for Query<D> {
+    fn default() -> Self {
+        Self(
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+            Default::default(),
+        )
+    }
+}
 
 pub(crate) type ConnectionResult<T> = async_graphql::Result<
     Connection<pagination::Base64Cursor, T, pagination::ConnectionFields, EmptyFields>,
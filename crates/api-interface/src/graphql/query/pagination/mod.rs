@@ -1,5 +1,9 @@
-use std::convert::Infallible;
+use std::{fmt::Display, future::Future, str::FromStr};
 
+use api_core::{
+    api::{CoreError, Page, Paged},
+    reexports::uuid::Uuid,
+};
 use async_graphql::{
     connection::{self, Connection, CursorType, Edge},
     SimpleObject,
@@ -11,7 +15,7 @@ use super::{ConnectionResult, Params};
 /// Base64 invalid states, used by `Base64Cursor`.
 pub enum Base64CursorError {
     /// Invalid cursor. This can happen if the base64 string is valid, but its contents don't
-    /// conform to the `name:index` pattern.
+    /// conform to the `name:value` pattern.
     Invalid,
     /// Decoding error. If this happens, the string isn't valid base64.
     DecodeError(base64::DecodeError),
@@ -23,23 +27,23 @@ impl std::fmt::Display for Base64CursorError {
     }
 }
 
-/// Base64 cursor implementation
-pub struct Base64Cursor {
+/// Base64 cursor implementation. Keyset pages use the last-seen id as the value
+pub struct Base64Cursor<T = Uuid> {
     name: &'static str,
-    index: usize,
+    value: T,
 }
 
-impl Base64Cursor {
-    const fn new(index: usize) -> Self {
+impl<T: Display + FromStr> Base64Cursor<T> {
+    const fn new(value: T) -> Self {
         Self {
             name: "Cursor",
-            index,
+            value,
         }
     }
 
     /// Returns a base64 string representation of the cursor
     fn encode(&self) -> String {
-        BASE64_URL_SAFE_NO_PAD.encode(format!("{}:{}", self.name, self.index))
+        BASE64_URL_SAFE_NO_PAD.encode(format!("{}:{}", self.name, self.value))
     }
 
     /// Decodes a base64 string into a cursor result
@@ -49,31 +53,25 @@ impl Base64Cursor {
             .map_err(Base64CursorError::DecodeError)?;
 
         let cursor = String::from_utf8(bytes).map_err(|_| Base64CursorError::Invalid)?;
-        let index = cursor
+        let value = cursor
             .split(':')
-            .last()
-            .map(|s| s.parse::<usize>())
+            .next_back()
+            .map(|s| s.parse::<T>())
             .ok_or(Base64CursorError::Invalid)?
             .map_err(|_| Base64CursorError::Invalid)?;
 
-        Ok(Self::new(index))
-    }
-
-    /// Increment and return the index. Uses saturating_add to avoid overflow
-    /// issues.
-    const fn increment(&self) -> usize {
-        self.index.saturating_add(1)
+        Ok(Self::new(value))
     }
 }
 
-impl From<Base64Cursor> for usize {
-    fn from(cursor: Base64Cursor) -> Self {
-        cursor.index
+impl From<Base64Cursor<Uuid>> for Uuid {
+    fn from(cursor: Base64Cursor<Uuid>) -> Self {
+        cursor.value
     }
 }
 
 /// Makes the `Base64Cursor` compatible with Relay connections
-impl CursorType for Base64Cursor {
+impl<T: Display + FromStr + Send + Sync> CursorType for Base64Cursor<T> {
     type Error = Base64CursorError;
 
     fn decode_cursor(s: &str) -> Result<Self, Self::Error> {
@@ -92,49 +90,51 @@ pub struct ConnectionFields {
     total_count: usize,
 }
 
-/// Creates a new Relay-compliant connection. Iterator must implement `ExactSizeIterator` to
-/// determine page position in the total result set.
-pub async fn paginate<T: async_graphql::OutputType, I: ExactSizeIterator<Item = T>>(
-    iter: I,
+/// Creates a new Relay-compliant connection from a keyset page. `first` reads forward from `after`
+/// and `last` reads backward from `before`, so the page is never loaded in full.
+pub async fn paginate_by_id<T, F, Fut>(
     p: Params,
     default_page_size: usize,
-) -> ConnectionResult<T> {
-    connection::query::<_, _, Base64Cursor, _, _, ConnectionFields, _, _, _, Infallible>(
+    id: fn(&T) -> Uuid,
+    fetch: F,
+) -> ConnectionResult<T>
+where
+    T: async_graphql::OutputType,
+    F: FnOnce(Page) -> Fut,
+    Fut: Future<Output = Result<Paged<T>, CoreError>>,
+{
+    connection::query::<_, _, Base64Cursor, _, _, ConnectionFields, _, _, _, async_graphql::Error>(
         p.after,
         p.before,
         p.first,
         p.last,
         |after, before, first, last| async move {
-            let iter_len = iter.len();
-
-            let (start, end) = {
-                let after = after.map(|a| a.increment()).unwrap_or(0);
-                let before: usize = before.map(|b| b.into()).unwrap_or(iter_len);
-
-                // Calculate start/end based on the provided first/last. Note that async-graphql disallows
-                // providing both (returning an error), so we can safely assume we have, at most, one of
-                // first or last.
-                match (first, last) {
-                    // First
-                    (Some(first), _) => (after, (after.saturating_add(first)).min(before)),
-                    // Last
-                    (_, Some(last)) => ((before.saturating_sub(last)).max(after), before),
-                    // Default page size
-                    _ => (after, default_page_size.min(before)),
+            let page = match (first, last) {
+                (Some(_), _) if before.is_some() => {
+                    return Err("'before' can only be used with 'last'".into())
                 }
+                (_, Some(_)) if after.is_some() => {
+                    return Err("'after' can only be used with 'first'".into())
+                }
+                (Some(first), _) => Page::forward(after.map(Uuid::from), first),
+                (_, Some(last)) => Page::backward(before.map(Uuid::from), last),
+                _ => Page::forward(after.map(Uuid::from), default_page_size),
             };
 
+            let paged = fetch(page).await?;
+
             let mut connection = Connection::with_additional_fields(
-                start > 0,
-                end < iter_len,
+                paged.has_previous,
+                paged.has_next,
                 ConnectionFields {
-                    total_count: iter_len,
+                    total_count: paged.total_count,
                 },
             );
             connection.edges.extend(
-                (start..end)
-                    .zip(iter.skip(start))
-                    .map(|(cursor, node)| Edge::new(Base64Cursor::new(cursor), node)),
+                paged
+                    .items
+                    .into_iter()
+                    .map(|node| Edge::new(Base64Cursor::new(id(&node)), node)),
             );
             Ok(connection)
         },
@@ -1,24 +1,54 @@
-use api_core::{api::QuerySessions, reexports::uuid::Uuid, Session};
+use std::marker::PhantomData;
+
+use api_core::{reexports::uuid::Uuid, ApiKeyScope, Role, Session, Viewer};
 use async_graphql::{Context, Object};
 use tracing::instrument;
 
-use crate::graphql::extract_db;
+use crate::{
+    graphql::{
+        extract_db,
+        guard::{OwnerGuard, RoleGuard, ScopeGuard, SignedInGuard},
+    },
+    Backend,
+};
 
-#[derive(Default, Debug)]
-pub struct SessionQuery;
+pub struct SessionQuery<D>(PhantomData<D>);
+
+impl<D> Default for SessionQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Object]
-impl SessionQuery {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Backend> SessionQuery<D> {
+    #[graphql(
+        guard = "OwnerGuard::new(user_id).or(RoleGuard::new(&[Role::Service, Role::Admin]).and(ScopeGuard::new(ApiKeyScope::Sessions)))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn user_sessions(
         &self,
         ctx: &Context<'_>,
         user_id: Uuid,
     ) -> async_graphql::Result<Vec<Session>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         let sessions = database.get_user_sessions(&user_id).await?;
 
         Ok(sessions.collect())
     }
+
+    /// Sessions of the signed in user across all of their devices
+    #[graphql(guard = "SignedInGuard")]
+    #[instrument(skip(self, ctx), err(Debug))]
+    async fn my_sessions(&self, ctx: &Context<'_>) -> async_graphql::Result<Vec<Session>> {
+        let database = extract_db::<D>(ctx)?;
+        let Some(user) = Viewer::from_context(ctx).user() else {
+            return Ok(vec![]);
+        };
+
+        let sessions = database.get_user_sessions(&user.id).await?;
+
+        Ok(sessions.collect())
+    }
 }
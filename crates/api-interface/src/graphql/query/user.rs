@@ -1,18 +1,29 @@
-use api_core::{api::QueryUsers, reexports::uuid::Uuid, Session, User};
+use std::marker::PhantomData;
+
+use api_core::{
+    reexports::uuid::Uuid, ApiKeyScope, Role, Session, User, UserFilter, UserOrderBy, UserSearch,
+    UserSearchFilter, UserSearchResults, Viewer,
+};
 use async_graphql::{Context, Object, SimpleObject};
 use tracing::instrument;
 
-use crate::graphql::{extract_db, query::Params};
+use crate::{
+    graphql::{
+        extract_db,
+        guard::{RoleGuard, ScopeGuard},
+        query::Params,
+    },
+    Backend,
+};
 
-use super::{pagination::paginate, ConnectionResult};
+use super::{pagination::paginate_by_id, ConnectionResult};
 
-#[derive(Default, Debug)]
-pub struct UserQuery;
+pub struct UserQuery<D>(PhantomData<D>);
 
-#[derive(SimpleObject)]
-pub struct SearchResult {
-    user: User,
-    parent_name: Option<String>,
+impl<D> Default for UserQuery<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
 }
 
 #[derive(SimpleObject)]
@@ -22,11 +33,14 @@ pub struct SessionAndUser {
 }
 
 #[Object]
-impl UserQuery {
-    #[instrument(skip(ctx), err(Debug))]
+impl<D: Backend> UserQuery<D> {
+    #[allow(clippy::too_many_arguments)]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn users(
         &self,
         ctx: &Context<'_>,
+        #[graphql(default)] filter: UserFilter,
+        #[graphql(default)] order_by: UserOrderBy,
         #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
         #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
         #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
@@ -34,27 +48,41 @@ impl UserQuery {
     ) -> ConnectionResult<User> {
         let p = Params::new(after, before, first, last)?;
 
-        let database = extract_db(ctx)?;
+        if filter.has_phone_number.is_some() && !Viewer::from_context(ctx).can_view_all_private() {
+            return Err("forbidden".into());
+        }
 
-        let users = database.get_users().await?;
+        let database = extract_db::<D>(ctx)?;
 
-        paginate(users, p, 100).await
+        paginate_by_id(
+            p,
+            100,
+            |user: &User| user.id,
+            |page| database.get_users_page(page, &filter, order_by),
+        )
+        .await
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    /// The signed in caller
+    async fn viewer(&self, ctx: &Context<'_>) -> Option<User> {
+        Viewer::from_context(ctx).user().cloned()
+    }
+
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn user_by_id(&self, ctx: &Context<'_>, id: Uuid) -> async_graphql::Result<Option<User>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         database.get_user_by_id(&id).await.map_err(|e| e.into())
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Users))")]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn user_by_email(
         &self,
         ctx: &Context<'_>,
         email: String,
     ) -> async_graphql::Result<Option<User>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         database
             .get_user_by_email(&email)
@@ -62,14 +90,17 @@ impl UserQuery {
             .map_err(|e| e.into())
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Accounts))"
+    )]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn user_by_account(
         &self,
         ctx: &Context<'_>,
         provider_account_id: String,
         provider: String,
     ) -> async_graphql::Result<Option<User>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         database
             .get_user_by_account(&provider, &provider_account_id)
@@ -77,36 +108,40 @@ impl UserQuery {
             .map_err(|e| e.into())
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[graphql(
+        guard = "RoleGuard::new(&[Role::Service]).and(ScopeGuard::new(ApiKeyScope::Sessions))"
+    )]
+    #[instrument(skip(self, ctx, session_token), err(Debug))]
     async fn user_and_session(
         &self,
         ctx: &Context<'_>,
         session_token: String,
     ) -> async_graphql::Result<Option<SessionAndUser>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
 
         let res = database.get_session_and_user(&session_token).await?;
 
-        let res = res.map(|(user, session)| SessionAndUser { session, user });
-        Ok(res)
+        Ok(res.map(|(user, session)| SessionAndUser { session, user }))
     }
 
-    #[instrument(skip(ctx), err(Debug))]
+    #[instrument(skip(self, ctx), err(Debug))]
     async fn search(
         &self,
         ctx: &Context<'_>,
         #[graphql(validator(min_length = 1, max_length = 100))] query: String,
-        #[graphql(validator(min_length = 1, max_length = 100))] after: Option<String>,
-        #[graphql(validator(min_length = 1, max_length = 100))] before: Option<String>,
-        #[graphql(validator(minimum = 1, maximum = 100))] first: Option<i32>,
-        #[graphql(validator(minimum = 1, maximum = 100))] last: Option<i32>,
-    ) -> ConnectionResult<User> {
-        let p = Params::new(after, before, first, last)?;
-
-        let database = extract_db(ctx)?;
-
-        let users = database.search(&query).await?;
-
-        paginate(users, p, 100).await
+        #[graphql(default)] filter: UserSearchFilter,
+        #[graphql(default)] offset: usize,
+        #[graphql(default = 20, validator(minimum = 1, maximum = 100))] limit: usize,
+    ) -> async_graphql::Result<UserSearchResults> {
+        let database = extract_db::<D>(ctx)?;
+
+        let search = UserSearch {
+            query,
+            filter,
+            offset,
+            limit,
+        };
+
+        database.search(&search).await.map_err(|e| e.into())
     }
 }
@@ -1,14 +1,40 @@
 pub(crate) mod broker;
 pub(crate) mod user;
+use std::marker::PhantomData;
+
 use api_core::reexports::uuid::Uuid;
 
+use crate::Backend;
+
 use super::mutation::MutationType;
 
-#[derive(async_graphql::MergedSubscription, Default)]
-pub struct Subscription(user::UserSubscription);
+#[derive(async_graphql::MergedSubscription)]
+pub struct Subscription<D: Backend>(user::UserSubscription<D>);
+
+impl<D: Backend> Default for Subscription<D> {
+    fn default() -> Self {
+        Self(Default::default())
+    }
+}
 
-#[derive(Debug, Clone)]
-pub(crate) struct UserChanged {
+pub(crate) struct UserChanged<D> {
     pub mutation_type: MutationType,
     pub id: Uuid,
+    backend: PhantomData<D>,
+}
+
+impl<D> UserChanged<D> {
+    pub fn new(mutation_type: MutationType, id: Uuid) -> Self {
+        Self {
+            mutation_type,
+            id,
+            backend: PhantomData,
+        }
+    }
+}
+
+impl<D> Clone for UserChanged<D> {
+    fn clone(&self) -> Self {
+        Self::new(self.mutation_type, self.id)
+    }
 }
@@ -1,18 +1,31 @@
-use api_core::{api::QueryUsers, User};
+use std::marker::PhantomData;
+
+use api_core::User;
 use async_graphql::{Context, Object, Subscription};
 use futures_util::{Stream, StreamExt};
 
-use crate::graphql::{extract_db, mutation::MutationType, subscription::UserChanged};
+use crate::{
+    graphql::{extract_db, mutation::MutationType, subscription::UserChanged},
+    Backend,
+};
 
 use super::broker::SimpleBroker;
 
-#[derive(Default)]
-pub struct UserSubscription;
+pub struct UserSubscription<D>(PhantomData<D>);
+
+impl<D> Default for UserSubscription<D> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
 
 #[Subscription]
-impl UserSubscription {
-    async fn users(&self, mutation_type: Option<MutationType>) -> impl Stream<Item = UserChanged> {
-        SimpleBroker::<UserChanged>::subscribe().filter(move |event| {
+impl<D: Backend> UserSubscription<D> {
+    async fn users(
+        &self,
+        mutation_type: Option<MutationType>,
+    ) -> impl Stream<Item = UserChanged<D>> {
+        SimpleBroker::<UserChanged<D>>::subscribe().filter(move |event| {
             let res = if let Some(mutation_type) = mutation_type {
                 event.mutation_type == mutation_type
             } else {
@@ -24,7 +37,7 @@ impl UserSubscription {
 }
 
 #[Object]
-impl UserChanged {
+impl<D: Backend> UserChanged<D> {
     async fn mutation_type(&self) -> MutationType {
         self.mutation_type
     }
@@ -34,7 +47,7 @@ impl UserChanged {
     }
 
     async fn user(&self, ctx: &Context<'_>) -> async_graphql::Result<Option<User>> {
-        let database = extract_db(ctx)?;
+        let database = extract_db::<D>(ctx)?;
         let user = database.get_user_by_id(&self.id).await?;
 
         Ok(user)
@@ -1,10 +1,22 @@
-use api_database::Client;
+use std::sync::Arc;
+
+use api_core::{
+    api::{
+        CoreError, MutateAccountProviders, MutateAccounts, MutateApiKeys, MutateSessions,
+        MutateUsers, MutateVerificationTokens, QueryAccountProviders, QueryAccounts, QueryApiKeys,
+        QuerySessions, QueryUsers,
+    },
+    AccountLoader,
+};
+pub use api_database::SessionPolicy;
+use api_database::{Client, TokenCipher};
 use async_graphql::{extensions::ExtensionFactory, Schema, SchemaBuilder};
 use thiserror::Error;
 use tracing::{info, instrument, trace};
 
 use self::graphql::{mutation::Mutation, query::Query, subscription::Subscription};
 
+pub mod auth;
 pub mod graphql;
 
 #[derive(Debug, Clone, Copy)]
@@ -14,6 +26,11 @@ pub struct DatabaseCredentials<'a> {
     pub db_pass: &'a str,
     pub db_ns: &'a str,
     pub db: &'a str,
+    /// Apply pending migrations once connected
+    pub migrate: bool,
+    pub session_policy: SessionPolicy,
+    /// Hex encoded 256 bit key encrypting account tokens. Without it tokens can't be stored
+    pub account_token_key: Option<&'a str>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -24,25 +41,75 @@ pub struct RedisConfig<'a> {
     pub ttl: u64,
 }
 
-pub struct ApiSchemaBuilder {
-    builder: SchemaBuilder<Query, Mutation, Subscription>,
+/// A storage backend the GraphQL schema can be built against
+pub trait Backend:
+    QueryUsers
+    + MutateUsers
+    + QueryAccounts
+    + MutateAccounts
+    + QueryAccountProviders
+    + MutateAccountProviders
+    + QuerySessions
+    + MutateSessions
+    + QueryApiKeys
+    + MutateApiKeys
+    + MutateVerificationTokens
+    + Clone
+    + Send
+    + Sync
+    + 'static
+{
+}
+
+impl<T> Backend for T where
+    T: QueryUsers
+        + MutateUsers
+        + QueryAccounts
+        + MutateAccounts
+        + QueryAccountProviders
+        + MutateAccountProviders
+        + QuerySessions
+        + MutateSessions
+        + QueryApiKeys
+        + MutateApiKeys
+        + MutateVerificationTokens
+        + Clone
+        + Send
+        + Sync
+        + 'static
+{
+}
+
+pub type ApiSchema<D> = Schema<Query<D>, Mutation<D>, Subscription<D>>;
+
+pub struct ApiSchemaBuilder<D: Backend = Client> {
+    builder: SchemaBuilder<Query<D>, Mutation<D>, Subscription<D>>,
+    database: D,
 }
 
 #[derive(Error, Debug)]
 pub enum SchemaError {
     #[error(transparent)]
     DatabaseError(#[from] api_database::ClientError),
+    #[error("invalid account token key: {0}")]
+    AccountTokenKey(CoreError),
 }
 
-impl ApiSchemaBuilder {
+impl ApiSchemaBuilder<Client> {
     #[instrument(skip_all, fields(db.url = %database.db_dsn), name = "schema.init")]
     pub async fn new(
         database: DatabaseCredentials<'_>,
         redis: Option<RedisConfig<'_>>,
         meilisearch: Option<(&str, Option<&str>)>,
     ) -> Result<Self, SchemaError> {
+        let token_cipher = database
+            .account_token_key
+            .map(TokenCipher::from_hex)
+            .transpose()
+            .map_err(SchemaError::AccountTokenKey)?;
+
         trace!("creating database client");
-        let db_client = Client::try_new(
+        let mut db_client = Client::try_new(
             database.db_dsn,
             database.db_user,
             database.db_pass,
@@ -51,18 +118,37 @@ impl ApiSchemaBuilder {
             redis.map(|f| (f.redis_dsn, f.clustered, f.pool_size, f.ttl)),
             meilisearch,
         )
-        .await?;
+        .await?
+        .with_session_policy(database.session_policy);
+
+        if let Some(token_cipher) = token_cipher {
+            db_client = db_client.with_token_cipher(token_cipher);
+        }
 
         info!("database database client created");
 
+        if database.migrate {
+            let applied = db_client.migrate().await?;
+            info!(count = applied.len(), "pending migrations applied");
+        }
+
+        Ok(Self::with_database(db_client))
+    }
+}
+
+impl<D: Backend> ApiSchemaBuilder<D> {
+    #[instrument(skip_all, name = "schema.init")]
+    pub fn with_database(database: D) -> Self {
         let builder = Schema::build(
             Query::default(),
             Mutation::default(),
             Subscription::default(),
         )
-        .data(db_client);
+        .data(database.clone())
+        .data(Arc::new(database.clone()) as Arc<dyn AccountLoader>);
 
-        let builder = Self {
+        Self {
+            database,
             builder: {
                 #[cfg(debug_assertions)]
                 {
@@ -74,9 +160,7 @@ impl ApiSchemaBuilder {
                     builder.disable_introspection()
                 }
             },
-        };
-
-        Ok(builder)
+        }
     }
 
     #[instrument(skip(self, extension), name = "schema.ext")]
@@ -84,11 +168,17 @@ impl ApiSchemaBuilder {
         trace!("attaching extension to schema");
         Self {
             builder: self.builder.extension(extension),
+            ..self
         }
     }
 
+    /// The backend the schema reads from, for resolving callers outside of GraphQL
+    pub fn database(&self) -> &D {
+        &self.database
+    }
+
     #[instrument(skip(self), name = "schema.build")]
-    pub fn build(self) -> Schema<Query, Mutation, Subscription> {
+    pub fn build(self) -> ApiSchema<D> {
         trace!("building schema");
         self.builder.finish()
     }
@@ -0,0 +1,826 @@
+use api_core::{
+    api::{
+        MutateAccounts, MutateApiKeys, MutateSessions, MutateUsers, MutateVerificationTokens,
+        QueryAccounts, QuerySessions, QueryUsers,
+    },
+    memory::MemoryStore,
+    reexports::uuid::Uuid,
+    AccountProvider, AccountTokens, ApiKey, ApiKeyScope, Role, Session, User, UserType,
+    VerificationToken,
+};
+use time::{Duration, OffsetDateTime};
+
+use crate::{
+    auth::{
+        authenticate, authenticate_api_key, bearer_token, generate_api_key, generate_session_token,
+        hash_api_key, hash_session_token, hash_verification_token, AuthError, Viewer,
+        API_KEY_PREFIX, API_KEY_VISIBLE_LEN,
+    },
+    ApiSchemaBuilder,
+};
+
+async fn create_session(store: &MemoryStore, token: &str, expires_in: Duration) -> User {
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: format!("viewer_{token}"),
+            email: format!("{token}@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            role: Role::User,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+
+    store
+        .link_account("github", token, &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+    store
+        .create_session(&Session {
+            expires_at: OffsetDateTime::now_utc() + expires_in,
+            session_token: token.to_owned(),
+            account_provider: AccountProvider {
+                name: String::from("github"),
+                ..Default::default()
+            },
+            user_id: user.id,
+            id: Uuid::nil(),
+            refresh_at: None,
+            user_agent: None,
+            ip_address: None,
+            device_label: None,
+            created: OffsetDateTime::now_utc(),
+            last_seen_at: None,
+        })
+        .await
+        .unwrap();
+
+    user
+}
+
+#[test]
+fn parse_bearer_token() {
+    assert_eq!(bearer_token("Bearer abc"), Some("abc"));
+    assert_eq!(bearer_token("bearer  abc "), Some("abc"));
+    assert_eq!(bearer_token("Basic abc"), None);
+    assert_eq!(bearer_token("Bearer "), None);
+    assert_eq!(bearer_token("abc"), None);
+}
+
+#[tokio::test]
+async fn authenticate_viewer() {
+    let store = super::memory_store().await;
+    let user = create_session(&store, "valid", Duration::HOUR).await;
+    create_session(&store, "expired", -Duration::HOUR).await;
+
+    assert_eq!(authenticate(&store, None).await.unwrap(), Viewer::Anonymous);
+
+    let viewer = authenticate(&store, Some("Bearer valid")).await.unwrap();
+    assert_eq!(viewer.user(), Some(&user));
+
+    assert!(matches!(
+        authenticate(&store, Some("Bearer expired")).await,
+        Err(AuthError::InvalidToken)
+    ));
+    assert!(matches!(
+        authenticate(&store, Some("Bearer unknown")).await,
+        Err(AuthError::InvalidToken)
+    ));
+    assert!(matches!(
+        authenticate(&store, Some("valid")).await,
+        Err(AuthError::Malformed)
+    ));
+}
+
+#[tokio::test]
+async fn gql_viewer() {
+    let store = super::memory_store().await;
+    let user = create_session(&store, "valid", Duration::HOUR).await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+
+    let query = "query { viewer { id username } }";
+
+    let res = schema.execute(query).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(res.data, async_graphql::value!({ "viewer": null }));
+
+    let viewer = authenticate(&store, Some("Bearer valid")).await.unwrap();
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(viewer))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap()["viewer"]["id"],
+        user.id.to_string()
+    );
+}
+
+#[tokio::test]
+async fn gql_private_fields() {
+    let store = super::memory_store().await;
+    let user = create_session(&store, "owner", Duration::HOUR).await;
+    create_session(&store, "other", Duration::HOUR).await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+
+    let query = format!(
+        r#"query {{ userById(id: "{}") {{ username email }} }}"#,
+        user.id
+    );
+
+    let res = schema.execute(query.as_str()).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "userById": { "username": user.username.as_str(), "email": null } })
+    );
+
+    let other = authenticate(&store, Some("Bearer other")).await.unwrap();
+    let res = schema
+        .execute(async_graphql::Request::new(query.as_str()).data(other))
+        .await;
+    assert!(res.errors.is_empty());
+    assert!(res.data.into_json().unwrap()["userById"]["email"].is_null());
+
+    let owner = authenticate(&store, Some("Bearer owner")).await.unwrap();
+    let res = schema
+        .execute(async_graphql::Request::new(query.as_str()).data(owner))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap()["userById"]["email"],
+        user.email
+    );
+}
+
+#[tokio::test]
+async fn authenticate_api_keys() {
+    let store = super::memory_store().await;
+    let key = generate_api_key();
+    assert!(key.starts_with(API_KEY_PREFIX));
+    assert_ne!(key, generate_api_key());
+
+    let api_key = |key: &str, expires_at| ApiKey {
+        id: Uuid::nil(),
+        name: String::from("auth frontend"),
+        prefix: key[..API_KEY_VISIBLE_LEN].to_owned(),
+        hash: hash_api_key(key),
+        scopes: vec![ApiKeyScope::Sessions],
+        expires_at,
+        created: OffsetDateTime::now_utc(),
+    };
+
+    let created = store.create_api_key(&api_key(&key, None)).await.unwrap();
+    let viewer = authenticate_api_key(&store, &key).await.unwrap();
+    assert_eq!(viewer, Viewer::ApiKey(created));
+    assert_eq!(viewer.role(), Some(Role::Service));
+    assert!(viewer.has_scope(ApiKeyScope::Sessions));
+    assert!(!viewer.has_scope(ApiKeyScope::Users));
+
+    let expired = generate_api_key();
+    store
+        .create_api_key(&api_key(
+            &expired,
+            Some(OffsetDateTime::now_utc() - Duration::SECOND),
+        ))
+        .await
+        .unwrap();
+    assert!(matches!(
+        authenticate_api_key(&store, &expired).await,
+        Err(AuthError::InvalidApiKey)
+    ));
+    assert!(matches!(
+        authenticate_api_key(&store, &generate_api_key()).await,
+        Err(AuthError::InvalidApiKey)
+    ));
+}
+
+#[tokio::test]
+async fn gql_api_keys() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let admin = super::viewer_with_role(Role::Admin);
+
+    let create = r#"mutation {
+        createApiKey(name: "auth frontend", scopes: [SESSIONS]) { key apiKey { id prefix scopes } }
+    }"#;
+
+    let res = schema
+        .execute(async_graphql::Request::new(create).data(super::viewer_with_role(Role::Service)))
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(create).data(admin.clone()))
+        .await;
+    assert!(res.errors.is_empty());
+    let created = res.data.into_json().unwrap()["createApiKey"].clone();
+    let key = created["key"].as_str().unwrap().to_owned();
+    assert_eq!(
+        created["apiKey"]["prefix"].as_str(),
+        Some(&key[..API_KEY_VISIBLE_LEN])
+    );
+
+    // listing only shows the prefix
+    let res = schema
+        .execute(async_graphql::Request::new("query { apiKeys { prefix } }").data(admin.clone()))
+        .await;
+    assert!(res.errors.is_empty());
+    assert!(!res.data.to_string().contains(&key));
+
+    // keys are limited to their scopes
+    let viewer = authenticate_api_key(&store, &key).await.unwrap();
+    let res = schema
+        .execute(
+            async_graphql::Request::new("mutation { deleteExpiredSessions }").data(viewer.clone()),
+        )
+        .await;
+    assert!(res.errors.is_empty());
+    let res = schema
+        .execute(
+            async_graphql::Request::new(
+                r#"mutation { createUser(input: { username: "service", email: "service@email.com", userType: INDIVIDUAL }) { id } }"#,
+            )
+            .data(viewer),
+        )
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let revoke = format!(
+        r#"mutation {{ revokeApiKey(id: "{}") {{ id }} }}"#,
+        created["apiKey"]["id"].as_str().unwrap()
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(revoke).data(admin))
+        .await;
+    assert!(res.errors.is_empty());
+    assert!(matches!(
+        authenticate_api_key(&store, &key).await,
+        Err(AuthError::InvalidApiKey)
+    ));
+}
+
+#[tokio::test]
+async fn gql_session_tokens() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let service = super::viewer_with_role(Role::Service);
+    let user = create_session(&store, "existing", Duration::HOUR).await;
+    assert_ne!(generate_session_token(), generate_session_token());
+
+    let create = format!(
+        r#"mutation {{
+            createSession(input: {{ expiresAt: "{}", accountProvider: {{ name: "github" }}, userId: "{}" }}) {{ sessionToken session {{ id }} }}
+        }}"#,
+        (OffsetDateTime::now_utc() + Duration::HOUR)
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap(),
+        user.id
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(create).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json().unwrap();
+    let token = data["createSession"]["sessionToken"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+    let session_id = data["createSession"]["session"]["id"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    // only the digest is stored
+    let stored: Vec<_> = store
+        .get_user_sessions(&user.id)
+        .await
+        .unwrap()
+        .map(|session| session.session_token)
+        .collect();
+    assert!(stored.contains(&hash_session_token(&token)));
+    assert!(!stored.contains(&token));
+
+    let query = format!(
+        r#"query {{ userAndSession(sessionToken: "{token}") {{ session {{ id }} user {{ id }} }} }}"#
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty());
+    let data = res.data.into_json().unwrap();
+    assert_eq!(data["userAndSession"]["session"]["id"], session_id);
+    assert_eq!(data["userAndSession"]["user"]["id"], user.id.to_string());
+
+    // neither the token nor its digest can be read back
+    let query = format!(
+        r#"query {{ userAndSession(sessionToken: "{token}") {{ session {{ sessionToken }} }} }}"#
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(service.clone()))
+        .await;
+    assert!(!res.errors.is_empty());
+
+    // the digest itself does not authenticate
+    assert!(matches!(
+        authenticate(
+            &store,
+            Some(&format!("Bearer {}", hash_session_token(&token)))
+        )
+        .await,
+        Err(AuthError::InvalidToken)
+    ));
+    assert!(authenticate(&store, Some(&format!("Bearer {token}")))
+        .await
+        .is_ok());
+
+    let delete = format!(r#"mutation {{ deleteSession(id: "{token}") }}"#);
+    let res = schema
+        .execute(async_graphql::Request::new(delete).data(service))
+        .await;
+    assert!(res.errors.is_empty());
+    assert!(matches!(
+        authenticate(&store, Some(&format!("Bearer {token}"))).await,
+        Err(AuthError::InvalidToken)
+    ));
+}
+
+#[tokio::test]
+async fn gql_session_devices() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    create_session(&store, "owner", Duration::HOUR).await;
+    create_session(&store, "other", Duration::HOUR).await;
+    let owner = authenticate(&store, Some("Bearer owner")).await.unwrap();
+    let other = authenticate(&store, Some("Bearer other")).await.unwrap();
+
+    let query = "query { mySessions { id lastSeenAt } }";
+    let res = schema.execute(query).await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(owner.clone()))
+        .await;
+    assert!(res.errors.is_empty());
+    let sessions = res.data.into_json().unwrap()["mySessions"].clone();
+    assert_eq!(sessions.as_array().map(Vec::len), Some(1));
+    assert!(!sessions[0]["lastSeenAt"].is_null());
+    let id = sessions[0]["id"].as_str().unwrap().to_owned();
+
+    let revoke = format!(r#"mutation {{ revokeSession(id: "{id}") {{ id }} }}"#);
+    let res = schema.execute(revoke.as_str()).await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    // other users cannot tell the session exists
+    let res = schema
+        .execute(async_graphql::Request::new(revoke.as_str()).data(other))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(res.data, async_graphql::value!({ "revokeSession": null }));
+
+    let res = schema
+        .execute(async_graphql::Request::new(revoke.as_str()).data(owner))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(res.data.into_json().unwrap()["revokeSession"]["id"], id);
+    assert!(matches!(
+        authenticate(&store, Some("Bearer owner")).await,
+        Err(AuthError::InvalidToken)
+    ));
+}
+
+#[tokio::test]
+async fn gql_verification_tokens() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let service = super::viewer_with_role(Role::Service);
+
+    let create = format!(
+        r#"mutation {{
+            createVerificationToken(identifier: "user@email.com", expiresAt: "{}") {{ token }}
+        }}"#,
+        (OffsetDateTime::now_utc() + Duration::HOUR)
+            .format(&time::format_description::well_known::Rfc3339)
+            .unwrap()
+    );
+    let res = schema
+        .execute(
+            async_graphql::Request::new(create.as_str()).data(super::viewer_with_role(Role::User)),
+        )
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(create.as_str()).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let token = res.data.into_json().unwrap()["createVerificationToken"]["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    // only the digest is stored
+    let used = store
+        .use_verification_token("user@email.com", &token)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(used.token, hash_verification_token(&token));
+
+    let res = schema
+        .execute(async_graphql::Request::new(create.as_str()).data(service.clone()))
+        .await;
+    let token = res.data.into_json().unwrap()["createVerificationToken"]["token"]
+        .as_str()
+        .unwrap()
+        .to_owned();
+
+    let use_token = format!(
+        r#"mutation {{ useVerificationToken(identifier: "user@email.com", token: "{token}") {{ identifier token }} }}"#
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(use_token.as_str()).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "useVerificationToken": { "identifier": "user@email.com", "token": token.as_str() } })
+    );
+
+    let res = schema
+        .execute(async_graphql::Request::new(use_token.as_str()).data(service))
+        .await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "useVerificationToken": null })
+    );
+}
+
+#[tokio::test]
+async fn gql_verify_email() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let user = create_session(&store, "verify", Duration::HOUR).await;
+    store
+        .create_verification_token(&VerificationToken {
+            identifier: user.email.clone(),
+            token: String::from("secret"),
+            expires_at: OffsetDateTime::now_utc() + Duration::HOUR,
+        })
+        .await
+        .unwrap();
+
+    let verify = |token: &str| {
+        format!(
+            r#"mutation {{ verifyEmail(email: "{}", token: "{token}") {{ id }} }}"#,
+            user.email
+        )
+    };
+
+    let res = schema.execute(verify("wrong").as_str()).await;
+    assert_eq!(
+        res.errors[0].message,
+        "verification token is invalid or expired"
+    );
+
+    let res = schema.execute(verify("secret").as_str()).await;
+    assert!(res.errors.is_empty());
+    assert_eq!(
+        res.data.into_json().unwrap()["verifyEmail"]["id"],
+        user.id.to_string()
+    );
+    assert!(store
+        .get_user_by_id(&user.id)
+        .await
+        .unwrap()
+        .and_then(|user| user.email_verified)
+        .is_some());
+
+    // tokens are single use
+    let res = schema.execute(verify("secret").as_str()).await;
+    assert_eq!(
+        res.errors[0].message,
+        "verification token is invalid or expired"
+    );
+}
+
+#[tokio::test]
+async fn gql_account_tokens() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let service = super::viewer_with_role(Role::Service);
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: "tokens".to_owned(),
+            email: "tokens@email.com".to_owned(),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            email_verified: None,
+            role: Role::User,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+
+    let create = format!(
+        r#"mutation {{
+            createAccount(providerName: "github", providerAccountId: "1234", userId: "{}", tokens: {{ accessToken: "access", refreshToken: "refresh", tokenType: "bearer" }}) {{
+                providerAccountId accountId tokens {{ accessToken refreshToken }}
+            }}
+        }}"#,
+        user.id
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(create.as_str()).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "createAccount": { "providerAccountId": "1234", "accountId": "1234", "tokens": { "accessToken": "access", "refreshToken": "refresh" } } })
+    );
+
+    let update = r#"mutation {
+        updateAccountTokens(provider: "github", providerAccountId: "1234", tokens: { accessToken: "refreshed" }) {
+            tokens { accessToken refreshToken }
+        }
+    }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(update).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+
+    let query = r#"{ account(provider: "github", providerAccountId: "1234") { userId tokens { accessToken refreshToken } } }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(super::viewer_with_role(Role::Admin)))
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(service))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "account": { "userId": user.id.to_string(), "tokens": { "accessToken": "refreshed", "refreshToken": null } } })
+    );
+}
+
+#[tokio::test]
+async fn gql_delete_last_account() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: "last".to_owned(),
+            email: "last@email.com".to_owned(),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            email_verified: None,
+            role: Role::User,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+    store
+        .link_account("github", "last", &user.id, &AccountTokens::default())
+        .await
+        .unwrap();
+
+    let delete = r#"mutation { deleteAccount(provider: "github", providerAccountId: "last") }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(delete).data(super::viewer_with_role(Role::Service)))
+        .await;
+    assert_eq!(
+        res.errors[0].message,
+        "cannot unlink the user's last sign-in method"
+    );
+
+    let force =
+        r#"mutation { deleteAccount(provider: "github", providerAccountId: "last", force: true) }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(force).data(super::viewer_with_role(Role::Service)))
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(force).data(super::viewer_with_role(Role::Admin)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(store.get_user_accounts(&user.id).await.unwrap().len(), 0);
+}
+
+#[tokio::test]
+async fn gql_account_providers() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let service = super::viewer_with_role(Role::Service);
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: "providers".to_owned(),
+            email: "providers@email.com".to_owned(),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            email_verified: None,
+            role: Role::User,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+
+    let create = r#"mutation {
+        createAccountProvider(name: "okta", displayName: "Okta", providerType: OIDC) {
+            name displayName providerType enabled
+        }
+    }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(create).data(service.clone()))
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let res = schema
+        .execute(async_graphql::Request::new(create).data(super::viewer_with_role(Role::Admin)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "createAccountProvider": { "name": "okta", "displayName": "Okta", "providerType": "OIDC", "enabled": true } })
+    );
+
+    let link = format!(
+        r#"mutation {{
+            createAccount(providerName: "okta", providerAccountId: "okta-1", userId: "{}") {{
+                providerAccountId
+            }}
+        }}"#,
+        user.id
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(link.as_str()).data(service.clone()))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+
+    let disable = r#"mutation {
+        updateAccountProvider(name: "okta", displayName: null, enabled: false) {
+            displayName enabled
+        }
+    }"#;
+    let res = schema
+        .execute(async_graphql::Request::new(disable).data(super::viewer_with_role(Role::Admin)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "updateAccountProvider": { "displayName": null, "enabled": false } })
+    );
+
+    let link = format!(
+        r#"mutation {{
+            createAccount(providerName: "okta", providerAccountId: "okta-2", userId: "{}") {{
+                providerAccountId
+            }}
+        }}"#,
+        user.id
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(link.as_str()).data(service.clone()))
+        .await;
+    assert_eq!(res.errors[0].message, "account provider `okta` is disabled");
+
+    let link = format!(
+        r#"mutation {{
+            createAccount(providerName: "bitbucket", providerAccountId: "1", userId: "{}") {{
+                providerAccountId
+            }}
+        }}"#,
+        user.id
+    );
+    let res = schema
+        .execute(async_graphql::Request::new(link.as_str()).data(service))
+        .await;
+    assert_eq!(
+        res.errors[0].message,
+        "account provider `bitbucket` is not registered"
+    );
+
+    let res = schema
+        .execute("query { accountProviders { name enabled } }")
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({ "accountProviders": [
+            { "name": "github", "enabled": true },
+            { "name": "gitlab", "enabled": true },
+            { "name": "okta", "enabled": false },
+        ] })
+    );
+}
+
+#[tokio::test]
+async fn gql_register_user() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let expires_at = (OffsetDateTime::now_utc() + Duration::HOUR)
+        .format(&time::format_description::well_known::Rfc3339)
+        .unwrap();
+    let register = |provider: &str, email: &str| {
+        format!(
+            r#"mutation {{
+                registerUser(input: {{ username: "registered", email: "{email}", userType: INDIVIDUAL }}, providerName: "{provider}", providerAccountId: "1234", expiresAt: "{expires_at}", userAgent: "curl") {{
+                    user {{ id email }}
+                    session {{ userId userAgent }}
+                    sessionToken
+                }}
+            }}"#
+        )
+    };
+
+    let res = schema
+        .execute(
+            async_graphql::Request::new(register("github", "registered@email.com"))
+                .data(super::viewer_with_role(Role::User)),
+        )
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let service = super::viewer_with_role(Role::Service);
+    let res = schema
+        .execute(
+            async_graphql::Request::new(register("github", "registered@email.com"))
+                .data(service.clone()),
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json().unwrap();
+    let user_id = data["registerUser"]["user"]["id"].as_str().unwrap();
+    let session = &data["registerUser"]["session"];
+    assert_eq!(session["userId"].as_str(), Some(user_id));
+    assert_eq!(session["userAgent"].as_str(), Some("curl"));
+
+    // the returned token signs the new user in
+    let token = data["registerUser"]["sessionToken"].as_str().unwrap();
+    let viewer = authenticate(&store, Some(&format!("Bearer {token}")))
+        .await
+        .unwrap();
+    assert!(matches!(viewer, Viewer::User { ref user, .. } if user.id.to_string() == user_id));
+
+    let res = schema
+        .execute(
+            async_graphql::Request::new(register("bitbucket", "other@email.com")).data(service),
+        )
+        .await;
+    assert_eq!(
+        res.errors[0].message,
+        "account provider `bitbucket` is not registered"
+    );
+    assert_eq!(store.get_users().await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn gql_delete_user_reports_removed() {
+    let store = super::memory_store().await;
+    let schema = ApiSchemaBuilder::with_database(store.clone()).build();
+    let user = create_session(&store, "deleted", Duration::HOUR).await;
+
+    let delete = format!(
+        r#"mutation {{
+            deleteUser(id: "{}") {{ user {{ id }} accounts {{ provider providerAccountId }} sessions listings }}
+        }}"#,
+        user.id
+    );
+    let res = schema
+        .execute(
+            async_graphql::Request::new(delete.as_str()).data(super::viewer_with_role(Role::Admin)),
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        res.data,
+        async_graphql::value!({
+            "deleteUser": {
+                "user": { "id": user.id.to_string() },
+                "accounts": [{ "provider": "github", "providerAccountId": "deleted" }],
+                "sessions": 1,
+                "listings": 0,
+            }
+        })
+    );
+}
@@ -5,9 +5,15 @@ use async_graphql::{
     Request, ServerResult,
 };
 
-use crate::{ApiSchemaBuilder, DatabaseCredentials};
+use crate::{ApiSchema, ApiSchemaBuilder};
+use api_core::{
+    api::MutateAccountProviders, memory::MemoryStore, reexports::uuid::Uuid, AccountProvider, Role,
+    Session, User, UserType, Viewer,
+};
 use async_trait::async_trait;
+use time::{Duration, OffsetDateTime};
 
+mod auth;
 mod mutation;
 mod query;
 mod subscription;
@@ -34,31 +40,59 @@ impl Extension for TestExtension {
     }
 }
 
-async fn init_schema() -> async_graphql::Schema<
-    crate::graphql::query::Query,
-    crate::graphql::mutation::Mutation,
-    crate::graphql::subscription::Subscription,
-> {
-    dotenvy::dotenv().ok();
-    let db_host = std::env::var("TEST_DATABASE_URL").expect("TEST_DATABASE_URL");
-    let db_host = db_host.replace("http://", "");
+/// A memory store with the account providers the tests sign in through
+async fn memory_store() -> MemoryStore {
+    let store = MemoryStore::new();
 
-    let username = std::env::var("TEST_DATABASE_USERNAME").expect("TEST_DATABASE_USERNAME");
-    let password = std::env::var("TEST_DATABASE_PASSWORD").expect("TEST_DATABASE_PASSWORD");
-    let db_namespace = std::env::var("TEST_DATABASE_NAMESPACE").expect("TEST_DATABASE_NAMESPACE");
-    let db_name = std::env::var("TEST_DATABASE_NAME").expect("TEST_DATABASE_NAME");
+    for name in ["github", "gitlab"] {
+        store
+            .create_account_provider(&AccountProvider {
+                name: name.into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+    }
 
-    let database_credentials = DatabaseCredentials {
-        db_dsn: &db_host,
-        db_user: &username,
-        db_pass: &password,
-        db_ns: &db_namespace,
-        db: &db_name,
-    };
+    store
+}
 
-    ApiSchemaBuilder::new(database_credentials, None, None)
-        .await
-        .expect("schema created successfully")
+async fn init_schema() -> ApiSchema<MemoryStore> {
+    ApiSchemaBuilder::with_database(memory_store().await)
         .with_extension(DummyExtension)
         .build()
 }
+
+/// A signed in caller that is not backed by a stored user
+fn viewer_with_role(role: Role) -> Viewer {
+    let id = Uuid::now_v7();
+
+    Viewer::User {
+        user: Box::new(User {
+            id,
+            username: format!("{role:?}").to_lowercase(),
+            email: format!("{id}@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            role,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        }),
+        session: Session {
+            expires_at: OffsetDateTime::now_utc() + Duration::HOUR,
+            session_token: id.to_string(),
+            account_provider: AccountProvider::default(),
+            user_id: id,
+            id: Uuid::nil(),
+            refresh_at: None,
+            user_agent: None,
+            ip_address: None,
+            device_label: None,
+            created: OffsetDateTime::now_utc(),
+            last_seen_at: None,
+        },
+    }
+}
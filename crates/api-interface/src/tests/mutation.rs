@@ -1,20 +1,24 @@
 use core::panic;
 
-use async_graphql::Schema;
+use api_core::{memory::MemoryStore, Role, Viewer};
+use async_graphql::Request;
 use fake::{
     faker::internet::{en::Username, raw::FreeEmail},
     locales::EN,
     Fake,
 };
 
-use crate::graphql::{mutation::Mutation, query::Query, subscription::Subscription};
+use crate::ApiSchema;
 
 async fn execute_mutation(
     query: &str,
-    schema: &Schema<Query, Mutation, Subscription>,
+    schema: &ApiSchema<MemoryStore>,
+    viewer: &Viewer,
     mutation: &str,
 ) -> String {
-    let res = schema.execute(query).await;
+    let res = schema
+        .execute(Request::new(query).data(viewer.clone()))
+        .await;
 
     dbg!(query);
     dbg!(&res.errors);
@@ -58,6 +62,7 @@ async fn execute_mutation(
 #[tokio::test]
 async fn gql_mutation() {
     let schema = super::init_schema().await;
+    let viewer = super::viewer_with_role(Role::Admin);
     let username = format!("\"{}\"", Username().fake::<String>());
     let email = format!("\"{}\"", FreeEmail(EN).fake::<String>());
 
@@ -71,7 +76,7 @@ async fn gql_mutation() {
             "
     );
 
-    let id = execute_mutation(&create_mutation, &schema, "createUser").await;
+    let id = execute_mutation(&create_mutation, &schema, &viewer, "createUser").await;
     dbg!("id", &id);
 
     let update_mutation = format!(
@@ -84,19 +89,93 @@ async fn gql_mutation() {
             "
     );
 
-    let id_2 = execute_mutation(&update_mutation, &schema, "updateUser").await;
+    let id_2 = execute_mutation(&update_mutation, &schema, &viewer, "updateUser").await;
     assert_eq!(&id, &id_2);
 
     let delete_mutation = format!(
         r"
             mutation {{
               deleteUser(id: {id}) {{
+                user {{ id }}
+              }}
+            }}
+            "
+    );
+
+    let res = schema
+        .execute(Request::new(delete_mutation).data(viewer))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(
+        format!(
+            "\"{}\"",
+            res.data.into_json().unwrap()["deleteUser"]["user"]["id"]
+                .as_str()
+                .unwrap()
+        ),
+        id
+    );
+}
+
+#[tokio::test]
+async fn gql_mutation_guards() {
+    let schema = super::init_schema().await;
+    let service = super::viewer_with_role(Role::Service);
+    let username = format!("\"{}\"", Username().fake::<String>());
+    let email = format!("\"{}\"", FreeEmail(EN).fake::<String>());
+
+    let create_mutation = format!(
+        r"
+            mutation {{
+              createUser(input: {{ username: {username}, email: {email}, userType: INDIVIDUAL }}) {{
                 id
               }}
             }}
             "
     );
 
-    let id_3 = execute_mutation(&delete_mutation, &schema, "deleteUser").await;
-    assert_eq!(&id, &id_3);
+    let res = schema.execute(create_mutation.as_str()).await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let id = execute_mutation(&create_mutation, &schema, &service, "createUser").await;
+
+    let update_mutation = format!(
+        r"
+            mutation {{
+              updateUser(id: {id}, input: {{ username: {username}, email: {email}, userType: COMPANY }}) {{
+                id
+              }}
+            }}
+            "
+    );
+
+    for viewer in [
+        Viewer::Anonymous,
+        super::viewer_with_role(Role::User),
+        super::viewer_with_role(Role::Support),
+    ] {
+        let res = schema
+            .execute(Request::new(update_mutation.as_str()).data(viewer))
+            .await;
+        assert_eq!(res.errors[0].message, "forbidden");
+    }
+
+    let role_mutation = format!(
+        r"
+            mutation {{
+              setUserRole(id: {id}, role: SUPPORT) {{
+                id
+              }}
+            }}
+            "
+    );
+
+    let res = schema
+        .execute(Request::new(role_mutation.as_str()).data(service.clone()))
+        .await;
+    assert_eq!(res.errors[0].message, "forbidden");
+
+    let admin = super::viewer_with_role(Role::Admin);
+    let id_2 = execute_mutation(&role_mutation, &schema, &admin, "setUserRole").await;
+    assert_eq!(&id, &id_2);
 }
@@ -31,19 +31,32 @@ async fn gql_query() -> Result<(), Box<dyn std::error::Error>> {
 
 #[tokio::test]
 async fn gql_query_user() {
+    use api_core::Role;
+
     let schema = super::init_schema().await;
 
-    let res = schema
-        .execute(
-            r#"
+    let query = r#"
            query {
              userByEmail(email: "user@email.com") {
                id,
                name
              }
            } 
-           "#,
-        )
+           "#;
+
+    for viewer in [
+        api_core::Viewer::Anonymous,
+        super::viewer_with_role(Role::User),
+        super::viewer_with_role(Role::Admin),
+    ] {
+        let res = schema
+            .execute(async_graphql::Request::new(query).data(viewer))
+            .await;
+        assert_eq!(res.errors[0].message, "forbidden");
+    }
+
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(super::viewer_with_role(Role::Service)))
         .await;
 
     assert!(res.errors.is_empty());
@@ -93,3 +106,330 @@ async fn gql_query_user_by_id_ok() {
     dbg!(&res.errors);
     assert!(res.errors.is_empty());
 }
+
+#[tokio::test]
+async fn gql_query_shared_backend() {
+    use api_core::{api::MutateUsers, reexports::uuid::Uuid, Role, User, UserType};
+    use time::OffsetDateTime;
+
+    let store = super::memory_store().await;
+    let schema = crate::ApiSchemaBuilder::with_database(store.clone()).build();
+
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: String::from("backend"),
+            email: String::from("backend@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            role: Role::User,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+
+    let res = schema
+        .execute(format!(
+            r#"
+           query {{
+             userById(id: "{}") {{
+               username
+             }}
+           }}
+           "#,
+            user.id
+        ))
+        .await;
+
+    assert!(res.errors.is_empty());
+    assert_eq!(res.data.to_string(), r#"{userById: {username: "backend"}}"#);
+}
+
+#[tokio::test]
+async fn gql_query_user_accounts() {
+    use api_core::{
+        api::{MutateAccounts, MutateUsers},
+        reexports::uuid::Uuid,
+        AccountTokens, Role, User, UserType,
+    };
+    use async_graphql::Request;
+    use time::OffsetDateTime;
+
+    let store = super::memory_store().await;
+    let schema = crate::ApiSchemaBuilder::with_database(store.clone()).build();
+
+    let user = store
+        .create_user(&User {
+            id: Uuid::now_v7(),
+            username: String::from("connected"),
+            email: String::from("connected@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            role: Role::User,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await
+        .unwrap();
+    for (provider, id) in [("github", "1234"), ("gitlab", "5678")] {
+        store
+            .link_account(provider, id, &user.id, &AccountTokens::default())
+            .await
+            .unwrap();
+    }
+
+    let query = format!(
+        r#"{{ userById(id: "{}") {{ accounts {{ provider providerAccountId linkedAt }} }} }}"#,
+        user.id
+    );
+
+    // accounts are private to the user and to staff
+    let res = schema
+        .execute(Request::new(query.as_str()).data(super::viewer_with_role(Role::User)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(res.data.to_string(), "{userById: {accounts: null}}");
+
+    let res = schema
+        .execute(Request::new(query.as_str()).data(super::viewer_with_role(Role::Support)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json().unwrap();
+    let accounts = data["userById"]["accounts"].as_array().unwrap();
+    assert_eq!(accounts.len(), 2);
+    assert_eq!(accounts[0]["provider"], "github");
+    assert_eq!(accounts[0]["providerAccountId"], "1234");
+    assert_eq!(accounts[1]["provider"], "gitlab");
+    assert!(accounts[0]["linkedAt"].is_string());
+}
+
+#[tokio::test]
+async fn gql_query_users_by_cursor() {
+    use api_core::{api::MutateUsers, reexports::uuid::Uuid, Role, User, UserType};
+    use time::OffsetDateTime;
+
+    let store = super::memory_store().await;
+    let schema = crate::ApiSchemaBuilder::with_database(store.clone()).build();
+
+    let mut created = vec![];
+    for i in 0..3 {
+        let user = store
+            .create_user(&User {
+                id: Uuid::now_v7(),
+                username: format!("cursor{i}"),
+                email: format!("cursor{i}@email.com"),
+                name: None,
+                avatar: None,
+                user_type: UserType::Individual,
+                phone_number: None,
+                role: Role::User,
+                email_verified: None,
+                created: OffsetDateTime::now_utc(),
+                updated: OffsetDateTime::now_utc(),
+            })
+            .await
+            .unwrap();
+        created.push(user);
+    }
+    created.sort_by_key(|user| (user.created, user.id));
+
+    let query = |args: &str| {
+        format!(
+            r#"
+           query {{
+             users({args}) {{
+               totalCount
+               edges {{
+                 node {{
+                   username
+                 }}
+               }}
+               pageInfo {{
+                 endCursor
+                 hasNextPage
+                 hasPreviousPage
+               }}
+             }}
+           }}
+           "#
+        )
+    };
+
+    let res = schema.execute(query("first: 2")).await;
+    assert!(res.errors.is_empty());
+    let data = res.data.into_json().unwrap();
+    let users = &data["users"];
+    assert_eq!(users["totalCount"], 3);
+    assert_eq!(
+        users["edges"][1]["node"]["username"],
+        created[1].username.as_str()
+    );
+    assert_eq!(users["pageInfo"]["hasNextPage"], true);
+
+    let cursor = users["pageInfo"]["endCursor"].as_str().unwrap();
+    let res = schema
+        .execute(query(&format!(r#"first: 2, after: "{cursor}""#)))
+        .await;
+    assert!(res.errors.is_empty());
+    let data = res.data.into_json().unwrap();
+    let users = &data["users"];
+    assert_eq!(users["edges"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        users["edges"][0]["node"]["username"],
+        created[2].username.as_str()
+    );
+    assert_eq!(users["pageInfo"]["hasNextPage"], false);
+    assert_eq!(users["pageInfo"]["hasPreviousPage"], true);
+
+    let res = schema
+        .execute(query(&format!(r#"first: 2, before: "{cursor}""#)))
+        .await;
+    assert!(!res.errors.is_empty());
+}
+
+#[tokio::test]
+async fn gql_query_users_filtered() {
+    use api_core::{api::MutateUsers, reexports::uuid::Uuid, Role, User, UserType, Viewer};
+    use time::OffsetDateTime;
+
+    let store = super::memory_store().await;
+    let schema = crate::ApiSchemaBuilder::with_database(store.clone()).build();
+
+    for (username, user_type) in [
+        ("zed", UserType::Company),
+        ("amy", UserType::Company),
+        ("bob", UserType::Individual),
+    ] {
+        store
+            .create_user(&User {
+                id: Uuid::now_v7(),
+                username: username.to_owned(),
+                email: format!("{username}@email.com"),
+                name: None,
+                avatar: None,
+                user_type,
+                phone_number: None,
+                role: Role::User,
+                email_verified: None,
+                created: OffsetDateTime::now_utc(),
+                updated: OffsetDateTime::now_utc(),
+            })
+            .await
+            .unwrap();
+    }
+
+    let res = schema
+        .execute(
+            r#"
+           query {
+             users(first: 10, filter: { userType: COMPANY }, orderBy: USERNAME) {
+               totalCount
+               edges {
+                 node {
+                   username
+                 }
+               }
+             }
+           }
+           "#,
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json().unwrap();
+    let users = &data["users"];
+    assert_eq!(users["totalCount"], 2);
+    assert_eq!(users["edges"][0]["node"]["username"], "amy");
+    assert_eq!(users["edges"][1]["node"]["username"], "zed");
+
+    let query = "query { users(first: 10, filter: { hasPhoneNumber: false }) { totalCount } }";
+    for viewer in [Viewer::Anonymous, super::viewer_with_role(Role::User)] {
+        let res = schema
+            .execute(async_graphql::Request::new(query).data(viewer))
+            .await;
+        assert_eq!(res.errors[0].message, "forbidden");
+    }
+
+    let res = schema
+        .execute(async_graphql::Request::new(query).data(super::viewer_with_role(Role::Support)))
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    assert_eq!(res.data.into_json().unwrap()["users"]["totalCount"], 3);
+}
+
+#[tokio::test]
+async fn gql_search_users() {
+    use api_core::{api::MutateUsers, reexports::uuid::Uuid, Role, User, UserType};
+    use time::OffsetDateTime;
+
+    let store = super::memory_store().await;
+    let schema = crate::ApiSchemaBuilder::with_database(store.clone()).build();
+
+    for (username, user_type) in [
+        ("finder", UserType::Company),
+        ("pathfinder", UserType::Individual),
+        ("other", UserType::Company),
+    ] {
+        store
+            .create_user(&User {
+                id: Uuid::now_v7(),
+                username: username.to_owned(),
+                email: format!("{username}@email.com"),
+                name: None,
+                avatar: None,
+                user_type,
+                phone_number: None,
+                role: Role::User,
+                email_verified: None,
+                created: OffsetDateTime::now_utc(),
+                updated: OffsetDateTime::now_utc(),
+            })
+            .await
+            .unwrap();
+    }
+
+    let res = schema
+        .execute(
+            r#"
+           query {
+             search(query: "finder", filter: { userType: COMPANY }, limit: 5) {
+               totalHits
+               limit
+               hits {
+                 user {
+                   username
+                 }
+                 highlight {
+                   username
+                 }
+                 matchedTerms
+               }
+               facets {
+                 userType {
+                   value
+                   count
+                 }
+               }
+             }
+           }
+           "#,
+        )
+        .await;
+    assert!(res.errors.is_empty(), "{:?}", res.errors);
+    let data = res.data.into_json().unwrap();
+    let search = &data["search"];
+    assert_eq!(search["totalHits"], 1);
+    assert_eq!(search["limit"], 5);
+    assert_eq!(search["hits"][0]["user"]["username"], "finder");
+    assert_eq!(
+        search["hits"][0]["highlight"]["username"],
+        "<em>finder</em>"
+    );
+    assert_eq!(search["facets"]["userType"][0]["value"], "Company");
+}
@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use api_core::api::MutateSessions;
+use rand::Rng;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, instrument};
+
+/// How often expired sessions are purged and how many are removed per query
+#[derive(Debug, Clone, Copy)]
+pub struct SessionCleanup {
+    pub interval: Duration,
+    pub jitter: Duration,
+    pub batch_size: usize,
+}
+
+impl SessionCleanup {
+    /// The delay before the next run, randomised so replicas do not purge in lockstep
+    fn next_delay(&self) -> Duration {
+        let jitter = rand::thread_rng().gen_range(0..=self.jitter.as_millis() as u64);
+        self.interval + Duration::from_millis(jitter)
+    }
+}
+
+/// Spawns the cleanup loop on the runtime. Nothing is spawned when the interval or batch size
+/// is zero
+pub fn spawn<D>(database: D, config: SessionCleanup) -> Option<JoinHandle<()>>
+where
+    D: MutateSessions + Send + Sync + 'static,
+{
+    if config.interval.is_zero() || config.batch_size == 0 {
+        info!("session cleanup disabled");
+        return None;
+    }
+
+    Some(tokio::spawn(async move {
+        loop {
+            tokio::time::sleep(config.next_delay()).await;
+            purge(&database, config.batch_size).await;
+        }
+    }))
+}
+
+/// Deletes expired sessions one batch at a time until a batch comes back short
+#[instrument(name = "sessions.cleanup", skip(database))]
+pub async fn purge<D: MutateSessions>(database: &D, batch_size: usize) -> usize {
+    let mut total = 0;
+
+    loop {
+        match database.purge_expired_sessions(batch_size).await {
+            Ok(purged) => {
+                metrics::counter!("sessions_purged_total").increment(purged as u64);
+                total += purged;
+                if purged < batch_size {
+                    break;
+                }
+            }
+            Err(e) => {
+                error!("{e}");
+                break;
+            }
+        }
+    }
+
+    debug!(total, "expired sessions purged");
+
+    total
+}
@@ -1,4 +1,7 @@
+mod cleanup;
+mod migrate;
 mod routes;
+mod search;
 mod state;
 mod telemetry;
 
@@ -9,9 +12,9 @@ use std::future::ready;
 
 use anyhow::Result;
 use async_graphql::extensions::Tracing;
-use async_graphql_axum::{GraphQL, GraphQLSubscription};
+use async_graphql_axum::GraphQLSubscription;
 use axum::{
-    http::{header, HeaderValue, Method},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method},
     middleware,
     routing::get,
     Router,
@@ -22,8 +25,12 @@ use tracing::info;
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 
 use crate::routes::{
-    handler,
-    middleware::{graphql::Metrics, track_metrics},
+    graphql_handler, handler,
+    middleware::{
+        auth::{authenticate, API_KEY_HEADER},
+        graphql::Metrics,
+        track_metrics,
+    },
 };
 
 const SUBSCRIPTION_ENDPOINT: &str = "/ws";
@@ -32,6 +39,13 @@ const SUBSCRIPTION_ENDPOINT: &str = "/ws";
 async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
 
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("migrate") => return migrate::run(args).await,
+        Some("search") => return search::run(args).await,
+        _ => {}
+    }
+
     let _sentry_guard = telemetry::initialise()?;
 
     let state = state::AppState::try_from_env()?;
@@ -60,27 +74,60 @@ async fn create_router(state: state::AppState) -> Result<Router> {
     .with_extension(Tracing)
     .with_extension(Metrics);
 
+    let database = schema_builder.database().clone();
     let schema = schema_builder.build();
 
+    cleanup::spawn(database.clone(), state.session_cleanup);
+
     let router = Router::new()
-        .route("/", get(handler).post_service(GraphQL::new(schema.clone())))
+        .route(
+            "/",
+            get(handler)
+                .post(graphql_handler)
+                .layer(middleware::from_fn_with_state(database, authenticate)),
+        )
         .route(
             "/metrics",
             get(move || ready(state.metrics_handle.render())),
         )
-        .route_service(SUBSCRIPTION_ENDPOINT, GraphQLSubscription::new(schema))
+        .route_service(
+            SUBSCRIPTION_ENDPOINT,
+            GraphQLSubscription::new(schema.clone()),
+        )
         .route_layer(middleware::from_fn(track_metrics))
         .layer(TraceLayer::new_for_http().make_span_with(make_span))
-        .layer(
-            CorsLayer::new()
-                .allow_origin(state.frontend_url.parse::<HeaderValue>()?)
-                .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION])
-                .allow_methods([Method::GET, Method::POST]),
-        );
+        .layer(cors_layer(&state.frontend_url)?)
+        .with_state(schema);
 
     Ok(router)
 }
 
+/// Lets the frontend send credentials as a bearer token or an API key
+fn cors_layer(frontend_url: &str) -> Result<CorsLayer> {
+    Ok(CorsLayer::new()
+        .allow_origin(frontend_url.parse::<HeaderValue>()?)
+        .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, API_KEY_HEADER])
+        .allow_methods([Method::GET, Method::POST]))
+}
+
+/// Headers carrying credentials, which are never recorded on request spans
+const REDACTED_HEADERS: [HeaderName; 2] = [header::AUTHORIZATION, API_KEY_HEADER];
+
+/// Copies the headers with the values of [`REDACTED_HEADERS`] masked
+fn redact_headers(headers: &HeaderMap) -> HeaderMap {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if REDACTED_HEADERS.contains(name) {
+                HeaderValue::from_static("[redacted]")
+            } else {
+                value.clone()
+            };
+            (name.clone(), value)
+        })
+        .collect()
+}
+
 fn make_span(request: &axum::http::Request<axum::body::Body>) -> tracing::Span {
     let headers = request.headers();
 
@@ -98,6 +145,7 @@ fn make_span(request: &axum::http::Request<axum::body::Body>) -> tracing::Span {
         propagator.extract(&header_map)
     });
 
+    let headers = redact_headers(headers);
     let span = tracing::info_span!("users.request", ?headers);
     span.set_parent(parent_ctx);
     span
@@ -0,0 +1,70 @@
+use anyhow::{bail, Context, Result};
+use api_database::{Client, MIGRATIONS};
+
+use crate::state::AppState;
+
+const USAGE: &str = "usage: api-users migrate [up | down <version> | status]";
+
+enum Command {
+    Up,
+    Down(u32),
+    Status,
+}
+
+/// Runs the `migrate` subcommand against the configured database
+pub async fn run(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let command = match args.next().as_deref() {
+        None | Some("up") => Command::Up,
+        Some("down") => Command::Down(
+            args.next()
+                .context(USAGE)?
+                .parse()
+                .context("version must be a number")?,
+        ),
+        Some("status") => Command::Status,
+        Some(command) => bail!("unknown command `{command}`\n{USAGE}"),
+    };
+
+    let state = AppState::try_from_env()?;
+    let database = state.database_credentials();
+
+    let client = Client::try_new(
+        database.db_dsn,
+        database.db_user,
+        database.db_pass,
+        database.db_ns,
+        database.db,
+        None,
+        None,
+    )
+    .await?;
+
+    match command {
+        Command::Up => {
+            let applied = client.migrate().await?;
+            if applied.is_empty() {
+                println!("database is up to date");
+            }
+            for version in applied {
+                println!("applied {version}");
+            }
+        }
+        Command::Down(target) => {
+            for version in client.rollback(target).await? {
+                println!("reverted {version}");
+            }
+        }
+        Command::Status => {
+            let applied = client.applied_migrations().await?;
+
+            for migration in MIGRATIONS {
+                match applied.iter().find(|m| m.version == migration.version) {
+                    Some(record) => println!("{} applied {}", migration.name, record.applied_at),
+                    None => println!("{} pending", migration.name),
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
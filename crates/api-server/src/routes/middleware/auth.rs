@@ -0,0 +1,51 @@
+use api_database::Client;
+use api_interface::auth::{self, AuthError};
+use axum::{
+    extract::{Request, State},
+    http::{header, header::ToStrError, HeaderName, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tracing::error;
+
+pub(crate) const API_KEY_HEADER: HeaderName = HeaderName::from_static("x-api-key");
+
+/// Resolves the caller from the API key or bearer token and attaches it to the request as a
+/// `Viewer`. API keys take precedence when both are sent
+pub async fn authenticate(
+    State(database): State<Client>,
+    mut req: Request,
+    next: Next,
+) -> Response {
+    let (api_key, authorization) = match (
+        header_value(&req, API_KEY_HEADER.as_str()),
+        header_value(&req, header::AUTHORIZATION.as_str()),
+    ) {
+        (Ok(api_key), Ok(authorization)) => (api_key, authorization),
+        _ => return (StatusCode::UNAUTHORIZED, AuthError::Malformed.to_string()).into_response(),
+    };
+
+    let viewer = match api_key {
+        Some(api_key) => auth::authenticate_api_key(&database, &api_key).await,
+        None => auth::authenticate(&database, authorization.as_deref()).await,
+    };
+
+    match viewer {
+        Ok(viewer) => {
+            req.extensions_mut().insert(viewer);
+            next.run(req).await
+        }
+        Err(AuthError::Database(e)) => {
+            error!("{e}");
+            StatusCode::INTERNAL_SERVER_ERROR.into_response()
+        }
+        Err(e) => (StatusCode::UNAUTHORIZED, e.to_string()).into_response(),
+    }
+}
+
+fn header_value(req: &Request, name: &str) -> Result<Option<String>, ToStrError> {
+    req.headers()
+        .get(name)
+        .map(|value| value.to_str().map(str::to_owned))
+        .transpose()
+}
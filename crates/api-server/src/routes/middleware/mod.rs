@@ -1,3 +1,4 @@
+pub mod auth;
 pub mod graphql;
 
 use std::time::Instant;
@@ -1,6 +1,17 @@
 pub mod middleware;
 
-use axum::response::IntoResponse;
+use api_database::Client;
+use api_interface::{auth::Viewer, ApiSchema};
+use async_graphql_axum::{GraphQLRequest, GraphQLResponse};
+use axum::{extract::State, response::IntoResponse, Extension};
+
+pub async fn graphql_handler(
+    State(schema): State<ApiSchema<Client>>,
+    Extension(viewer): Extension<Viewer>,
+    req: GraphQLRequest,
+) -> GraphQLResponse {
+    schema.execute(req.into_inner().data(viewer)).await.into()
+}
 
 pub async fn handler() -> impl IntoResponse {
     #[cfg(debug_assertions)]
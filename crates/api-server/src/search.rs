@@ -0,0 +1,51 @@
+use anyhow::{bail, Context, Result};
+use api_database::{Client, REINDEX_BATCH_SIZE};
+
+use crate::state::AppState;
+
+const USAGE: &str = "usage: api-users search [bootstrap | reindex [batch size]]";
+
+enum Command {
+    Bootstrap,
+    Reindex(usize),
+}
+
+/// Runs the `search` subcommand against the configured search server
+pub async fn run(mut args: impl Iterator<Item = String>) -> Result<()> {
+    let command = match args.next().as_deref() {
+        Some("bootstrap") => Command::Bootstrap,
+        Some("reindex") => Command::Reindex(match args.next() {
+            Some(size) => size.parse().context("batch size must be a number")?,
+            None => REINDEX_BATCH_SIZE,
+        }),
+        Some(command) => bail!("unknown command `{command}`\n{USAGE}"),
+        None => bail!(USAGE),
+    };
+
+    let state = AppState::try_from_env()?;
+    let database = state.database_credentials();
+
+    let client = Client::try_new(
+        database.db_dsn,
+        database.db_user,
+        database.db_pass,
+        database.db_ns,
+        database.db,
+        None,
+        Some(state.meilisearch_credentials()),
+    )
+    .await?;
+
+    match command {
+        Command::Bootstrap => {
+            client.bootstrap_search_index().await?;
+            println!("search index is configured");
+        }
+        Command::Reindex(batch_size) => {
+            let count = client.reindex_users(batch_size).await?;
+            println!("indexed {count} users");
+        }
+    }
+
+    Ok(())
+}
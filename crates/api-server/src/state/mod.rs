@@ -1,11 +1,12 @@
 pub mod env;
 
 use anyhow::{Ok, Result};
-use api_interface::{DatabaseCredentials, RedisConfig};
+use api_interface::{DatabaseCredentials, RedisConfig, SessionPolicy};
 use metrics_exporter_prometheus::PrometheusHandle;
+use time::Duration;
 use tracing::{error, instrument, warn};
 
-use crate::telemetry::metrics::setup_metrics_recorder;
+use crate::{cleanup::SessionCleanup, telemetry::metrics::setup_metrics_recorder};
 
 pub struct AppState {
     pub port: u16,
@@ -14,6 +15,7 @@ pub struct AppState {
     database_password: String,
     database_namespace: String,
     database_name: String,
+    database_migrate: bool,
     pub frontend_url: String,
     pub metrics_handle: PrometheusHandle,
     redis_dsn: String,
@@ -22,6 +24,9 @@ pub struct AppState {
     cache_ttl: u64,
     meilisearch_host: String,
     meilisearch_api_key: Option<String>,
+    session_policy: SessionPolicy,
+    account_token_key: Option<String>,
+    pub session_cleanup: SessionCleanup,
 }
 
 impl AppState {
@@ -62,6 +67,7 @@ impl AppState {
         let database_password = env::extract_variable(db_pass, "");
         let database_namespace = env::extract_variable(db_ns, "");
         let database_name = env::extract_variable(db_name, "");
+        let database_migrate = env::extract_variable("DATABASE_MIGRATE", "false");
         let frontend_url = env::extract_variable("FRONTEND_URL", "http://localhost:5173");
         let redis_dsn = env::extract_variable(redis_host, "redis://localhost:6379");
         let redis_clustered = env::extract_variable(redis_is_cluster, "false");
@@ -76,6 +82,40 @@ impl AppState {
             Some(meilisearch_api_key)
         };
 
+        let account_token_key = env::extract_variable("ACCOUNT_TOKEN_KEY", "");
+        let account_token_key = if account_token_key.is_empty() {
+            warn!("ACCOUNT_TOKEN_KEY is not set, account tokens can't be stored");
+            None
+        } else {
+            Some(account_token_key)
+        };
+
+        let defaults = SessionPolicy::default();
+        let session_policy = SessionPolicy {
+            idle_timeout: duration_variable("SESSION_IDLE_TIMEOUT_SECS", defaults.idle_timeout),
+            max_lifetime: duration_variable("SESSION_MAX_LIFETIME_SECS", defaults.max_lifetime),
+            refresh_threshold: duration_variable(
+                "SESSION_REFRESH_THRESHOLD_SECS",
+                defaults.refresh_threshold,
+            ),
+        };
+
+        let cleanup_batch_size = env::extract_variable("SESSION_CLEANUP_BATCH_SIZE", "500");
+        let session_cleanup = SessionCleanup {
+            interval: duration_variable("SESSION_CLEANUP_INTERVAL_SECS", Duration::minutes(15))
+                .unsigned_abs(),
+            jitter: duration_variable("SESSION_CLEANUP_JITTER_SECS", Duration::minutes(1))
+                .unsigned_abs(),
+            batch_size: cleanup_batch_size.parse().unwrap_or_else(|_| {
+                error!(
+                    val = cleanup_batch_size,
+                    default = 500,
+                    "session cleanup batch size invalid"
+                );
+                500
+            }),
+        };
+
         let metrics_handle = setup_metrics_recorder()?;
 
         Ok(AppState {
@@ -85,11 +125,18 @@ impl AppState {
             database_password,
             database_name,
             database_namespace,
+            database_migrate: database_migrate.parse().unwrap_or_else(|_| {
+                warn!("DATABASE_MIGRATE is not a boolean value");
+                false
+            }),
             frontend_url,
             metrics_handle,
             redis_dsn,
             meilisearch_host,
             meilisearch_api_key,
+            session_policy,
+            account_token_key,
+            session_cleanup,
             redis_clustered: redis_clustered.parse().unwrap_or_else(|_| {
                 warn!("REDIS_CLUSTER is not a boolean value");
                 false
@@ -109,13 +156,16 @@ impl AppState {
         })
     }
 
-    pub fn database_credentials(&self) -> DatabaseCredentials {
+    pub fn database_credentials(&self) -> DatabaseCredentials<'_> {
         DatabaseCredentials {
             db_dsn: &self.database_dsn,
             db_user: &self.database_username,
             db_pass: &self.database_password,
             db_ns: &self.database_namespace,
             db: &self.database_name,
+            migrate: self.database_migrate,
+            session_policy: self.session_policy,
+            account_token_key: self.account_token_key.as_deref(),
         }
     }
 
@@ -123,7 +173,7 @@ impl AppState {
         (&self.meilisearch_host, self.meilisearch_api_key.as_deref())
     }
 
-    pub fn redis_credentials(&self) -> RedisConfig {
+    pub fn redis_credentials(&self) -> RedisConfig<'_> {
         RedisConfig {
             redis_dsn: &self.redis_dsn,
             clustered: self.redis_clustered,
@@ -132,3 +182,20 @@ impl AppState {
         }
     }
 }
+
+fn duration_variable(variable: &str, default: Duration) -> Duration {
+    let value = env::extract_variable(variable, &default.whole_seconds().to_string());
+
+    match value.parse() {
+        std::result::Result::Ok(seconds) => Duration::seconds(seconds),
+        Err(_) => {
+            error!(
+                var = variable,
+                val = value,
+                default = default.whole_seconds(),
+                "duration in seconds invalid"
+            );
+            default
+        }
+    }
+}
@@ -0,0 +1,113 @@
+use std::time::Duration;
+
+use anyhow::Result;
+use api_core::{
+    api::{MutateAccountProviders, MutateSessions, MutateUsers, QuerySessions, QueryUsers},
+    memory::MemoryStore,
+    reexports::uuid::Uuid,
+    AccountProvider, Role, Session, User, UserType,
+};
+use time::OffsetDateTime;
+
+use crate::cleanup::{self, SessionCleanup};
+
+async fn store_with_sessions(expired: usize) -> Result<(MemoryStore, User)> {
+    let store = MemoryStore::default();
+    store
+        .create_account_provider(&AccountProvider {
+            name: String::from("github"),
+            ..Default::default()
+        })
+        .await?;
+    let user = store
+        .create_user(&User {
+            id: Uuid::nil(),
+            username: String::from("cleanup"),
+            email: String::from("cleanup@email.com"),
+            name: None,
+            avatar: None,
+            user_type: UserType::Individual,
+            phone_number: None,
+            role: Role::User,
+            email_verified: None,
+            created: OffsetDateTime::now_utc(),
+            updated: OffsetDateTime::now_utc(),
+        })
+        .await?;
+
+    let now = OffsetDateTime::now_utc();
+    let expires = (0..expired)
+        .map(|_| now - time::Duration::HOUR)
+        .chain([now + time::Duration::HOUR]);
+    for (index, expires_at) in expires.enumerate() {
+        store
+            .create_session(&Session {
+                id: Uuid::nil(),
+                expires_at,
+                session_token: format!("cleanup_{index}"),
+                account_provider: AccountProvider {
+                    name: String::from("github"),
+                    ..Default::default()
+                },
+                user_id: user.id,
+                refresh_at: None,
+                user_agent: None,
+                ip_address: None,
+                device_label: None,
+                created: now,
+                last_seen_at: None,
+            })
+            .await?;
+    }
+
+    Ok((store, user))
+}
+
+#[tokio::test]
+async fn purge_keeps_live_sessions() -> Result<()> {
+    let (store, user) = store_with_sessions(5).await?;
+
+    // batches are repeated until one comes back short
+    assert_eq!(cleanup::purge(&store, 2).await, 5);
+    assert_eq!(cleanup::purge(&store, 2).await, 0);
+
+    let live = store
+        .get_session_and_user("cleanup_5")
+        .await?
+        .map(|(found, _)| found.id);
+    assert_eq!(live, Some(user.id));
+    assert_eq!(store.get_user_sessions(&user.id).await?.len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn spawned_cleanup_purges_expired_sessions() -> Result<()> {
+    let (store, user) = store_with_sessions(3).await?;
+
+    let disabled = SessionCleanup {
+        interval: Duration::ZERO,
+        jitter: Duration::ZERO,
+        batch_size: 2,
+    };
+    assert!(cleanup::spawn(store.clone(), disabled).is_none());
+
+    let handle = cleanup::spawn(
+        store.clone(),
+        SessionCleanup {
+            interval: Duration::from_millis(10),
+            ..disabled
+        },
+    )
+    .expect("cleanup to be spawned");
+
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    handle.abort();
+
+    // the job already removed every expired session
+    assert_eq!(store.purge_expired_sessions(10).await?, 0);
+    assert!(store.get_session_and_user("cleanup_3").await?.is_some());
+    assert_eq!(store.get_user_sessions(&user.id).await?.len(), 1);
+
+    Ok(())
+}
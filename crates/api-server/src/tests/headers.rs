@@ -0,0 +1,54 @@
+use anyhow::Result;
+use axum::{
+    body::Body,
+    http::{header, HeaderMap, HeaderValue, Method, Request},
+    routing::get,
+    Router,
+};
+use tower::ServiceExt;
+
+use crate::{cors_layer, redact_headers};
+
+#[test]
+fn credentials_are_redacted() {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        header::AUTHORIZATION,
+        HeaderValue::from_static("Bearer secret"),
+    );
+    headers.insert("x-api-key", HeaderValue::from_static("ak_secret"));
+    headers.insert(header::USER_AGENT, HeaderValue::from_static("curl"));
+
+    let redacted = redact_headers(&headers);
+    assert_eq!(redacted[header::AUTHORIZATION], "[redacted]");
+    assert_eq!(redacted["x-api-key"], "[redacted]");
+    assert_eq!(redacted[header::USER_AGENT], "curl");
+    assert!(!format!("{redacted:?}").contains("secret"));
+}
+
+#[tokio::test]
+async fn cors_allows_api_keys() -> Result<()> {
+    let router = Router::new()
+        .route("/", get(|| async { "" }))
+        .layer(cors_layer("http://localhost:3000")?);
+
+    let response = router
+        .oneshot(
+            Request::builder()
+                .uri("/")
+                .method(Method::OPTIONS)
+                .header(header::ORIGIN, "http://localhost:3000")
+                .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+                .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key")
+                .body(Body::empty())?,
+        )
+        .await?;
+
+    let allowed = response.headers()[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str()?;
+    assert!(allowed.split(',').any(|name| name.trim() == "x-api-key"));
+    assert!(allowed
+        .split(',')
+        .any(|name| name.trim() == "authorization"));
+
+    Ok(())
+}
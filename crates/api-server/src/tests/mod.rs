@@ -1,3 +1,6 @@
+mod cleanup;
+mod headers;
+
 use crate::{create_router, state::AppState};
 use anyhow::Result;
 use axum::{